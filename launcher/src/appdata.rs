@@ -0,0 +1,45 @@
+use crate::get_appdata_dir;
+
+use ct_lib::log;
+use ct_lib::system;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Returns the path of a file that is stored next to the `logging.txt` in the application data
+/// directory
+pub fn appdata_filepath(filename: &str) -> String {
+    system::path_join(&get_appdata_dir(), filename)
+}
+
+/// Reads the given json file from the application data directory. Returns the default value if
+/// there is no such file yet or if it is unreadable. `description` names the content in warnings.
+pub fn load_json_or_default<T: Default + DeserializeOwned>(filename: &str, description: &str) -> T {
+    let filepath = appdata_filepath(filename);
+    let content = match std::fs::read_to_string(&filepath) {
+        Ok(content) => content,
+        Err(_) => return T::default(),
+    };
+    serde_json::from_str(&content).unwrap_or_else(|error| {
+        log::warn!(
+            "Could not read {} from '{}' : {}",
+            description,
+            filepath,
+            error
+        );
+        T::default()
+    })
+}
+
+/// Writes the given value as json file into the application data directory
+pub fn save_json<T: Serialize>(filename: &str, description: &str, value: &T) -> Result<(), String> {
+    let filepath = appdata_filepath(filename);
+    serde_json::to_string_pretty(value)
+        .map_err(|error| error.to_string())
+        .and_then(|content| std::fs::write(&filepath, content).map_err(|error| error.to_string()))
+        .map_err(|error| {
+            format!(
+                "Could not write {} to '{}' : {}",
+                description, filepath, error
+            )
+        })
+}
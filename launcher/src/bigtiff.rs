@@ -0,0 +1,427 @@
+use crate::pixel_buffer::{ColorType, PixelBuffer};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use rayon::prelude::*;
+
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+};
+
+/// Width and height of the square tiles in pixels. Needs to be a multiple of 16.
+pub const TILE_SIZE: usize = 512;
+
+const TIFF_TYPE_ASCII: u16 = 2;
+const TIFF_TYPE_SHORT: u16 = 3;
+const TIFF_TYPE_LONG: u16 = 4;
+const TIFF_TYPE_RATIONAL: u16 = 5;
+const TIFF_TYPE_UNDEFINED: u16 = 7;
+const TIFF_TYPE_LONG8: u16 = 16;
+
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_BITS_PER_SAMPLE: u16 = 258;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC_INTERPRETATION: u16 = 262;
+const TAG_SAMPLES_PER_PIXEL: u16 = 277;
+const TAG_X_RESOLUTION: u16 = 282;
+const TAG_Y_RESOLUTION: u16 = 283;
+const TAG_PLANAR_CONFIGURATION: u16 = 284;
+const TAG_RESOLUTION_UNIT: u16 = 296;
+const TAG_SOFTWARE: u16 = 305;
+const TAG_COLOR_MAP: u16 = 320;
+const TAG_TILE_WIDTH: u16 = 322;
+const TAG_TILE_LENGTH: u16 = 323;
+const TAG_TILE_OFFSETS: u16 = 324;
+const TAG_TILE_BYTE_COUNTS: u16 = 325;
+const TAG_EXTRA_SAMPLES: u16 = 338;
+const TAG_ICC_PROFILE: u16 = 34675;
+
+const COMPRESSION_DEFLATE: u16 = 8;
+
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u64,
+    /// Big endian encoded values
+    data: Vec<u8>,
+}
+
+impl IfdEntry {
+    fn shorts(tag: u16, values: &[u16]) -> IfdEntry {
+        IfdEntry {
+            tag,
+            field_type: TIFF_TYPE_SHORT,
+            count: values.len() as u64,
+            data: values
+                .iter()
+                .flat_map(|value| value.to_be_bytes().to_vec())
+                .collect(),
+        }
+    }
+
+    fn long(tag: u16, value: u32) -> IfdEntry {
+        IfdEntry {
+            tag,
+            field_type: TIFF_TYPE_LONG,
+            count: 1,
+            data: value.to_be_bytes().to_vec(),
+        }
+    }
+
+    fn long8s(tag: u16, values: &[u64]) -> IfdEntry {
+        IfdEntry {
+            tag,
+            field_type: TIFF_TYPE_LONG8,
+            count: values.len() as u64,
+            data: values
+                .iter()
+                .flat_map(|value| value.to_be_bytes().to_vec())
+                .collect(),
+        }
+    }
+
+    fn rational(tag: u16, value: f64) -> IfdEntry {
+        let denominator = 1000u32;
+        let numerator = (value * denominator as f64).round() as u32;
+        let mut data = numerator.to_be_bytes().to_vec();
+        data.extend_from_slice(&denominator.to_be_bytes());
+        IfdEntry {
+            tag,
+            field_type: TIFF_TYPE_RATIONAL,
+            count: 1,
+            data,
+        }
+    }
+
+    fn ascii(tag: u16, text: &str) -> IfdEntry {
+        let mut data = text.as_bytes().to_vec();
+        data.push(0);
+        IfdEntry {
+            tag,
+            field_type: TIFF_TYPE_ASCII,
+            count: data.len() as u64,
+            data,
+        }
+    }
+
+    fn undefined(tag: u16, data: Vec<u8>) -> IfdEntry {
+        IfdEntry {
+            tag,
+            field_type: TIFF_TYPE_UNDEFINED,
+            count: data.len() as u64,
+            data,
+        }
+    }
+}
+
+/// Describes the image that is written by `write_tiled_bigtiff`. The pixels themselves are
+/// rendered on demand.
+pub struct BigTiffImageInfo<'a> {
+    pub width: usize,
+    pub height: usize,
+    /// Determines color type, bit depth and palette of the output
+    pub format: &'a PixelBuffer,
+    pub ppi: Option<f64>,
+    /// Zlib compressed ICC profile as found in a png `iCCP` chunk
+    pub icc_profile: Option<&'a [u8]>,
+}
+
+/// Writes a tiled and deflate compressed BigTIFF file. The pixels are requested row by row from
+/// `render_row` which gets the index of the first pixel of the row and the row buffer to fill.
+/// Only a single row of tiles is kept in memory at any time.
+///
+/// NOTE: We write big endian files so that 16 bit samples can be copied as they are
+pub fn write_tiled_bigtiff<RenderRow>(
+    filepath: &str,
+    info: &BigTiffImageInfo,
+    render_row: RenderRow,
+) -> Result<(), String>
+where
+    RenderRow: Fn(usize, &mut [u8]) + Sync,
+{
+    let io_error =
+        |error: std::io::Error| format!("Could not write tiff file '{}' : {}", filepath, error);
+
+    let file = File::create(filepath).map_err(io_error)?;
+    let mut writer = BufWriter::new(file);
+
+    // Header with a placeholder for the offset of the image file directory
+    writer.write_all(b"MM").map_err(io_error)?;
+    writer.write_all(&43u16.to_be_bytes()).map_err(io_error)?;
+    writer.write_all(&8u16.to_be_bytes()).map_err(io_error)?;
+    writer.write_all(&0u16.to_be_bytes()).map_err(io_error)?;
+    writer.write_all(&0u64.to_be_bytes()).map_err(io_error)?;
+    let mut position: u64 = 16;
+
+    let bytes_per_pixel = info.format.bytes_per_pixel();
+    let tiles_across = (info.width + TILE_SIZE - 1) / TILE_SIZE;
+    let tiles_down = (info.height + TILE_SIZE - 1) / TILE_SIZE;
+    let mut tile_offsets = Vec::with_capacity(tiles_across * tiles_down);
+    let mut tile_byte_counts = Vec::with_capacity(tiles_across * tiles_down);
+
+    let row_size = info.width * bytes_per_pixel;
+    let mut strip = vec![0u8; row_size * TILE_SIZE];
+    for tile_row in 0..tiles_down {
+        let first_row = tile_row * TILE_SIZE;
+        let strip_height = TILE_SIZE.min(info.height - first_row);
+        strip[..(strip_height * row_size)]
+            .par_chunks_mut(row_size)
+            .enumerate()
+            .for_each(|(row, row_buffer)| {
+                render_row((first_row + row) * info.width, row_buffer);
+            });
+
+        // NOTE: Tiles at the right and bottom edge are padded to the full tile size
+        let strip = &strip;
+        let compressed_tiles: Vec<std::io::Result<Vec<u8>>> = (0..tiles_across)
+            .into_par_iter()
+            .map(|tile_column| {
+                let tile_row_size = TILE_SIZE * bytes_per_pixel;
+                let first_column_byte = tile_column * tile_row_size;
+                let copy_size = tile_row_size.min(row_size - first_column_byte);
+                let mut tile = vec![0u8; tile_row_size * TILE_SIZE];
+                for row in 0..strip_height {
+                    let source_begin = row * row_size + first_column_byte;
+                    let dest_begin = row * tile_row_size;
+                    tile[dest_begin..(dest_begin + copy_size)]
+                        .copy_from_slice(&strip[source_begin..(source_begin + copy_size)]);
+                }
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+                encoder.write_all(&tile)?;
+                encoder.finish()
+            })
+            .collect();
+
+        for compressed_tile in compressed_tiles {
+            let compressed_tile = compressed_tile.map_err(io_error)?;
+            writer.write_all(&compressed_tile).map_err(io_error)?;
+            tile_offsets.push(position);
+            tile_byte_counts.push(compressed_tile.len() as u64);
+            position += compressed_tile.len() as u64;
+        }
+    }
+
+    let entries = create_ifd_entries(info, &tile_offsets, &tile_byte_counts)?;
+
+    // Values that do not fit into the entries themselves are written before the directory
+    let mut entry_value_fields = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        if entry.data.len() <= 8 {
+            let mut value_field = entry.data.clone();
+            value_field.resize(8, 0);
+            entry_value_fields.push(value_field);
+        } else {
+            if position % 2 == 1 {
+                writer.write_all(&[0]).map_err(io_error)?;
+                position += 1;
+            }
+            writer.write_all(&entry.data).map_err(io_error)?;
+            entry_value_fields.push(position.to_be_bytes().to_vec());
+            position += entry.data.len() as u64;
+        }
+    }
+
+    if position % 2 == 1 {
+        writer.write_all(&[0]).map_err(io_error)?;
+        position += 1;
+    }
+    let ifd_offset = position;
+    writer
+        .write_all(&(entries.len() as u64).to_be_bytes())
+        .map_err(io_error)?;
+    for (entry, value_field) in entries.iter().zip(entry_value_fields.iter()) {
+        writer
+            .write_all(&entry.tag.to_be_bytes())
+            .map_err(io_error)?;
+        writer
+            .write_all(&entry.field_type.to_be_bytes())
+            .map_err(io_error)?;
+        writer
+            .write_all(&entry.count.to_be_bytes())
+            .map_err(io_error)?;
+        writer.write_all(value_field).map_err(io_error)?;
+    }
+    // There is no next image file directory
+    writer.write_all(&0u64.to_be_bytes()).map_err(io_error)?;
+
+    writer.seek(SeekFrom::Start(8)).map_err(io_error)?;
+    writer
+        .write_all(&ifd_offset.to_be_bytes())
+        .map_err(io_error)?;
+    writer.flush().map_err(io_error)
+}
+
+/// Returns the entries of the image file directory sorted by tag
+fn create_ifd_entries(
+    info: &BigTiffImageInfo,
+    tile_offsets: &[u64],
+    tile_byte_counts: &[u64],
+) -> Result<Vec<IfdEntry>, String> {
+    let format = info.format;
+    let sample_count = format.color_type.sample_count();
+    let photometric_interpretation = match format.color_type {
+        ColorType::Gray | ColorType::GrayAlpha => 1,
+        ColorType::Rgb | ColorType::Rgba => 2,
+        ColorType::Indexed => 3,
+    };
+
+    let mut result = vec![
+        IfdEntry::long(TAG_IMAGE_WIDTH, info.width as u32),
+        IfdEntry::long(TAG_IMAGE_LENGTH, info.height as u32),
+        IfdEntry::shorts(
+            TAG_BITS_PER_SAMPLE,
+            &vec![format.bit_depth as u16; sample_count],
+        ),
+        IfdEntry::shorts(TAG_COMPRESSION, &[COMPRESSION_DEFLATE]),
+        IfdEntry::shorts(
+            TAG_PHOTOMETRIC_INTERPRETATION,
+            &[photometric_interpretation],
+        ),
+        IfdEntry::shorts(TAG_SAMPLES_PER_PIXEL, &[sample_count as u16]),
+        IfdEntry::shorts(TAG_PLANAR_CONFIGURATION, &[1]),
+        IfdEntry::ascii(
+            TAG_SOFTWARE,
+            &format!("Repeaty {}", env!("CARGO_PKG_VERSION")),
+        ),
+        IfdEntry::shorts(TAG_TILE_WIDTH, &[TILE_SIZE as u16]),
+        IfdEntry::shorts(TAG_TILE_LENGTH, &[TILE_SIZE as u16]),
+        IfdEntry::long8s(TAG_TILE_OFFSETS, tile_offsets),
+        IfdEntry::long8s(TAG_TILE_BYTE_COUNTS, tile_byte_counts),
+    ];
+
+    if let Some(ppi) = info.ppi {
+        result.push(IfdEntry::rational(TAG_X_RESOLUTION, ppi));
+        result.push(IfdEntry::rational(TAG_Y_RESOLUTION, ppi));
+        // Inch
+        result.push(IfdEntry::shorts(TAG_RESOLUTION_UNIT, &[2]));
+    }
+
+    if format.color_type == ColorType::Indexed {
+        // NOTE: The color map contains all red values followed by all green and blue values
+        //       with 16 bit each
+        let mut color_map = vec![0u16; 3 * 256];
+        for (index, color) in format.palette.chunks_exact(3).enumerate().take(256) {
+            for channel in 0..3 {
+                color_map[channel * 256 + index] = color[channel] as u16 * 257;
+            }
+        }
+        result.push(IfdEntry::shorts(TAG_COLOR_MAP, &color_map));
+    }
+
+    if format.color_type == ColorType::GrayAlpha || format.color_type == ColorType::Rgba {
+        // Unassociated alpha
+        result.push(IfdEntry::shorts(TAG_EXTRA_SAMPLES, &[2]));
+    }
+
+    if let Some(icc_profile) = info.icc_profile {
+        let mut decompressed = Vec::new();
+        ZlibDecoder::new(icc_profile)
+            .read_to_end(&mut decompressed)
+            .map_err(|error| format!("Could not decompress ICC profile : {}", error))?;
+        result.push(IfdEntry::undefined(TAG_ICC_PROFILE, decompressed));
+    }
+
+    result.sort_by_key(|entry| entry.tag);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u16(data: &[u8], offset: usize) -> u16 {
+        u16::from_be_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn read_u64(data: &[u8], offset: usize) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&data[offset..(offset + 8)]);
+        u64::from_be_bytes(bytes)
+    }
+
+    /// Returns the field type, count and value field of every entry of the first image file
+    /// directory
+    fn read_ifd_entries(tiff: &[u8]) -> Vec<(u16, u16, u64, Vec<u8>)> {
+        let ifd_offset = read_u64(tiff, 8) as usize;
+        let entry_count = read_u64(tiff, ifd_offset) as usize;
+        let entries = (0..entry_count)
+            .map(|index| {
+                let entry_offset = ifd_offset + 8 + 20 * index;
+                (
+                    read_u16(tiff, entry_offset),
+                    read_u16(tiff, entry_offset + 2),
+                    read_u64(tiff, entry_offset + 4),
+                    tiff[(entry_offset + 12)..(entry_offset + 20)].to_vec(),
+                )
+            })
+            .collect();
+        assert_eq!(read_u64(tiff, ifd_offset + 8 + 20 * entry_count), 0);
+        entries
+    }
+
+    #[test]
+    fn header_directory_and_tile_offsets() {
+        let filepath = std::env::temp_dir()
+            .join("repeaty_test_bigtiff.tif")
+            .to_string_lossy()
+            .to_string();
+        let format = PixelBuffer::new(1, 1, ColorType::Rgb, 8);
+        let info = BigTiffImageInfo {
+            width: TILE_SIZE + 88,
+            height: TILE_SIZE + 8,
+            format: &format,
+            ppi: Some(300.0),
+            icc_profile: None,
+        };
+        // Every pixel gets the number of its tile as red value
+        let width = info.width;
+        write_tiled_bigtiff(&filepath, &info, |start_index, row| {
+            let y = start_index / width;
+            for (x, pixel) in row.chunks_exact_mut(3).enumerate() {
+                pixel[0] = (2 * (y / TILE_SIZE) + x / TILE_SIZE) as u8;
+            }
+        })
+        .unwrap();
+        let tiff = std::fs::read(&filepath).unwrap();
+        std::fs::remove_file(&filepath).ok();
+
+        assert_eq!(&tiff[0..2], b"MM");
+        assert_eq!(read_u16(&tiff, 2), 43);
+        assert_eq!(read_u16(&tiff, 4), 8);
+        assert_eq!(read_u16(&tiff, 6), 0);
+
+        let entries = read_ifd_entries(&tiff);
+        let tags: Vec<u16> = entries.iter().map(|entry| entry.0).collect();
+        let mut sorted_tags = tags.clone();
+        sorted_tags.sort();
+        assert_eq!(tags, sorted_tags);
+
+        let entry = |tag: u16| entries.iter().find(|entry| entry.0 == tag).unwrap();
+        assert_eq!(
+            entry(TAG_IMAGE_WIDTH).3[0..4],
+            (info.width as u32).to_be_bytes()
+        );
+        assert_eq!(
+            entry(TAG_IMAGE_LENGTH).3[0..4],
+            (info.height as u32).to_be_bytes()
+        );
+
+        let tile_count = 4;
+        let (_, field_type, count, value_field) = entry(TAG_TILE_OFFSETS);
+        assert_eq!((*field_type, *count), (TIFF_TYPE_LONG8, tile_count));
+        let offsets_offset = read_u64(value_field, 0) as usize;
+        let (_, _, _, value_field) = entry(TAG_TILE_BYTE_COUNTS);
+        let byte_counts_offset = read_u64(value_field, 0) as usize;
+        for tile_index in 0..(tile_count as usize) {
+            let offset = read_u64(&tiff, offsets_offset + 8 * tile_index) as usize;
+            let byte_count = read_u64(&tiff, byte_counts_offset + 8 * tile_index) as usize;
+            let mut tile = Vec::new();
+            ZlibDecoder::new(&tiff[offset..(offset + byte_count)])
+                .read_to_end(&mut tile)
+                .unwrap();
+            assert_eq!(tile.len(), TILE_SIZE * TILE_SIZE * 3);
+            assert_eq!(tile[0], tile_index as u8);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::pixel_buffer::{ColorType, PixelBuffer};
+use crate::PngMetadataChunks;
+
+/// How the samples of an image relate to linear light
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    /// Neither an `sRGB` nor a `gAMA` chunk is present so samples are blended as they are
+    Identity,
+    Srgb,
+    /// The gamma of the `gAMA` chunk, i.e. `sample = linear ^ gamma`
+    Gamma(f64),
+}
+
+impl TransferFunction {
+    /// Like png decoders we let an `sRGB` chunk take precedence over a `gAMA` chunk
+    pub fn from_png_metadata(png_metadata: &PngMetadataChunks) -> TransferFunction {
+        if png_metadata.contains_key("sRGB") {
+            return TransferFunction::Srgb;
+        }
+        if let Some(chunk) = png_metadata.get("gAMA") {
+            if chunk.len() == 4 {
+                let gamma =
+                    u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64 / 100_000.0;
+                if gamma > 0.0 {
+                    return TransferFunction::Gamma(gamma);
+                }
+            }
+        }
+        TransferFunction::Identity
+    }
+
+    /// Expects and returns values between 0 and 1
+    fn linearized(self, sample: f64) -> f64 {
+        match self {
+            TransferFunction::Identity => sample,
+            TransferFunction::Srgb => {
+                if sample <= 0.04045 {
+                    sample / 12.92
+                } else {
+                    ((sample + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma(gamma) => sample.powf(1.0 / gamma),
+        }
+    }
+
+    /// Expects and returns values between 0 and 1
+    fn encoded(self, linear: f64) -> f64 {
+        match self {
+            TransferFunction::Identity => linear,
+            TransferFunction::Srgb => {
+                if linear <= 0.0031308 {
+                    linear * 12.92
+                } else {
+                    1.055 * linear.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma(gamma) => linear.powf(gamma),
+        }
+    }
+}
+
+/// Composites the image onto the given ground color and returns it as opaque RGB with the bit
+/// depth of the image. Blending happens in linear light according to the given transfer function.
+pub fn flattened(
+    image: &PixelBuffer,
+    ground_color: [u8; 3],
+    transfer: TransferFunction,
+) -> PixelBuffer {
+    let rgba = image.to_rgba();
+    let linear = |sample: u8| transfer.linearized(sample as f64 / 255.0);
+    let ground_linear = [
+        linear(ground_color[0]),
+        linear(ground_color[1]),
+        linear(ground_color[2]),
+    ];
+
+    let mut result = PixelBuffer::new(
+        rgba.width as u32,
+        rgba.height as u32,
+        ColorType::Rgb,
+        rgba.bit_depth,
+    );
+    let mut data = Vec::with_capacity(result.data.len());
+    for pixel in rgba.data.chunks_exact(rgba.bytes_per_pixel()) {
+        let alpha = rgba.normalized_sample(pixel, 3);
+        for (channel, &ground_channel_linear) in ground_linear.iter().enumerate() {
+            let linear = alpha * transfer.linearized(rgba.normalized_sample(pixel, channel))
+                + (1.0 - alpha) * ground_channel_linear;
+            result.push_normalized_sample(&mut data, transfer.encoded(linear.max(0.0).min(1.0)));
+        }
+    }
+    result.data = data;
+    result
+}
+
+/// A ground image that is repeated over the whole pattern. The pattern is composited onto it after
+/// its tiles are placed so that the ground continues across tile edges. Its own transparency is
+/// ignored.
+pub struct GroundLayer<'a> {
+    ground: &'a PixelBuffer,
+    transfer: TransferFunction,
+    pattern_pixel_width: i32,
+    /// Position of the top left pattern pixel in the repeated ground. This lets the ground
+    /// continue across the panels of a wallpaper.
+    origin_x: i32,
+    origin_y: i32,
+}
+
+impl<'a> GroundLayer<'a> {
+    /// Expects the ground in RGBA
+    pub fn new(
+        ground: &'a PixelBuffer,
+        transfer: TransferFunction,
+        pattern_pixel_width: i32,
+        origin_x: i32,
+        origin_y: i32,
+    ) -> GroundLayer<'a> {
+        assert!(ground.color_type == ColorType::Rgba);
+        GroundLayer {
+            ground,
+            transfer,
+            pattern_pixel_width,
+            origin_x,
+            origin_y,
+        }
+    }
+
+    /// Composites a run of RGBA pixels of the pattern in the given format onto the ground and
+    /// makes them opaque. `start_index` is the index of the first pixel in the whole pattern.
+    pub fn draw(&self, format: &PixelBuffer, output_pixels: &mut [u8], start_index: usize) {
+        debug_assert!(format.color_type == ColorType::Rgba);
+        let transfer = self.transfer;
+        let pattern_width = self.pattern_pixel_width as usize;
+        for (offset, pixel) in output_pixels
+            .chunks_exact_mut(format.bytes_per_pixel())
+            .enumerate()
+        {
+            let index = start_index + offset;
+            let x = (self.origin_x + (index % pattern_width) as i32).rem_euclid(self.ground.width);
+            let y = (self.origin_y + (index / pattern_width) as i32).rem_euclid(self.ground.height);
+            let ground_pixel = self.ground.pixel(x, y);
+
+            let alpha = format.normalized_sample(pixel, 3);
+            for channel in 0..3 {
+                let ground_linear =
+                    transfer.linearized(self.ground.normalized_sample(ground_pixel, channel));
+                let linear = alpha * transfer.linearized(format.normalized_sample(pixel, channel))
+                    + (1.0 - alpha) * ground_linear;
+                format.set_normalized_sample(
+                    pixel,
+                    channel,
+                    transfer.encoded(linear.max(0.0).min(1.0)),
+                );
+            }
+            format.set_normalized_sample(pixel, 3, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ground_layer_continues_across_runs_and_panels() {
+        let mut ground = PixelBuffer::new(3, 2, ColorType::Rgba, 8);
+        for (index, pixel) in ground.data.chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&[10 * index as u8, 0, 0, 255]);
+        }
+        let pattern_width = 4;
+        let pattern = PixelBuffer::new(pattern_width as u32, 3, ColorType::Rgba, 8);
+        let ground_layer =
+            GroundLayer::new(&ground, TransferFunction::Identity, pattern_width, 2, 1);
+
+        let mut whole = pattern.data.clone();
+        ground_layer.draw(&pattern, &mut whole, 0);
+        let mut split = pattern.data.clone();
+        let (first, second) = split.split_at_mut(5 * 4);
+        ground_layer.draw(&pattern, first, 0);
+        ground_layer.draw(&pattern, second, 5);
+        assert_eq!(whole, split);
+
+        for (index, pixel) in whole.chunks_exact(4).enumerate() {
+            let ground_x = (2 + index % 4) % 3;
+            let ground_y = (1 + index / 4) % 2;
+            assert_eq!(pixel, ground.pixel(ground_x as i32, ground_y as i32));
+        }
+    }
+}
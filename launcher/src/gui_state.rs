@@ -0,0 +1,37 @@
+use crate::appdata;
+use crate::{LengthUnit, PatternSettings};
+
+use ct_lib::log;
+use ct_lib::serde_derive::{Deserialize, Serialize};
+
+const GUI_STATE_FILENAME: &str = "gui_state.json";
+const RECENT_FILEPATHS_MAX_COUNT: usize = 10;
+
+/// Everything the GUI remembers between sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiState {
+    pub length_unit: LengthUnit,
+    /// Most recently used images and job files, newest first
+    pub recent_filepaths: Vec<String>,
+    pub settings: PatternSettings,
+}
+
+impl GuiState {
+    /// Returns the default state if there is no previously saved state or if it is unreadable
+    pub fn load() -> GuiState {
+        appdata::load_json_or_default(GUI_STATE_FILENAME, "GUI state")
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = appdata::save_json(GUI_STATE_FILENAME, "GUI state", self) {
+            log::warn!("{}", error);
+        }
+    }
+}
+
+pub fn add_recent_filepath(recent_filepaths: &mut Vec<String>, filepath: &str) {
+    recent_filepaths.retain(|recent_filepath| recent_filepath != filepath);
+    recent_filepaths.insert(0, filepath.to_string());
+    recent_filepaths.truncate(RECENT_FILEPATHS_MAX_COUNT);
+}
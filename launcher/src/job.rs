@@ -0,0 +1,291 @@
+use crate::{
+    add_pattern_pdf_page_from_file, batch_pdf_filepath, benchmark_png_encoder_for_file,
+    collect_image_filepaths, create_pattern_from_file, finish_batch_pdf, job_date_text,
+    pdf::PdfWriter, PatternSettings,
+};
+
+use ct_lib::log;
+use ct_lib::serde_derive::{Deserialize, Serialize};
+use ct_lib::system;
+
+use std::sync::Mutex;
+
+/// Describes a complete pattern run so that it can be repeated later with the exact same settings.
+/// Job files are read and written as TOML or JSON depending on their file extension.
+///
+/// Example:
+/// ```toml
+/// inputs = ["D:\\images\\example_image.png", "D:\\images\\folder_with_images"]
+///
+/// [settings]
+/// size_mode = "Dimension"
+/// repeat_x = 5.0
+/// repeat_y = 5.0
+/// dim_mm_x = 1000.0
+/// dim_mm_y = 1000.0
+/// aspect_lock = "AspectRatio"
+/// locked_aspect_ratio = 1.0
+/// keep_whole_repeats = false
+/// snap_policy = "Centered"
+/// tile_layout = "HalfDrop"
+/// bleed_mm = 3.0
+/// print_marks = true
+/// output_color_type = "Original"
+/// output_format = "PdfTilingPattern"
+/// pdf_single_file_for_batch = true
+/// svg_link_tile = false
+/// ppi_override = 300.0
+/// motif_layout = "AB/BA"
+/// motifs = ["D:\\images\\second_motif.png"]
+/// output_dir = "D:\\output"
+///
+/// [settings.metadata]
+/// copy_color_information = true
+/// copy_dpi = true
+///
+/// [settings.png_encoder]
+/// compression_level = "High"
+/// filter_mode = "Adaptive"
+/// chunk_size_kib = 256
+/// thread_count = 0
+///
+/// [settings.poster]
+/// paper_size = "A4"
+/// overlap_mm = 10.0
+///
+/// [settings.wallpaper]
+/// enabled = false
+/// roll_width_mm = 530.0
+/// roll_length_mm = 10050.0
+/// match_type = "Drop"
+///
+/// [settings.mural]
+/// enabled = false
+/// wall_width_mm = 4000.0
+/// wall_height_mm = 2600.0
+/// panel_width_mm = 1000.0
+/// overlap_mm = 20.0
+///
+/// [settings.gutter]
+/// horizontal = 5.0
+/// vertical = 5.0
+/// unit = "Millimeter"
+/// background_color = [255, 255, 255]
+///
+/// [settings.flatten]
+/// enabled = true
+/// background_color = [255, 255, 255]
+/// ground_image = "linen.png"
+///
+/// [settings.toss]
+/// enabled = false
+/// cell_width_mm = 200.0
+/// cell_height_mm = 200.0
+/// spacing_mm = 5.0
+/// rotate = true
+/// seed = 1
+///
+/// [settings.variation]
+/// enabled = false
+/// mirror = true
+/// rotate = false
+/// variants = ["D:\\images\\variant.png"]
+/// seed = 1
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatternJob {
+    /// Image files or directories containing image files
+    pub inputs: Vec<String>,
+    pub settings: PatternSettings,
+}
+
+fn is_json_filepath(filepath: &str) -> bool {
+    system::path_to_extension(filepath)
+        .to_lowercase()
+        .ends_with("json")
+}
+
+impl PatternJob {
+    pub fn load_from_file(job_filepath: &str) -> Result<PatternJob, String> {
+        let content = std::fs::read_to_string(job_filepath)
+            .map_err(|error| format!("Could not open job file '{}' : {}", job_filepath, error))?;
+
+        if is_json_filepath(job_filepath) {
+            serde_json::from_str(&content).map_err(|error| error.to_string())
+        } else {
+            toml::from_str(&content).map_err(|error| error.to_string())
+        }
+        .map_err(|error| format!("Could not read job file '{}' : {}", job_filepath, error))
+    }
+
+    pub fn save_to_file(&self, job_filepath: &str) -> Result<(), String> {
+        let content = if is_json_filepath(job_filepath) {
+            serde_json::to_string_pretty(self).map_err(|error| error.to_string())
+        } else {
+            toml::to_string_pretty(self).map_err(|error| error.to_string())
+        }
+        .map_err(|error| format!("Could not serialize job '{}' : {}", job_filepath, error))?;
+
+        std::fs::write(job_filepath, content)
+            .map_err(|error| format!("Could not write job file '{}' : {}", job_filepath, error))
+    }
+
+    /// Validates the settings and returns all images of the inputs
+    fn collect_valid_image_filepaths(&self) -> Result<Vec<String>, String> {
+        if !self.settings.is_valid() {
+            return Err("The job contains invalid repeat counts or dimensions".to_string());
+        }
+        if !self.settings.png_encoder.is_valid() {
+            return Err("The job contains invalid png encoder options".to_string());
+        }
+
+        let image_filepaths: Vec<String> = self
+            .inputs
+            .iter()
+            .flat_map(|input_path| collect_image_filepaths(input_path))
+            .collect();
+        if image_filepaths.is_empty() {
+            return Err("The job does not contain any input images".to_string());
+        }
+        Ok(image_filepaths)
+    }
+
+    /// Creates patterns for all inputs of the job. Returns an error if at least one input failed.
+    pub fn run(&self) -> Result<(), String> {
+        let image_filepaths = self.collect_valid_image_filepaths()?;
+        let batch_pdf_writer = if self.settings.writes_single_pdf_for_batch() {
+            let pdf_filepath = batch_pdf_filepath(&self.settings, &image_filepaths);
+            Some(Mutex::new(PdfWriter::create(&pdf_filepath)?))
+        } else {
+            None
+        };
+
+        let job_date = job_date_text();
+        let mut failure_count = 0;
+        for image_filepath in &image_filepaths {
+            let result = match &batch_pdf_writer {
+                Some(pdf_writer) => add_pattern_pdf_page_from_file(
+                    image_filepath,
+                    &self.settings,
+                    &job_date,
+                    pdf_writer,
+                ),
+                None => create_pattern_from_file(image_filepath, &self.settings, &job_date),
+            };
+            match result {
+                Ok(output_filepath) => {
+                    log::info!("Created pattern '{}'", output_filepath);
+                }
+                Err(error_message) => {
+                    log::error!(
+                        "Could not create pattern for '{}' : {}",
+                        image_filepath,
+                        error_message
+                    );
+                    failure_count += 1;
+                }
+            }
+        }
+
+        if let Some(pdf_writer) = batch_pdf_writer {
+            let pdf_filepath = finish_batch_pdf(pdf_writer)?;
+            log::info!("Wrote all patterns to '{}'", pdf_filepath);
+        }
+
+        log::info!(
+            "Finished job with {} successes and {} failures",
+            image_filepaths.len() - failure_count,
+            failure_count
+        );
+        if failure_count > 0 {
+            Err(format!(
+                "{} of {} images failed",
+                failure_count,
+                image_filepaths.len()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes the patterns of all inputs with different png encoder options and logs the timings
+    pub fn run_png_encoder_benchmark(&self) -> Result<(), String> {
+        for image_filepath in &self.collect_valid_image_filepaths()? {
+            benchmark_png_encoder_for_file(image_filepath, &self.settings)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AspectLock, OutputFormat, SizeMode, SnapPolicy, TileLayout, WallpaperMatch};
+
+    fn non_default_job() -> PatternJob {
+        let mut job = PatternJob::default();
+        job.inputs = vec!["images/a.png".to_string(), "images/folder".to_string()];
+        let settings = &mut job.settings;
+        settings.size_mode = SizeMode::Dimension;
+        settings.repeat_x = 2.5;
+        settings.repeat_y = 3.0;
+        settings.dim_mm_x = 1000.0;
+        settings.dim_mm_y = 750.5;
+        settings.aspect_lock = AspectLock::AspectRatio;
+        settings.locked_aspect_ratio = 0.75;
+        settings.keep_whole_repeats = true;
+        settings.snap_policy = SnapPolicy::Centered;
+        settings.tile_layout = TileLayout::HalfBrick;
+        settings.output_format = OutputFormat::Svg;
+        settings.ppi_override = Some(150.0);
+        settings.motif_layout = "AB/BA".to_string();
+        settings.motifs = vec!["images/b.png".to_string()];
+        settings.wallpaper.enabled = true;
+        settings.wallpaper.match_type = WallpaperMatch::Drop;
+        settings.gutter.horizontal = 4.0;
+        settings.gutter.background_color = Some([1, 2, 3]);
+        settings.toss.seed = 42;
+        settings.variation.variants = vec!["images/variant.png".to_string()];
+        job
+    }
+
+    fn save_and_load(name: &str, job: &PatternJob) -> PatternJob {
+        let filepath = std::env::temp_dir()
+            .join(format!("repeaty_test_{}", name))
+            .to_string_lossy()
+            .to_string();
+        job.save_to_file(&filepath).unwrap();
+        let result = PatternJob::load_from_file(&filepath);
+        std::fs::remove_file(&filepath).ok();
+        result.unwrap()
+    }
+
+    #[test]
+    fn job_survives_saving_and_loading() {
+        let job = non_default_job();
+        assert_ne!(job, PatternJob::default());
+        assert_eq!(save_and_load("job.toml", &job), job);
+        assert_eq!(save_and_load("job.json", &job), job);
+    }
+
+    #[test]
+    fn documented_example_is_a_valid_job() {
+        let source = include_str!("job.rs");
+        let start = source.find("/// ```toml\n").unwrap() + "/// ```toml\n".len();
+        let end = start + source[start..].find("/// ```").unwrap();
+        let example: String = source[start..end]
+            .lines()
+            .map(|line| format!("{}\n", line.trim_start_matches("///").trim_start()))
+            .collect();
+
+        let job: PatternJob = toml::from_str(&example).unwrap();
+        assert_eq!(job.inputs.len(), 2);
+        assert_eq!(job.settings.aspect_lock, AspectLock::AspectRatio);
+        assert_eq!(job.settings.snap_policy, SnapPolicy::Centered);
+        assert_eq!(job.settings.tile_layout, TileLayout::HalfDrop);
+        assert_eq!(job.settings.output_format, OutputFormat::PdfTilingPattern);
+        assert_eq!(job.settings.wallpaper.match_type, WallpaperMatch::Drop);
+        assert_eq!(job.settings.variation.seed, 1);
+    }
+}
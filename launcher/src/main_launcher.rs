@@ -1,18 +1,50 @@
 #![windows_subsystem = "windows"]
 
-use ct_lib::bitmap::*;
 use ct_lib::system;
 use ct_lib::system::PathHelper;
 
-use ct_lib::serde_derive::Deserialize;
+use ct_lib::serde_derive::{Deserialize, Serialize};
 
 use ct_lib::log;
 
 use rayon::prelude::*;
 
-use std::{collections::HashMap, fs::File};
+use std::{
+    collections::HashMap,
+    fs::File,
+    sync::{Arc, Mutex},
+};
 
+mod appdata;
+mod bigtiff;
+mod flatten;
+mod gui_state;
+mod job;
 mod main_launcher_info;
+mod motifs;
+mod panels;
+mod pdf;
+mod pixel_buffer;
+mod poster;
+mod preset;
+mod print_marks;
+mod svg;
+mod timing_history;
+mod toss;
+mod variation;
+
+use flatten::{GroundLayer, TransferFunction};
+use gui_state::GuiState;
+use job::PatternJob;
+use motifs::MotifLayout;
+use panels::PanelLayout;
+use pdf::{PageSize, PdfWriter};
+use pixel_buffer::{ColorType, OutputColorType, PixelBuffer};
+use poster::{PosterLayout, PosterSheet};
+use preset::Preset;
+use print_marks::{PrintMarks, RasterPrintMarks, PRINT_MARKS_MARGIN_MM};
+use timing_history::{PatternTimer, TimingHistory};
+use variation::TileVariation;
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Unit conversion
@@ -41,6 +73,74 @@ fn pixel_per_inch_in_pixel_per_millimeter(pixels_per_inch: f64) -> f64 {
     pixels_per_inch / inch_in_millimeter(1.0)
 }
 
+/// Example: 3725.0 -> "1h 2min"
+fn pretty_print_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 60 * 60 {
+        format!("{}min {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {}min", seconds / (60 * 60), (seconds / 60) % 60)
+    }
+}
+
+/// Example: 1536 -> "1.50 KiB"
+fn pretty_print_bytes(bytes: u64) -> String {
+    let units = ["bytes", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut value = bytes as f64;
+    let mut unit_index = 0;
+    while value >= 1024.0 && unit_index + 1 < units.len() {
+        value /= 1024.0;
+        unit_index += 1;
+    }
+    if unit_index == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.2} {}", value, units[unit_index])
+    }
+}
+
+/// The unit in which physical dimensions are shown and entered in the GUI. Internally we always
+/// work with millimeter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum LengthUnit {
+    Millimeter,
+    Centimeter,
+    Inch,
+}
+impl Default for LengthUnit {
+    fn default() -> Self {
+        LengthUnit::Millimeter
+    }
+}
+
+impl LengthUnit {
+    fn abbreviation(self) -> &'static str {
+        match self {
+            LengthUnit::Millimeter => "mm",
+            LengthUnit::Centimeter => "cm",
+            LengthUnit::Inch => "in",
+        }
+    }
+
+    fn from_millimeter(self, millimeter: f64) -> f64 {
+        match self {
+            LengthUnit::Millimeter => millimeter,
+            LengthUnit::Centimeter => millimeter / 10.0,
+            LengthUnit::Inch => millimeter_in_inch(millimeter),
+        }
+    }
+
+    fn to_millimeter(self, value: f64) -> f64 {
+        match self {
+            LengthUnit::Millimeter => value,
+            LengthUnit::Centimeter => value * 10.0,
+            LengthUnit::Inch => inch_in_millimeter(value),
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Paths
 
@@ -52,35 +152,242 @@ fn get_executable_dir() -> String {
     }
 }
 
+fn get_appdata_dir() -> String {
+    system::get_appdata_dir(
+        main_launcher_info::LAUNCHER_COMPANY_NAME,
+        main_launcher_info::LAUNCHER_SAVE_FOLDER_NAME,
+    )
+    .unwrap_or(get_executable_dir())
+}
+
 /// Example:
-/// exe path: "C:\bin\repeaty.exe"
+/// output_dir_root: "C:\bin"
 /// imagepath: "D:\images\example_image.png"
 /// output_dir_suffix: "__20x23__134x312mm"
 ///
 /// This returns:
-/// "C:\bin\example_image__20x23__134x312mm.png"
-fn get_image_output_filepath(image_filepath: &str, image_suffix: &str) -> String {
-    let output_dir_root = get_executable_dir();
+/// "C:\bin\example_image__20x23__134x312mm"
+fn get_image_output_filepath(
+    output_dir_root: &str,
+    image_filepath: &str,
+    image_suffix: &str,
+) -> String {
     let image_filename = system::path_to_filename_without_extension(image_filepath) + image_suffix;
     system::path_join(&output_dir_root, &image_filename)
 }
 
-// NOTE: THIS IS FOR INTERNAL TESTING
-#[cfg(debug_assertions)]
-fn get_image_filepath_from_commandline() -> Option<String> {
-    Some("examples/kers.png".to_string())
-    // None
+/// Returns the given path if it is a file or all png files that are contained in the given path
+/// (including its subdirectories) if it is a directory
+fn collect_image_filepaths(path: &str) -> Vec<String> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            log::warn!("Could not read path '{}' : {}", path, error);
+            return Vec::new();
+        }
+    };
+    if !metadata.is_dir() {
+        return vec![path.to_string()];
+    }
+
+    let mut result = Vec::new();
+    match std::fs::read_dir(path) {
+        Ok(entries) => {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let entry_path = entry.path();
+                let entry_filepath = entry_path.to_string_borrowed_or_panic();
+                if entry_path.is_dir() {
+                    result.extend(collect_image_filepaths(entry_filepath));
+                } else if system::path_to_extension(entry_filepath)
+                    .to_lowercase()
+                    .ends_with("png")
+                {
+                    result.push(entry_filepath.to_string());
+                }
+            }
+        }
+        Err(error) => log::warn!("Could not read directory '{}' : {}", path, error),
+    }
+    result.sort();
+    result
+}
+
+fn is_job_filepath(filepath: &str) -> bool {
+    let extension = system::path_to_extension(filepath).to_lowercase();
+    extension.ends_with("toml") || extension.ends_with("json")
 }
 
-#[cfg(not(debug_assertions))]
-fn get_image_filepath_from_commandline() -> Option<String> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        args.last().cloned()
-    } else {
-        // NOTE: The first argument is the executable path
-        None
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Commandline
+
+const COMMANDLINE_USAGE: &str = "Usage: repeaty [--job <jobfile>] [--preset <name>] [--nogui] \
+    [--benchmark-png] [--compression fast|default|high] [--filter adaptive|none|sub|up|average|paeth] \
+    [--chunk-size <KiB>] [--threads <count>] [images or directories]";
+
+/// Png encoder options given on the commandline. They take precedence over the options of the job
+/// or the last GUI session.
+#[derive(Debug, Default)]
+struct PngEncoderArguments {
+    compression_level: Option<PngCompressionLevel>,
+    filter_mode: Option<PngFilterMode>,
+    chunk_size_kib: Option<usize>,
+    thread_count: Option<usize>,
+}
+
+impl PngEncoderArguments {
+    fn apply_to(&self, options: &mut PngEncoderOptions) {
+        if let Some(compression_level) = self.compression_level {
+            options.compression_level = compression_level;
+        }
+        if let Some(filter_mode) = self.filter_mode {
+            options.filter_mode = filter_mode;
+        }
+        if let Some(chunk_size_kib) = self.chunk_size_kib {
+            options.chunk_size_kib = chunk_size_kib;
+        }
+        if let Some(thread_count) = self.thread_count {
+            options.thread_count = thread_count;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CommandlineArguments {
+    /// Images or directories that are loaded on startup
+    input_paths: Vec<String>,
+    /// Job description file that is loaded on startup
+    job_filepath: Option<String>,
+    /// Applied to the settings of the job or the last GUI session
+    preset: Option<Preset>,
+    /// Runs the job without opening a window and exits afterwards
+    run_without_gui: bool,
+    /// Writes the patterns of the job with different png encoder options and logs the timings
+    /// instead of running the job normally
+    benchmark_png_encoder: bool,
+    png_encoder: PngEncoderArguments,
+}
+
+fn get_commandline_arguments() -> Vec<String> {
+    // NOTE: The first argument is the executable path
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // NOTE: THIS IS FOR INTERNAL TESTING
+    #[cfg(debug_assertions)]
+    {
+        if args.is_empty() {
+            return vec!["examples/kers.png".to_string()];
+        }
+    }
+
+    args
+}
+
+fn parse_commandline_arguments(args: &[String]) -> Result<CommandlineArguments, String> {
+    let mut result = CommandlineArguments::default();
+
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--job" => {
+                let job_filepath = args_iter
+                    .next()
+                    .ok_or_else(|| format!("Missing job filepath. {}", COMMANDLINE_USAGE))?;
+                result.job_filepath = Some(job_filepath.clone());
+            }
+            "--preset" => {
+                let name = args_iter.next().map(String::as_str).unwrap_or_default();
+                let preset = preset::find_preset(name).ok_or_else(|| {
+                    let preset_names: Vec<String> = preset::all_presets()
+                        .into_iter()
+                        .map(|preset| format!("'{}'", preset.name))
+                        .collect();
+                    format!(
+                        "Unknown preset '{}'. Available presets are {}. {}",
+                        name,
+                        preset_names.join(", "),
+                        COMMANDLINE_USAGE
+                    )
+                })?;
+                result.preset = Some(preset);
+            }
+            "--nogui" => result.run_without_gui = true,
+            "--benchmark-png" => result.benchmark_png_encoder = true,
+            "--compression" => {
+                let value = args_iter.next().map(String::as_str).unwrap_or_default();
+                let compression_level = match value {
+                    "fast" => PngCompressionLevel::Fast,
+                    "default" => PngCompressionLevel::Default,
+                    "high" => PngCompressionLevel::High,
+                    _ => {
+                        return Err(format!(
+                            "Invalid compression level '{}'. {}",
+                            value, COMMANDLINE_USAGE
+                        ))
+                    }
+                };
+                result.png_encoder.compression_level = Some(compression_level);
+            }
+            "--filter" => {
+                let value = args_iter.next().map(String::as_str).unwrap_or_default();
+                let filter_mode = match value {
+                    "adaptive" => PngFilterMode::Adaptive,
+                    "none" => PngFilterMode::None,
+                    "sub" => PngFilterMode::Sub,
+                    "up" => PngFilterMode::Up,
+                    "average" => PngFilterMode::Average,
+                    "paeth" => PngFilterMode::Paeth,
+                    _ => {
+                        return Err(format!(
+                            "Invalid filter mode '{}'. {}",
+                            value, COMMANDLINE_USAGE
+                        ))
+                    }
+                };
+                result.png_encoder.filter_mode = Some(filter_mode);
+            }
+            "--chunk-size" => {
+                let value = args_iter.next().map(String::as_str).unwrap_or_default();
+                let chunk_size_kib = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&chunk_size_kib| chunk_size_kib >= PNG_MIN_CHUNK_SIZE_KIB)
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid chunk size '{}', it needs to be at least {} KiB. {}",
+                            value, PNG_MIN_CHUNK_SIZE_KIB, COMMANDLINE_USAGE
+                        )
+                    })?;
+                result.png_encoder.chunk_size_kib = Some(chunk_size_kib);
+            }
+            "--threads" => {
+                let value = args_iter.next().map(String::as_str).unwrap_or_default();
+                let thread_count = value.parse::<usize>().map_err(|_| {
+                    format!("Invalid thread count '{}'. {}", value, COMMANDLINE_USAGE)
+                })?;
+                result.png_encoder.thread_count = Some(thread_count);
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("Unknown argument '{}'. {}", arg, COMMANDLINE_USAGE));
+            }
+            _ if is_job_filepath(arg) => result.job_filepath = Some(arg.clone()),
+            _ => result.input_paths.push(arg.clone()),
+        }
+    }
+
+    if result.benchmark_png_encoder && !result.run_without_gui {
+        return Err(format!(
+            "Benchmarking the png encoder requires '--nogui'. {}",
+            COMMANDLINE_USAGE
+        ));
+    }
+    if result.run_without_gui && result.job_filepath.is_none() {
+        return Err(format!(
+            "Running without GUI requires a job file. {}",
+            COMMANDLINE_USAGE
+        ));
     }
+
+    Ok(result)
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -151,33 +458,193 @@ fn png_extract_ancillary_chunks(image_filepath: &str) -> Result<PngMetadataChunk
     Ok(result)
 }
 
-fn load_bitmap(image_filepath: &str) -> Result<Bitmap, String> {
+/// Returns the zlib compressed ICC profile of the given png `iCCP` chunk. The compressed data can
+/// be embedded into pdf streams as is.
+fn icc_profile_from_iccp_chunk(iccp_chunk: &[u8]) -> Option<&[u8]> {
+    // NOTE: The chunk starts with a zero terminated profile name followed by the compression method
+    let name_end = iccp_chunk.iter().position(|&byte| byte == 0)?;
+    let compression_method = *iccp_chunk.get(name_end + 1)?;
+    if compression_method != 0 {
+        return None;
+    }
+    iccp_chunk.get((name_end + 2)..)
+}
+
+fn png_phys_chunk_from_ppi(ppi: f64) -> Vec<u8> {
+    let pixel_per_meter = ppi / meter_in_inch(1.0);
+    png_phys_chunk_from_pixel_per_meter(pixel_per_meter, pixel_per_meter)
+}
+
+fn png_phys_chunk_from_pixel_per_meter(pixel_per_meter_x: f64, pixel_per_meter_y: f64) -> Vec<u8> {
+    let mut result = Vec::with_capacity(9);
+    result.extend_from_slice(&(pixel_per_meter_x.round() as u32).to_be_bytes());
+    result.extend_from_slice(&(pixel_per_meter_y.round() as u32).to_be_bytes());
+    result.push(1); // Unit is meter
+    result
+}
+
+fn load_bitmap(image_filepath: &str) -> Result<PixelBuffer, String> {
     if system::path_to_extension(&image_filepath).ends_with("png") {
-        Bitmap::from_png_file(&image_filepath)
+        PixelBuffer::from_png_file(&image_filepath)
     } else {
         Err("We only support PNG images".to_string())
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum PngCompressionLevel {
+    Fast,
+    Default,
+    High,
+}
+impl Default for PngCompressionLevel {
+    fn default() -> Self {
+        PngCompressionLevel::Default
+    }
+}
+
+/// The filter that is applied to the image rows before compression. `Adaptive` picks the best
+/// filter for each row while the others use the same filter for all rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum PngFilterMode {
+    Adaptive,
+    None,
+    Sub,
+    Up,
+    Average,
+    Paeth,
+}
+impl Default for PngFilterMode {
+    fn default() -> Self {
+        PngFilterMode::Adaptive
+    }
+}
+
+/// The png encoder does not accept chunks smaller than this
+const PNG_MIN_CHUNK_SIZE_KIB: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+struct PngEncoderOptions {
+    compression_level: PngCompressionLevel,
+    filter_mode: PngFilterMode,
+    /// The image is split into chunks of this size that are compressed in parallel
+    chunk_size_kib: usize,
+    /// Uses one thread per CPU core if zero
+    thread_count: usize,
+}
+
+impl Default for PngEncoderOptions {
+    fn default() -> Self {
+        PngEncoderOptions {
+            compression_level: PngCompressionLevel::default(),
+            filter_mode: PngFilterMode::default(),
+            chunk_size_kib: 256,
+            thread_count: 0,
+        }
+    }
+}
+
+impl PngEncoderOptions {
+    fn is_valid(&self) -> bool {
+        self.chunk_size_kib >= PNG_MIN_CHUNK_SIZE_KIB
+    }
+
+    /// Example: "compression High, filter Adaptive, 256KiB chunks, all threads"
+    fn description(&self) -> String {
+        let threads = if self.thread_count == 0 {
+            "all threads".to_string()
+        } else {
+            format!("{} threads", self.thread_count)
+        };
+        format!(
+            "compression {:?}, filter {:?}, {}KiB chunks, {}",
+            self.compression_level, self.filter_mode, self.chunk_size_kib, threads
+        )
+    }
+
+    fn to_mtpng<'a>(
+        &self,
+        thread_pool: Option<&'a rayon::ThreadPool>,
+    ) -> Result<mtpng::encoder::Options<'a>, std::io::Error> {
+        let mut result = mtpng::encoder::Options::new();
+        result.set_compression_level(match self.compression_level {
+            PngCompressionLevel::Fast => mtpng::CompressionLevel::Fast,
+            PngCompressionLevel::Default => mtpng::CompressionLevel::Default,
+            PngCompressionLevel::High => mtpng::CompressionLevel::High,
+        })?;
+        result.set_filter_mode(match self.filter_mode {
+            PngFilterMode::Adaptive => mtpng::Mode::Adaptive,
+            PngFilterMode::None => mtpng::Mode::Fixed(mtpng::Filter::None),
+            PngFilterMode::Sub => mtpng::Mode::Fixed(mtpng::Filter::Sub),
+            PngFilterMode::Up => mtpng::Mode::Fixed(mtpng::Filter::Up),
+            PngFilterMode::Average => mtpng::Mode::Fixed(mtpng::Filter::Average),
+            PngFilterMode::Paeth => mtpng::Mode::Fixed(mtpng::Filter::Paeth),
+        })?;
+        result.set_chunk_size(self.chunk_size_kib * 1024)?;
+        if let Some(thread_pool) = thread_pool {
+            result.set_thread_pool(thread_pool)?;
+        }
+        Ok(result)
+    }
+}
+
 fn encode_png(
-    image: &Bitmap,
+    image: &PixelBuffer,
     output_filepath: &str,
     additional_chunks: &PngMetadataChunks,
+    encoder_options: &PngEncoderOptions,
 ) -> Result<(), std::io::Error> {
     let file = File::create(output_filepath)?;
-    let options = mtpng::encoder::Options::default();
-    let mut encoder = mtpng::encoder::Encoder::new(file, &options);
+    encode_png_to_writer(image, file, additional_chunks, encoder_options)
+}
+
+fn encode_png_to_writer<Writer: std::io::Write>(
+    image: &PixelBuffer,
+    writer: Writer,
+    additional_chunks: &PngMetadataChunks,
+    encoder_options: &PngEncoderOptions,
+) -> Result<(), std::io::Error> {
+    // NOTE: Without a dedicated thread pool the encoder uses the global one
+    let thread_pool = if encoder_options.thread_count > 0 {
+        Some(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(encoder_options.thread_count)
+                .build()
+                .map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error))?,
+        )
+    } else {
+        None
+    };
+    let options = encoder_options.to_mtpng(thread_pool.as_ref())?;
+
+    let mut encoder = mtpng::encoder::Encoder::new(writer, &options);
+
+    // NOTE: Images loaded from 1, 2 or 4 bit png files are written with their original bit depth
+    let packed = image.packed_to_source_bit_depth();
+    let (bit_depth, data, transparency) = match &packed {
+        Some(packed) => (packed.bit_depth, &packed.data, &packed.transparency),
+        None => (image.bit_depth, &image.data, &image.transparency),
+    };
 
     let mut header = mtpng::Header::new();
     header.set_size(image.width as u32, image.height as u32)?;
-    header.set_color(mtpng::ColorType::TruecolorAlpha, 8)?;
+    header.set_color(image.color_type.to_mtpng(), bit_depth)?;
     encoder.write_header(&header)?;
 
     for (chunktype, chunk) in additional_chunks {
         encoder.write_chunk(chunktype.as_bytes(), chunk)?;
     }
 
-    encoder.write_image_rows(image.as_bytes())?;
+    // NOTE: The palette and transparency chunks need to come after the color information chunks
+    if !image.palette.is_empty() {
+        encoder.write_palette(&image.palette)?;
+    }
+    if let Some(transparency) = transparency {
+        encoder.write_transparency(transparency)?;
+    }
+
+    encoder.write_image_rows(data)?;
     encoder.finish()?;
 
     Ok(())
@@ -229,66 +696,362 @@ fn get_ppi_from_png_metadata(
     }
 }
 
-fn create_pattern_png(
-    png_output_filepath: &str,
-    image: &Bitmap,
-    png_metadata: &PngMetadataChunks,
-    result_pixel_width: i32,
-    result_pixel_height: i32,
-) -> Result<(), String> {
-    let mut result_image = Bitmap::new(result_pixel_width as u32, result_pixel_height as u32);
+/// Describes where the input image is placed on the output image. Every tile occupies a cell that
+/// consists of the input image followed by the gutter to its right and below it.
+#[derive(Debug, Clone, Copy, Default)]
+struct TilePlacement {
+    layout: TileLayout,
+    /// Position inside the cell that is shown at the top left corner of the output image.
+    /// This can exceed the cell size by one cell for the offset layouts.
+    offset_x: i32,
+    offset_y: i32,
+    gutter_x: i32,
+    gutter_y: i32,
+    /// The gutter is transparent if no color is given
+    background_color: Option<[u8; 3]>,
+    /// Number of columns and rows that were dropped when reducing the offsets. This keeps the
+    /// tile variation attached to the same tiles when the placement is shifted.
+    column_shift: i32,
+    row_shift: i32,
+    variation: TileVariation,
+}
 
-    {
-        let _timer = ct_lib::TimerScoped::new_scoped("Compositing", true);
+impl TilePlacement {
+    /// The cell size is the input image size plus the gutter
+    fn new(
+        layout: TileLayout,
+        snap_policy: SnapPolicy,
+        cell_width: i32,
+        cell_height: i32,
+        output_width: i32,
+        output_height: i32,
+    ) -> TilePlacement {
+        match snap_policy {
+            SnapPolicy::Off | SnapPolicy::WholeRepeats => TilePlacement {
+                layout,
+                ..TilePlacement::default()
+            },
+            SnapPolicy::Centered => {
+                // We split the partial tile evenly between both edges. The first complete tile
+                // then starts at half the remainder.
+                fn centered_offset(output_size: i32, tile_size: i32) -> i32 {
+                    let remainder = output_size % tile_size;
+                    (tile_size - remainder / 2) % tile_size
+                }
+                TilePlacement {
+                    layout,
+                    offset_x: centered_offset(output_width, cell_width),
+                    offset_y: centered_offset(output_height, cell_height),
+                    ..TilePlacement::default()
+                }
+            }
+        }
+    }
 
-        fn copy_pixels_tiled(
-            input_image: &Bitmap,
-            output_image_width: i32,
-            output_image_buffer: &mut [PixelRGBA],
-            start_index: usize,
-        ) {
-            for index in 0..output_image_buffer.len() {
-                let output_x = (index + start_index) % output_image_width as usize;
-                let output_y = (index + start_index) / output_image_width as usize;
+    /// Leaves the given gutter between neighboring tiles
+    fn with_gutter(self, gutter: &GutterOptions, pixel_per_mm: f64) -> TilePlacement {
+        let (gutter_x, gutter_y) = gutter.pixels(pixel_per_mm);
+        TilePlacement {
+            gutter_x,
+            gutter_y,
+            background_color: gutter.background_color,
+            ..self
+        }
+    }
+
+    /// Picks a variant and transform for every tile
+    fn with_variation(self, variation: TileVariation) -> TilePlacement {
+        TilePlacement { variation, ..self }
+    }
+
+    /// Moves the placement so that the output image starts at the given position of the previous
+    /// output image
+    fn shifted(
+        self,
+        shift_x: i32,
+        shift_y: i32,
+        input_width: i32,
+        input_height: i32,
+    ) -> TilePlacement {
+        // NOTE: The offset layouts only repeat after two tiles
+        let period_x = 2 * (input_width + self.gutter_x);
+        let period_y = 2 * (input_height + self.gutter_y);
+        let offset_x = self.offset_x + shift_x;
+        let offset_y = self.offset_y + shift_y;
+        TilePlacement {
+            offset_x: offset_x.rem_euclid(period_x),
+            offset_y: offset_y.rem_euclid(period_y),
+            column_shift: self.column_shift + 2 * offset_x.div_euclid(period_x),
+            row_shift: self.row_shift + 2 * offset_y.div_euclid(period_y),
+            ..self
+        }
+    }
+
+    /// A grid placement that shows exactly one cell for output formats that repeat the tile
+    /// themselves
+    fn single_cell(self) -> TilePlacement {
+        TilePlacement {
+            layout: TileLayout::Grid,
+            offset_x: 0,
+            offset_y: 0,
+            column_shift: 0,
+            row_shift: 0,
+            variation: TileVariation::default(),
+            ..self
+        }
+    }
+
+    fn has_gutter(&self) -> bool {
+        self.gutter_x > 0 || self.gutter_y > 0
+    }
 
-                let input_x = output_x as i32 % input_image.width;
-                let input_y = output_y as i32 % input_image.height;
+    /// Moves the placement so that the repeat continues into a bleed of the given size around the
+    /// output image
+    fn with_bleed(self, bleed_pixels: i32, input_width: i32, input_height: i32) -> TilePlacement {
+        self.shifted(-bleed_pixels, -bleed_pixels, input_width, input_height)
+    }
+
+    /// Returns the position inside the input image that is shown at the given output position or
+    /// `None` if the output position lies in the gutter. With tile variation the position lies
+    /// inside the tile sheet.
+    fn input_position(
+        &self,
+        output_x: i32,
+        output_y: i32,
+        input_width: i32,
+        input_height: i32,
+    ) -> Option<(i32, i32)> {
+        let cell_width = input_width + self.gutter_x;
+        let cell_height = input_height + self.gutter_y;
+        let tiled_x = output_x + self.offset_x;
+        let tiled_y = output_y + self.offset_y;
+        let (column, row, cell_x, cell_y) = match self.layout {
+            TileLayout::Grid => (
+                tiled_x / cell_width,
+                tiled_y / cell_height,
+                tiled_x % cell_width,
+                tiled_y % cell_height,
+            ),
+            TileLayout::HalfDrop => {
+                // Every second column is moved down by half a cell
+                let column = tiled_x / cell_width;
+                let shifted_y = tiled_y + cell_height - (column % 2) * (cell_height / 2);
+                (
+                    column,
+                    shifted_y / cell_height,
+                    tiled_x % cell_width,
+                    shifted_y % cell_height,
+                )
+            }
+            TileLayout::HalfBrick => {
+                // Every second row is moved right by half a cell
+                let row = tiled_y / cell_height;
+                let shifted_x = tiled_x + cell_width - (row % 2) * (cell_width / 2);
+                (
+                    shifted_x / cell_width,
+                    row,
+                    shifted_x % cell_width,
+                    tiled_y % cell_height,
+                )
+            }
+        };
+        if cell_x < input_width && cell_y < input_height {
+            Some(self.variation.sheet_position(
+                column + self.column_shift,
+                row + self.row_shift,
+                cell_x,
+                cell_y,
+                input_width,
+                input_height,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Fills the given part of the output image with the tiled input image. `start_index` is the
+/// index of the first pixel of the buffer inside the output image. A gutter requires an input
+/// image that can show the background (see `tile_for_gutter_background`). With tile variation the
+/// input image is the tile sheet (see `variation::tile_sheet`).
+fn copy_pixels_tiled(
+    input_image: &PixelBuffer,
+    placement: &TilePlacement,
+    output_image_width: i32,
+    output_image_buffer: &mut [u8],
+    start_index: usize,
+) {
+    let bytes_per_pixel = input_image.bytes_per_pixel();
+    let tile_height = input_image.height / placement.variation.tile_count();
+    let background_pixel = if placement.has_gutter() {
+        input_image.background_pixel(placement.background_color)
+    } else {
+        Vec::new()
+    };
+    for (index, output_pixel) in output_image_buffer
+        .chunks_exact_mut(bytes_per_pixel)
+        .enumerate()
+    {
+        let output_x = (index + start_index) % output_image_width as usize;
+        let output_y = (index + start_index) / output_image_width as usize;
 
-                output_image_buffer[index] = input_image.get(input_x, input_y);
+        match placement.input_position(
+            output_x as i32,
+            output_y as i32,
+            input_image.width,
+            tile_height,
+        ) {
+            Some((input_x, input_y)) => {
+                output_pixel.copy_from_slice(input_image.pixel(input_x, input_y))
             }
+            None => output_pixel.copy_from_slice(&background_pixel),
         }
+    }
+}
+
+/// Composites the pattern. Print marks require an image without palette or transparent color key.
+/// A ground layer requires an RGBA image which stays RGBA but becomes opaque.
+fn create_pattern_bitmap(
+    image: &PixelBuffer,
+    result_pixel_width: i32,
+    result_pixel_height: i32,
+    placement: &TilePlacement,
+    ground: Option<&GroundLayer>,
+    print_marks: Option<&RasterPrintMarks>,
+) -> PixelBuffer {
+    let mut result_image =
+        image.new_with_same_format(result_pixel_width as u32, result_pixel_height as u32);
+    let (paper_pixel, ink_pixel) = if print_marks.is_some() {
+        (image.opaque_gray_pixel(255), image.opaque_gray_pixel(0))
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    {
+        let _timer = ct_lib::TimerScoped::new_scoped("Compositing", true);
 
+        // NOTE: The chunk size is given in pixels so that no pixel is split between two chunks
         let chunk_size = 4 * 1024 * 1024;
+        let bytes_per_pixel = result_image.bytes_per_pixel();
         let result_image_width = result_image.width;
         result_image
             .data
-            .par_chunks_mut(chunk_size)
+            .par_chunks_mut(chunk_size * bytes_per_pixel)
             .enumerate()
             .for_each(|(chunk_index, chunk)| {
                 let start_index = chunk_index * chunk_size;
-                copy_pixels_tiled(&image, result_image_width, chunk, start_index);
+                copy_pixels_tiled(&image, placement, result_image_width, chunk, start_index);
+                if let Some(ground) = ground {
+                    ground.draw(&image, chunk, start_index);
+                }
+                if let Some(print_marks) = print_marks {
+                    print_marks.draw(chunk, start_index, &paper_pixel, &ink_pixel);
+                }
             });
     }
 
-    {
-        let _timer = ct_lib::TimerScoped::new_scoped("Writing", true);
-        encode_png(&result_image, &png_output_filepath, &png_metadata).map_err(|error| {
-            format!(
-                "Could not write png file to '{}' : {}",
-                png_output_filepath, error
-            )
-        })
-    }
+    result_image
+}
+
+/// Composites one sheet of a poster. The part of the pattern that belongs to the sheet is
+/// surrounded by a paper colored border with the sheet marks. Requires an image without palette or
+/// transparent color key and an RGBA image for a ground layer.
+fn create_poster_sheet_bitmap(
+    image: &PixelBuffer,
+    geometry: &PatternGeometry,
+    ground: Option<&GroundLayer>,
+    pattern_print_marks: Option<&RasterPrintMarks>,
+    poster: &PosterLayout,
+    sheet: &PosterSheet,
+    sheet_marks: &RasterPrintMarks,
+) -> PixelBuffer {
+    let mut result_image = image.new_with_same_format(
+        poster.sheet_pixel_width as u32,
+        poster.sheet_pixel_height as u32,
+    );
+    let paper_pixel = image.opaque_gray_pixel(255);
+    let ink_pixel = image.opaque_gray_pixel(0);
+
+    let _timer = ct_lib::TimerScoped::new_scoped("Compositing sheet", true);
+
+    let bytes_per_pixel = result_image.bytes_per_pixel();
+    let sheet_width = result_image.width as usize;
+    let content_begin = poster.border_pixels as usize * bytes_per_pixel;
+    let content_end = content_begin + sheet.region_width as usize * bytes_per_pixel;
+    result_image
+        .data
+        .par_chunks_mut(sheet_width * bytes_per_pixel)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let region_y = y as i32 - poster.border_pixels;
+            if 0 <= region_y && region_y < sheet.region_height {
+                let pattern_y = (sheet.region_y + region_y) as usize;
+                let start_index =
+                    pattern_y * geometry.pixel_width as usize + sheet.region_x as usize;
+                let content = &mut row[content_begin..content_end];
+                copy_pixels_tiled(
+                    &image,
+                    &geometry.placement,
+                    geometry.pixel_width,
+                    content,
+                    start_index,
+                );
+                if let Some(ground) = ground {
+                    ground.draw(&image, content, start_index);
+                }
+                if let Some(print_marks) = pattern_print_marks {
+                    print_marks.draw(content, start_index, &paper_pixel, &ink_pixel);
+                }
+            }
+            sheet_marks.draw(row, y * sheet_width, &paper_pixel, &ink_pixel);
+        });
+
+    result_image
+}
+
+fn write_pattern_png(
+    png_output_filepath: &str,
+    pattern: &PixelBuffer,
+    png_metadata: &PngMetadataChunks,
+    encoder_options: &PngEncoderOptions,
+) -> Result<(), String> {
+    log::info!(
+        "Writing '{}' with {}",
+        png_output_filepath,
+        encoder_options.description()
+    );
+    let _timer = ct_lib::TimerScoped::new_scoped("Writing", true);
+    encode_png(
+        &pattern,
+        &png_output_filepath,
+        &png_metadata,
+        encoder_options,
+    )
+    .map_err(|error| {
+        format!(
+            "Could not write png file to '{}' : {}",
+            png_output_filepath, error
+        )
+    })
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Image
 
+#[derive(Debug, Clone)]
 struct InputImage {
     pub filepath: String,
-    pub bitmap: Bitmap,
+    pub bitmap: PixelBuffer,
+    /// Color type of the loaded png which the embedded ICC profile belongs to. The bitmap can have
+    /// another color type after combining or tossing motifs.
+    pub source_color_type: ColorType,
     pub png_metadata: PngMetadataChunks,
     pub ppi: Option<f64>,
+    pub ppi_from_metadata: Option<f64>,
+    /// Alternative tiles for the tile variation
+    pub variants: Vec<PixelBuffer>,
+    /// RGBA ground image that the pattern is flattened onto
+    pub ground: Option<PixelBuffer>,
 }
 
 impl InputImage {
@@ -298,522 +1061,5369 @@ impl InputImage {
         let ppi = get_ppi_from_png_metadata(&filepath, &png_metadata)?;
         Ok(InputImage {
             filepath: filepath.to_string(),
+            source_color_type: bitmap.color_type,
             bitmap,
             png_metadata,
             ppi,
+            ppi_from_metadata: ppi,
+            variants: Vec::new(),
+            ground: None,
         })
     }
 
-    fn width_height_pixel_per_mm(&self) -> (f64, f64, f64) {
-        let width = self.bitmap.width as f64;
-        let height = self.bitmap.height as f64;
-        let pixel_per_mm = pixel_per_inch_in_pixel_per_millimeter(self.ppi.unwrap_or(72.0));
-        (width, height, pixel_per_mm)
+    /// Combines this image as motif `A` with the other motifs of the settings into one tile
+    /// according to the motif layout or by tossing them over a repeat cell. Returns the image
+    /// unchanged if there is neither a motif layout nor a toss.
+    fn with_motifs(self, settings: &PatternSettings) -> Result<InputImage, String> {
+        if settings.toss.enabled {
+            return self.with_tossed_motifs(settings);
+        }
+        if settings.motif_layout.trim().is_empty() {
+            return Ok(self);
+        }
+        let layout = MotifLayout::parse(&settings.motif_layout)?;
+        let other_motif_count = layout.motif_count().saturating_sub(1);
+        if settings.motifs.len() < other_motif_count {
+            return Err(format!(
+                "The motif layout '{}' needs {} motifs but only {} are given",
+                settings.motif_layout,
+                layout.motif_count(),
+                settings.motifs.len() + 1
+            ));
+        }
+
+        let mut motif_bitmaps = self.load_other_motifs(settings, other_motif_count, true)?;
+        motif_bitmaps.insert(0, self.bitmap);
+        let bitmap = motifs::combine_motifs(&motif_bitmaps, &layout).map_err(|error| {
+            format!(
+                "Could not combine motifs of '{}' : {}",
+                self.filepath, error
+            )
+        })?;
+        Ok(InputImage { bitmap, ..self })
     }
 
-    fn output_image_pixel_width_height_filepath(
-        &self,
-        repeat_x: f64,
-        repeat_y: f64,
-        dim_mm_x: f64,
-        dim_mm_y: f64,
-    ) -> (i32, i32, String) {
-        let suffix_text = format!(
-            "__{}x{}__{}x{}mm",
-            pretty_print_float(repeat_x),
-            pretty_print_float(repeat_y),
-            pretty_print_float(dim_mm_x),
-            pretty_print_float(dim_mm_y)
+    /// Scatters this image and all other motifs of the settings over a repeat cell which then
+    /// replaces the image. Motifs may have different sizes.
+    fn with_tossed_motifs(self, settings: &PatternSettings) -> Result<InputImage, String> {
+        let filepath = &self.filepath;
+        let error =
+            |reason: String| format!("Could not toss motifs of '{}' : {}", filepath, reason);
+        if settings.motifs.len() >= motifs::MAX_MOTIF_COUNT {
+            return Err(error(format!(
+                "At most {} motifs are supported but {} are given",
+                motifs::MAX_MOTIF_COUNT,
+                settings.motifs.len() + 1
+            )));
+        }
+        let mut motif_bitmaps = self.load_other_motifs(settings, settings.motifs.len(), false)?;
+        motif_bitmaps.insert(0, self.bitmap);
+
+        let pixel_per_mm = pixel_per_inch_in_pixel_per_millimeter(
+            settings
+                .ppi_override
+                .or(self.ppi_from_metadata)
+                .unwrap_or(72.0),
         );
-        let png_output_filepath = get_image_output_filepath(&self.filepath, &suffix_text) + ".png";
-        (
-            (repeat_x * self.bitmap.width as f64).round() as i32,
-            (repeat_y * self.bitmap.height as f64).round() as i32,
-            png_output_filepath,
+        let cell_width = (settings.toss.cell_width_mm * pixel_per_mm)
+            .round()
+            .max(1.0);
+        let cell_height = (settings.toss.cell_height_mm * pixel_per_mm)
+            .round()
+            .max(1.0);
+        // NOTE: Keeping the bounding circles apart prevents overlaps for any rotation
+        let max_motif_diagonal = motif_bitmaps
+            .iter()
+            .map(|motif| (motif.width as f64).hypot(motif.height as f64))
+            .fold(0.0, f64::max);
+        let min_distance = max_motif_diagonal + settings.toss.spacing_mm * pixel_per_mm;
+        if cell_width < min_distance || cell_height < min_distance {
+            return Err(error(format!(
+                "The cell of {}x{}mm is smaller than the largest motif plus spacing of {}mm",
+                pretty_print_float(settings.toss.cell_width_mm),
+                pretty_print_float(settings.toss.cell_height_mm),
+                pretty_print_float(min_distance / pixel_per_mm)
+            )));
+        }
+
+        if cell_width > OUTPUT_MAX_DIMENSION || cell_height > OUTPUT_MAX_DIMENSION {
+            return Err(error(format!(
+                "The cell would be {:.0}x{:.0} pixels but at most {:.0} pixels per side are \
+                 supported",
+                cell_width, cell_height, OUTPUT_MAX_DIMENSION
+            )));
+        }
+        // NOTE: The motifs are converted to RGBA with the bit depth of the cell for rendering
+        let bytes_per_pixel = 4 * toss::cell_bit_depth(&motif_bitmaps) as usize / 8;
+        let motifs_memory_bytes: u64 = motif_bitmaps
+            .iter()
+            .map(|motif| uncompressed_size_bytes(motif.width, motif.height, bytes_per_pixel))
+            .sum();
+        let cell_memory_bytes =
+            uncompressed_size_bytes(cell_width as i32, cell_height as i32, bytes_per_pixel);
+        check_available_memory(
+            &format!("The toss cell for '{}'", filepath),
+            motifs_memory_bytes.saturating_add(cell_memory_bytes),
+        )?;
+
+        let motif_sizes: Vec<(i32, i32)> = motif_bitmaps
+            .iter()
+            .map(|motif| (motif.width, motif.height))
+            .collect();
+        let tossed_motifs = toss::toss_motifs(
+            cell_width,
+            cell_height,
+            min_distance,
+            &motif_sizes,
+            settings.toss.rotate,
+            settings.toss.seed,
+        );
+        let bitmap = toss::render_toss_cell(
+            &motif_bitmaps,
+            cell_width as i32,
+            cell_height as i32,
+            &tossed_motifs,
         )
+        .map_err(error)?;
+        Ok(InputImage { bitmap, ..self })
     }
-}
-
-////////////////////////////////////////////////////////////////////////////////////////////////////
-// GUI
 
-use iced::{
-    button, text_input, Align, Application, Button, Column, Command, Element, Length::FillPortion,
-    Row, Settings, Subscription, Text, TextInput,
-};
+    /// Loads the first `count` motifs after motif `A` from the settings. They need the same DPI
+    /// as this image unless there is a DPI override.
+    fn load_other_motifs(
+        &self,
+        settings: &PatternSettings,
+        count: usize,
+        needs_same_size: bool,
+    ) -> Result<Vec<PixelBuffer>, String> {
+        let mut result = Vec::with_capacity(count);
+        for (index, motif_filepath) in settings.motifs[..count].iter().enumerate() {
+            let motif = InputImage::new(motif_filepath)?;
+            if needs_same_size
+                && (motif.bitmap.width != self.bitmap.width
+                    || motif.bitmap.height != self.bitmap.height)
+            {
+                return Err(format!(
+                    "Motif {} '{}' is {}x{} pixels but motif A '{}' is {}x{} pixels",
+                    motifs::motif_name(index + 1),
+                    motif_filepath,
+                    motif.bitmap.width,
+                    motif.bitmap.height,
+                    self.filepath,
+                    self.bitmap.width,
+                    self.bitmap.height
+                ));
+            }
+            // NOTE: An override replaces the DPI of all motifs so they do not need to match
+            if !self.has_same_ppi(&motif) && settings.ppi_override.is_none() {
+                return Err(format!(
+                    "Motif {} '{}' has {} DPI but motif A '{}' has {} DPI",
+                    motifs::motif_name(index + 1),
+                    motif_filepath,
+                    motif
+                        .ppi_from_metadata
+                        .map(pretty_print_float)
+                        .unwrap_or_else(|| "no".to_string()),
+                    self.filepath,
+                    self.ppi_from_metadata
+                        .map(pretty_print_float)
+                        .unwrap_or_else(|| "no".to_string())
+                ));
+            }
+            if let Some(chunk_name) = self.differing_color_chunk(&motif) {
+                return Err(format!(
+                    "Motif {} '{}' has another {} chunk than motif A '{}'",
+                    motifs::motif_name(index + 1),
+                    motif_filepath,
+                    chunk_name,
+                    self.filepath
+                ));
+            }
+            result.push(motif.bitmap);
+        }
+        Ok(result)
+    }
 
-const LABEL_SIZE_DEFAULT: u16 = 20;
-const LABEL_SIZE_INVALID: u16 = 25;
-const COLOR_DEFAULT: iced::Color = iced::Color::BLACK;
-const COLOR_INVALID: iced::Color = iced::Color::from_rgb(1.0, 0.0, 0.0);
-const DEFAULT_PPI: f64 = 72.0;
+    /// Loads the variant tiles of the tile variation. Does nothing if the variation is disabled.
+    fn with_variants(self, settings: &PatternSettings) -> Result<InputImage, String> {
+        if !settings.variation.enabled {
+            return Ok(self);
+        }
+        let mut variants = Vec::with_capacity(settings.variation.variants.len());
+        for variant_filepath in &settings.variation.variants {
+            let variant = InputImage::new(variant_filepath)?;
+            if variant.bitmap.width != self.bitmap.width
+                || variant.bitmap.height != self.bitmap.height
+            {
+                return Err(format!(
+                    "Variant '{}' is {}x{} pixels but the tile of '{}' is {}x{} pixels",
+                    variant_filepath,
+                    variant.bitmap.width,
+                    variant.bitmap.height,
+                    self.filepath,
+                    self.bitmap.width,
+                    self.bitmap.height
+                ));
+            }
+            if variant.bitmap.bit_depth != self.bitmap.bit_depth {
+                return Err(format!(
+                    "Variant '{}' has {} bit samples but '{}' has {} bit samples",
+                    variant_filepath,
+                    variant.bitmap.bit_depth,
+                    self.filepath,
+                    self.bitmap.bit_depth
+                ));
+            }
+            if !self.has_same_ppi(&variant) && settings.ppi_override.is_none() {
+                return Err(format!(
+                    "Variant '{}' has {} DPI but '{}' has {} DPI",
+                    variant_filepath,
+                    variant
+                        .ppi_from_metadata
+                        .map(pretty_print_float)
+                        .unwrap_or_else(|| "no".to_string()),
+                    self.filepath,
+                    self.ppi_from_metadata
+                        .map(pretty_print_float)
+                        .unwrap_or_else(|| "no".to_string())
+                ));
+            }
+            if let Some(chunk_name) = self.differing_color_chunk(&variant) {
+                return Err(format!(
+                    "Variant '{}' has another {} chunk than '{}'",
+                    variant_filepath, chunk_name, self.filepath
+                ));
+            }
+            variants.push(variant.bitmap);
+        }
+        Ok(InputImage { variants, ..self })
+    }
 
-#[derive(Debug, Clone)]
-enum GuiEvent {
-    ChangedRepeatCountX(String),
-    ChangedRepeatCountY(String),
-    ChangedDimensionMillimeterX(String),
-    ChangedDimensionMillimeterY(String),
-    PressedStartButton,
-    WindowEvent(iced_native::Event),
-}
+    /// Loads the ground image of the flatten options. Does nothing if the pattern is not flattened
+    /// onto a ground image.
+    fn with_ground(self, settings: &PatternSettings) -> Result<InputImage, String> {
+        let ground_filepath = match &settings.flatten.ground_image {
+            Some(ground_filepath) if settings.flatten.enabled => ground_filepath,
+            _ => {
+                return Ok(InputImage {
+                    ground: None,
+                    ..self
+                })
+            }
+        };
+        let ground = load_bitmap(ground_filepath)?
+            .converted(OutputColorType::Rgba)
+            .map_err(|error| format!("Could not convert '{}' : {}", ground_filepath, error))?;
+        Ok(InputImage {
+            ground: Some(ground),
+            ..self
+        })
+    }
 
-enum ProcessState {
-    Idle,
-    Running,
-    Finished,
-}
-impl Default for ProcessState {
-    fn default() -> Self {
-        ProcessState::Idle
+    /// Returns the name of the first chunk that describes the colors differently in the other
+    /// image. Images that are shown together are written with the chunks of this image only.
+    fn differing_color_chunk(&self, other: &InputImage) -> Option<&'static str> {
+        ["gAMA", "sRGB", "iCCP"]
+            .iter()
+            .find(|&&chunk_name| {
+                self.png_metadata.get(chunk_name) != other.png_metadata.get(chunk_name)
+            })
+            .copied()
     }
-}
 
-#[derive(Default)]
-struct RepeatyGui {
-    image: Option<InputImage>,
+    fn has_same_ppi(&self, other: &InputImage) -> bool {
+        match (self.ppi_from_metadata, other.ppi_from_metadata) {
+            (Some(ppi), Some(other_ppi)) => (ppi - other_ppi).abs() < 0.01,
+            (None, None) => true,
+            _ => false,
+        }
+    }
 
-    repeat_x: f64,
-    repeat_y: f64,
+    /// Picks a variant and transform for every tile according to the variation settings
+    fn tile_variation(&self, variation: &VariationOptions) -> TileVariation {
+        if !variation.enabled {
+            return TileVariation::default();
+        }
+        TileVariation::new(
+            variation.seed,
+            1 + self.variants.len(),
+            variation.mirror,
+            variation.rotate,
+            self.bitmap.width == self.bitmap.height,
+        )
+    }
 
-    dim_mm_x: f64,
-    dim_mm_y: f64,
+    /// Uses the given DPI instead of the one stored in the image metadata. Passing `None` restores
+    /// the DPI from the image metadata.
+    fn set_ppi_override(&mut self, ppi_override: Option<f64>) {
+        self.ppi = ppi_override.or(self.ppi_from_metadata);
+    }
 
-    repeat_x_text: String,
-    repeat_y_text: String,
+    /// Returns the ancillary chunks that are written to the output image
+    fn output_png_metadata(&self, options: &MetadataOptions) -> PngMetadataChunks {
+        let mut result = PngMetadataChunks::new();
+        for (chunktype, chunk) in &self.png_metadata {
+            let copy_chunk = match chunktype.as_str() {
+                "cHRM" | "gAMA" | "iCCP" | "sRGB" => options.copy_color_information,
+                "pHYs" => options.copy_dpi,
+                _ => false,
+            };
+            if copy_chunk {
+                result.insert(chunktype.clone(), chunk.clone());
+            }
+        }
 
-    dim_mm_x_text: String,
-    dim_mm_y_text: String,
+        if options.copy_dpi && self.ppi != self.ppi_from_metadata {
+            if let Some(ppi) = self.ppi {
+                result.insert("pHYs".to_string(), png_phys_chunk_from_ppi(ppi));
+            }
+        }
 
-    start_button_widget: button::State,
+        result
+    }
 
-    repeat_x_widget: text_input::State,
-    repeat_y_widget: text_input::State,
+    fn pixel_per_mm(&self) -> f64 {
+        pixel_per_inch_in_pixel_per_millimeter(self.ppi.unwrap_or(72.0))
+    }
 
-    dim_mm_x_widget: text_input::State,
-    dim_mm_y_widget: text_input::State,
+    /// Returns the size of one repeat in pixels including the given gutter and the pixel density
+    fn width_height_pixel_per_mm(&self, gutter: &GutterOptions) -> (f64, f64, f64) {
+        let pixel_per_mm = self.pixel_per_mm();
+        let (gutter_x, gutter_y) = gutter.pixels(pixel_per_mm);
+        let width = (self.bitmap.width + gutter_x) as f64;
+        let height = (self.bitmap.height + gutter_y) as f64;
+        (width, height, pixel_per_mm)
+    }
 
-    process_state: ProcessState,
+    fn output_image_pixel_width_height_filepath(
+        &self,
+        output_dir: &str,
+        repeat_x: f64,
+        repeat_y: f64,
+        dim_mm_x: f64,
+        dim_mm_y: f64,
+        gutter: &GutterOptions,
+    ) -> (i32, i32, String) {
+        let suffix_text = format!(
+            "__{}x{}__{}x{}mm",
+            pretty_print_float(repeat_x),
+            pretty_print_float(repeat_y),
+            pretty_print_float(dim_mm_x),
+            pretty_print_float(dim_mm_y)
+        );
+        let output_filepath = get_image_output_filepath(output_dir, &self.filepath, &suffix_text);
+        let (cell_width, cell_height, _) = self.width_height_pixel_per_mm(gutter);
+        (
+            (repeat_x * cell_width).round() as i32,
+            (repeat_y * cell_height).round() as i32,
+            output_filepath,
+        )
+    }
+}
 
-    current_error: Option<String>,
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Pattern settings
+
+/// How the repeated tiles are arranged relative to each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum TileLayout {
+    Grid,
+    /// Every second column is moved down by half a tile height
+    HalfDrop,
+    /// Every second row is moved right by half a tile width
+    HalfBrick,
+}
+impl Default for TileLayout {
+    fn default() -> Self {
+        TileLayout::Grid
+    }
 }
 
-impl RepeatyGui {
-    fn new() -> RepeatyGui {
-        let mut result = RepeatyGui::default();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum GutterUnit {
+    Millimeter,
+    Pixel,
+}
+impl Default for GutterUnit {
+    fn default() -> Self {
+        GutterUnit::Millimeter
+    }
+}
 
-        if let Some(image_filepath) = get_image_filepath_from_commandline() {
-            result.load_image(&image_filepath);
+/// Space between neighboring tiles. It is part of the repeat so that the pattern stays seamless.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct GutterOptions {
+    horizontal: f64,
+    vertical: f64,
+    unit: GutterUnit,
+    /// The gutter is transparent if no color is given
+    background_color: Option<[u8; 3]>,
+}
+impl Default for GutterOptions {
+    fn default() -> Self {
+        GutterOptions {
+            horizontal: 0.0,
+            vertical: 0.0,
+            unit: GutterUnit::Millimeter,
+            background_color: None,
         }
+    }
+}
 
-        result
+impl GutterOptions {
+    fn is_valid(&self) -> bool {
+        self.horizontal >= 0.0 && self.vertical >= 0.0
     }
 
-    fn load_image(&mut self, image_filepath: &str) {
-        let image = {
-            let image_result = InputImage::new(&image_filepath);
-            if let Err(error_message) = InputImage::new(&image_filepath) {
-                self.current_error = Some(error_message);
-                return;
-            }
-            self.current_error = None;
-            image_result.unwrap()
+    /// Returns the horizontal and vertical gutter rounded to whole pixels
+    fn pixels(&self, pixel_per_mm: f64) -> (i32, i32) {
+        let pixel_per_unit = match self.unit {
+            GutterUnit::Millimeter => pixel_per_mm,
+            GutterUnit::Pixel => 1.0,
         };
+        (
+            (self.horizontal * pixel_per_unit).round().max(0.0) as i32,
+            (self.vertical * pixel_per_unit).round().max(0.0) as i32,
+        )
+    }
+}
 
-        self.image = Some(image);
-        self.process_state = ProcessState::Idle;
+/// Determines which of the values the user entered stays fixed when the same settings are applied
+/// to images with different dimensions or DPI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum SizeMode {
+    RepeatCount,
+    Dimension,
+}
+impl Default for SizeMode {
+    fn default() -> Self {
+        SizeMode::RepeatCount
+    }
+}
 
-        if self.repeat_x <= 0.0
-            || self.repeat_y <= 0.0
-            || self.dim_mm_x <= 0.0
-            || self.dim_mm_y <= 0.0
-            || self.repeat_x.is_nan()
-            || self.repeat_y.is_nan()
-            || self.dim_mm_x.is_nan()
-            || self.dim_mm_y.is_nan()
-        {
-            self.set_repeat_x(5.0);
-            self.set_repeat_y(5.0);
-            self.repeat_x_text = pretty_print_float(self.repeat_x);
-            self.repeat_y_text = pretty_print_float(self.repeat_y);
-        }
+/// Ties the vertical values to the horizontal values (and vice versa) when editing them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum AspectLock {
+    Off,
+    /// Uses the same repeat count for both axes
+    RepeatCount,
+    /// Keeps the ratio between the physical height and width
+    AspectRatio,
+}
+impl Default for AspectLock {
+    fn default() -> Self {
+        AspectLock::Off
     }
+}
 
-    fn set_repeat_x(&mut self, value: f64) {
-        if let Some(image) = &self.image {
-            let (input_width, _input_height, pixel_per_mm) = image.width_height_pixel_per_mm();
+/// Determines how we deal with partial tiles at the edges of the output image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum SnapPolicy {
+    /// Partial tiles are cut off at the right and bottom edges
+    Off,
+    /// Entered physical dimensions are rounded to the nearest whole repeat count
+    WholeRepeats,
+    /// Partial tiles are split symmetrically between opposite edges
+    Centered,
+}
+impl Default for SnapPolicy {
+    fn default() -> Self {
+        SnapPolicy::Off
+    }
+}
 
-            self.repeat_x = value;
-            self.dim_mm_x = self.repeat_x * input_width / pixel_per_mm;
-            self.dim_mm_x_text = pretty_print_float(self.dim_mm_x);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum OutputFormat {
+    Png,
+    /// A pdf page with the physical size of the pattern that contains the pattern image
+    Pdf,
+    /// A pdf page with the physical size of the pattern that contains the input image once and
+    /// repeats it with a pdf tiling pattern. This keeps the file small for large patterns.
+    PdfTilingPattern,
+    /// An svg with the physical size of the pattern that repeats the input image with a
+    /// `<pattern>` element
+    Svg,
+    /// A tiled BigTIFF file that is written without keeping the whole pattern in memory
+    BigTiff,
+}
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Png
+    }
+}
 
-            self.process_state = ProcessState::Idle;
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => ".png",
+            OutputFormat::Pdf | OutputFormat::PdfTilingPattern => ".pdf",
+            OutputFormat::Svg => ".svg",
+            OutputFormat::BigTiff => ".tif",
         }
     }
-    fn set_repeat_y(&mut self, value: f64) {
-        if let Some(image) = &self.image {
-            let (_input_width, input_height, pixel_per_mm) = image.width_height_pixel_per_mm();
-
-            self.repeat_y = value;
-            self.dim_mm_y = self.repeat_y * input_height / pixel_per_mm;
-            self.dim_mm_y_text = pretty_print_float(self.dim_mm_y);
 
-            self.process_state = ProcessState::Idle;
+    fn is_pdf(self) -> bool {
+        match self {
+            OutputFormat::Pdf | OutputFormat::PdfTilingPattern => true,
+            OutputFormat::Png | OutputFormat::Svg | OutputFormat::BigTiff => false,
         }
     }
-    fn set_dim_mm_x(&mut self, value: f64) {
-        if let Some(image) = &self.image {
-            let (input_width, _input_height, pixel_per_mm) = image.width_height_pixel_per_mm();
 
-            self.dim_mm_x = value;
-            self.repeat_x = self.dim_mm_x * pixel_per_mm / input_width;
-            self.repeat_x_text = pretty_print_float(self.repeat_x);
+    /// Formats that contain the tile only once and let the viewer repeat it
+    fn repeats_tile_itself(self) -> bool {
+        match self {
+            OutputFormat::PdfTilingPattern | OutputFormat::Svg => true,
+            OutputFormat::Png | OutputFormat::Pdf | OutputFormat::BigTiff => false,
+        }
+    }
+}
 
-            self.process_state = ProcessState::Idle;
+/// Determines which ancillary chunks of the input image are copied to the output image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct MetadataOptions {
+    /// ICC profile, chroma, gamma and sRGB information
+    copy_color_information: bool,
+    copy_dpi: bool,
+}
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        MetadataOptions {
+            copy_color_information: true,
+            copy_dpi: true,
         }
     }
-    fn set_dim_mm_y(&mut self, value: f64) {
-        if let Some(image) = &self.image {
-            let (_input_width, input_height, pixel_per_mm) = image.width_height_pixel_per_mm();
+}
 
-            self.dim_mm_y = value;
-            self.repeat_y = self.dim_mm_y * pixel_per_mm / input_height;
-            self.repeat_y_text = pretty_print_float(self.repeat_y);
+/// Composites transparent parts onto an opaque ground for printers that handle transparency
+/// unpredictably
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FlattenOptions {
+    enabled: bool,
+    background_color: [u8; 3],
+    /// Png file that is used as ground instead of the background color if set
+    ground_image: Option<String>,
+}
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        FlattenOptions {
+            enabled: false,
+            background_color: [255, 255, 255],
+            ground_image: None,
+        }
+    }
+}
 
-            self.process_state = ProcessState::Idle;
+/// Scatters the motifs at random positions over a repeat cell instead of repeating the input
+/// image on a grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct TossOptions {
+    enabled: bool,
+    cell_width_mm: f64,
+    cell_height_mm: f64,
+    /// Minimum space between the bounding circles of neighboring motifs
+    spacing_mm: f64,
+    /// Turns every motif by a random angle
+    rotate: bool,
+    /// The same seed always tosses the motifs the same way
+    seed: u64,
+}
+impl Default for TossOptions {
+    fn default() -> Self {
+        TossOptions {
+            enabled: false,
+            cell_width_mm: 200.0,
+            cell_height_mm: 200.0,
+            spacing_mm: 5.0,
+            rotate: true,
+            seed: 1,
         }
     }
 }
 
-impl Application for RepeatyGui {
-    type Executor = iced::executor::Default;
-    type Message = GuiEvent;
-    type Flags = ();
+impl TossOptions {
+    fn is_valid(&self) -> bool {
+        self.cell_width_mm > 0.0 && self.cell_height_mm > 0.0 && self.spacing_mm >= 0.0
+    }
+}
 
-    fn new(_flags: ()) -> (RepeatyGui, Command<Self::Message>) {
-        (RepeatyGui::new(), Command::none())
+/// Breaks up visible repetition by showing every tile mirrored, rotated or replaced by a variant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct VariationOptions {
+    enabled: bool,
+    /// Allows mirroring tiles horizontally, vertically or both
+    mirror: bool,
+    /// Allows turning tiles upside down and by 90 degrees if they are square
+    rotate: bool,
+    /// Png files with the same pixel size as the tile that are picked instead of it
+    variants: Vec<String>,
+    /// The same seed always varies the tiles the same way
+    seed: u64,
+}
+impl Default for VariationOptions {
+    fn default() -> Self {
+        VariationOptions {
+            enabled: false,
+            mirror: true,
+            rotate: false,
+            variants: Vec::new(),
+            seed: 1,
+        }
     }
+}
 
-    fn title(&self) -> String {
-        String::from(main_launcher_info::LAUNCHER_WINDOW_TITLE)
+/// Paper size of the sheets a poster is split into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum PosterPaperSize {
+    /// The pattern is written as a single image
+    Off,
+    A4,
+    A3,
+    Letter,
+}
+impl Default for PosterPaperSize {
+    fn default() -> Self {
+        PosterPaperSize::Off
     }
+}
 
-    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
-        match message {
-            GuiEvent::ChangedRepeatCountX(value_str) => {
-                self.repeat_x_text = value_str;
-                if let Some(value) = self.repeat_x_text.parse::<f64>().ok() {
-                    self.set_repeat_x(value);
-                }
-            }
-            GuiEvent::ChangedRepeatCountY(value_str) => {
-                self.repeat_y_text = value_str;
-                if let Some(value) = self.repeat_y_text.parse::<f64>().ok() {
-                    self.set_repeat_y(value);
-                }
-            }
-            GuiEvent::ChangedDimensionMillimeterX(value_str) => {
-                self.dim_mm_x_text = value_str;
-                if let Some(value) = self.dim_mm_x_text.parse::<f64>().ok() {
-                    self.set_dim_mm_x(value);
-                }
-            }
-            GuiEvent::ChangedDimensionMillimeterY(value_str) => {
-                self.dim_mm_y_text = value_str;
-                if let Some(value) = self.dim_mm_y_text.parse::<f64>().ok() {
-                    self.set_dim_mm_y(value);
-                }
-            }
-            GuiEvent::PressedStartButton => {
-                if let Some(image) = &self.image {
-                    if self.repeat_x <= 0.0
-                        || self.repeat_y <= 0.0
-                        || self.dim_mm_x <= 0.0
-                        || self.dim_mm_y <= 0.0
-                        || self.repeat_x.is_nan()
-                        || self.repeat_y.is_nan()
-                        || self.dim_mm_x.is_nan()
-                        || self.dim_mm_y.is_nan()
-                    {
-                        self.current_error =
-                            Some("Some of the input values above are incorrect".to_string());
-                    } else {
-                        self.process_state = ProcessState::Running;
+impl PosterPaperSize {
+    /// Width and height in millimeter in portrait orientation
+    fn dimensions_mm(self) -> Option<(f64, f64)> {
+        match self {
+            PosterPaperSize::Off => None,
+            PosterPaperSize::A4 => Some((210.0, 297.0)),
+            PosterPaperSize::A3 => Some((297.0, 420.0)),
+            PosterPaperSize::Letter => Some((215.9, 279.4)),
+        }
+    }
+}
 
-                        let (
-                            output_image_pixel_width,
-                            output_image_pixel_height,
-                            png_output_filepath,
-                        ) = image.output_image_pixel_width_height_filepath(
-                            self.repeat_x,
-                            self.repeat_y,
-                            self.dim_mm_x,
-                            self.dim_mm_y,
-                        );
+/// Overlaps larger than this would waste most of a sheet
+const POSTER_MAX_OVERLAP_MM: f64 = 50.0;
 
-                        if let Err(error_message) = create_pattern_png(
-                            &png_output_filepath,
-                            &image.bitmap,
-                            &image.png_metadata,
-                            output_image_pixel_width,
-                            output_image_pixel_height,
-                        ) {
-                            self.current_error = Some(error_message);
-                            self.process_state = ProcessState::Idle;
-                        } else {
-                            self.current_error = None;
-                            self.process_state = ProcessState::Finished;
-                        }
-                    }
-                }
-            }
-            GuiEvent::WindowEvent(window_event) => match window_event {
-                iced_native::Event::Window(window_event) => match window_event {
-                    iced_native::window::Event::FileDropped(filepath) => {
-                        self.load_image(&filepath.to_string_borrowed_or_panic());
-                    }
-                    _ => {}
-                },
-                iced_native::Event::Keyboard(key_event) => match key_event {
-                    iced_native::input::keyboard::Event::Input { key_code, .. } => {
-                        if key_code == iced_native::input::keyboard::KeyCode::Escape {
-                            std::process::exit(0);
-                        }
-                    }
-                    _ => {}
-                },
-                _ => {}
-            },
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct PosterOptions {
+    paper_size: PosterPaperSize,
+    /// Width of the strip that neighboring sheets have in common
+    overlap_mm: f64,
+}
+impl Default for PosterOptions {
+    fn default() -> Self {
+        PosterOptions {
+            paper_size: PosterPaperSize::Off,
+            overlap_mm: 10.0,
         }
+    }
+}
 
-        Command::none()
+impl PosterOptions {
+    fn is_valid(&self) -> bool {
+        self.overlap_mm >= 0.0 && self.overlap_mm <= POSTER_MAX_OVERLAP_MM
     }
+}
 
-    fn subscription(&self) -> Subscription<GuiEvent> {
-        iced_native::subscription::events().map(GuiEvent::WindowEvent)
+/// How the repeat continues between neighboring wallpaper panels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum WallpaperMatch {
+    /// The repeat continues horizontally so that all panels start at the same height
+    Straight,
+    /// Every second panel is moved down by half a tile height
+    Drop,
+}
+impl Default for WallpaperMatch {
+    fn default() -> Self {
+        WallpaperMatch::Straight
     }
+}
 
-    fn view(&mut self) -> Element<Self::Message> {
-        let result = if let Some(image) = &self.image {
-            // We have an image already loaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct WallpaperOptions {
+    /// Splits the pattern into one panel per roll width
+    enabled: bool,
+    roll_width_mm: f64,
+    roll_length_mm: f64,
+    match_type: WallpaperMatch,
+}
+impl Default for WallpaperOptions {
+    fn default() -> Self {
+        WallpaperOptions {
+            enabled: false,
+            roll_width_mm: 530.0,
+            roll_length_mm: 10050.0,
+            match_type: WallpaperMatch::Straight,
+        }
+    }
+}
 
-            let input_image_stats = draw_input_image_stats(image);
-            let output_image_stats = draw_output_image_stats(
-                image,
-                self.repeat_x,
-                self.repeat_y,
-                self.dim_mm_x,
-                self.dim_mm_y,
-            );
-            let input_fields = draw_textinput_fields(
-                &self.repeat_x_text,
-                &self.repeat_y_text,
-                &self.dim_mm_x_text,
-                &self.dim_mm_y_text,
-                &mut self.repeat_x_widget,
-                &mut self.repeat_y_widget,
-                &mut self.dim_mm_x_widget,
-                &mut self.dim_mm_y_widget,
+impl WallpaperOptions {
+    fn is_valid(&self) -> bool {
+        self.roll_width_mm > 0.0 && self.roll_length_mm > 0.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct MuralOptions {
+    /// Uses the wall dimensions as pattern dimensions and splits the pattern into panels
+    enabled: bool,
+    wall_width_mm: f64,
+    wall_height_mm: f64,
+    panel_width_mm: f64,
+    /// Width of the strip that neighboring panels have in common
+    overlap_mm: f64,
+}
+impl Default for MuralOptions {
+    fn default() -> Self {
+        MuralOptions {
+            enabled: false,
+            wall_width_mm: 4000.0,
+            wall_height_mm: 2600.0,
+            panel_width_mm: 1000.0,
+            overlap_mm: 20.0,
+        }
+    }
+}
+
+impl MuralOptions {
+    fn is_valid(&self) -> bool {
+        self.wall_width_mm > 0.0
+            && self.wall_height_mm > 0.0
+            && self.panel_width_mm > 0.0
+            && self.overlap_mm >= 0.0
+            && self.overlap_mm < self.panel_width_mm
+    }
+}
+
+// NOTE: Fields that serialize into tables (i.e. structs) need to stay below all other fields so
+//       that we can write the settings as TOML
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct PatternSettings {
+    size_mode: SizeMode,
+
+    repeat_x: f64,
+    repeat_y: f64,
+
+    dim_mm_x: f64,
+    dim_mm_y: f64,
+
+    aspect_lock: AspectLock,
+    /// Height divided by width that is kept when using `AspectLock::AspectRatio`
+    locked_aspect_ratio: f64,
+    /// Rounds repeat counts to whole numbers while an aspect lock is active
+    keep_whole_repeats: bool,
+
+    snap_policy: SnapPolicy,
+    tile_layout: TileLayout,
+
+    /// Continues the repeat by this much beyond the dimensions on every side for print trimming
+    bleed_mm: f64,
+    /// Adds a margin with crop marks, registration marks and a slug line around the bleed
+    print_marks: bool,
+
+    output_color_type: OutputColorType,
+
+    output_format: OutputFormat,
+    /// Writes all patterns of a batch as pages of a single pdf file instead of one file each
+    pdf_single_file_for_batch: bool,
+    /// Writes the tile as png file next to the svg and links it instead of embedding it
+    svg_link_tile: bool,
+
+    /// Uses this DPI for all input images instead of the DPI from their metadata
+    ppi_override: Option<f64>,
+
+    /// Rows of motif letters separated by `/` like `AB/BA`. Uses the input image alone if empty.
+    motif_layout: String,
+    /// Png files of the motifs `B`, `C` and `D`. The input image is always motif `A`.
+    motifs: Vec<String>,
+
+    /// Defaults to the directory of the executable if not set
+    output_dir: Option<String>,
+
+    metadata: MetadataOptions,
+    png_encoder: PngEncoderOptions,
+    /// Splits the pattern into printer paper sized sheets
+    poster: PosterOptions,
+    /// Splits the pattern into wallpaper panels
+    wallpaper: WallpaperOptions,
+    /// Covers a wall with overlapping panels
+    mural: MuralOptions,
+    /// Space between neighboring tiles
+    gutter: GutterOptions,
+    /// Writes opaque RGB output by compositing transparent parts onto a ground
+    flatten: FlattenOptions,
+    /// Scatters the motifs over a repeat cell
+    toss: TossOptions,
+    /// Varies the tiles to break up visible repetition
+    variation: VariationOptions,
+}
+
+impl PatternSettings {
+    fn output_dir(&self) -> String {
+        self.output_dir.clone().unwrap_or_else(get_executable_dir)
+    }
+
+    /// A transparent gutter gets the flatten background color as it would be black otherwise. It
+    /// stays transparent if the pattern is flattened onto a ground image so that the ground shows.
+    fn effective_gutter(&self) -> GutterOptions {
+        let mut result = self.gutter;
+        if self.flatten.enabled
+            && self.flatten.ground_image.is_none()
+            && result.background_color.is_none()
+        {
+            result.background_color = Some(self.flatten.background_color);
+        }
+        result
+    }
+
+    /// Returns what the chosen output color type lacks to show the gutter. A transparent gutter
+    /// needs an alpha channel, a colored gutter needs color samples and every gutter needs direct
+    /// color instead of a palette. `Original` and `Smallest` are adapted to the gutter instead.
+    /// A pattern that is flattened onto a ground image is converted to RGB after compositing and
+    /// has no conflict.
+    fn gutter_color_type_conflict(&self, pixel_per_mm: f64) -> Option<&'static str> {
+        let gutter = self.effective_gutter();
+        if gutter.pixels(pixel_per_mm) == (0, 0) || self.flattens_onto_ground_image() {
+            return None;
+        }
+        let needs_alpha = gutter.background_color.is_none();
+        let needs_color = gutter
+            .background_color
+            .map(|[r, g, b]| r != g || g != b)
+            .unwrap_or(false);
+        match self.output_color_type {
+            OutputColorType::Indexed => Some("direct color instead of a palette"),
+            OutputColorType::Gray | OutputColorType::Rgb if needs_alpha => {
+                Some("an alpha channel for the transparent gutter")
+            }
+            OutputColorType::Gray | OutputColorType::GrayAlpha if needs_color => {
+                Some("color samples for the colored gutter")
+            }
+            _ => None,
+        }
+    }
+
+    /// Describes why the automatic output color types may differ from the color type of the input
+    /// image
+    fn color_type_note(&self, pixel_per_mm: f64) -> Option<&'static str> {
+        match self.output_color_type {
+            OutputColorType::Original if self.flatten.enabled => {
+                return Some("Color type: RGB as the transparency is flattened");
+            }
+            OutputColorType::Smallest if self.flattens_onto_ground_image() => {
+                return Some("Color type: RGB as the transparency is flattened");
+            }
+            OutputColorType::Original | OutputColorType::Smallest => {}
+            _ => return None,
+        }
+        if self.effective_gutter().pixels(pixel_per_mm) != (0, 0) {
+            return Some("Color type: adapted so that it can show the gutter");
+        }
+        None
+    }
+
+    /// The ground image is composited after the tiles are placed instead of flattening each tile
+    fn flattens_onto_ground_image(&self) -> bool {
+        self.flatten.enabled && self.flatten.ground_image.is_some()
+    }
+
+    fn writes_single_pdf_for_batch(&self) -> bool {
+        self.output_format.is_pdf() && self.pdf_single_file_for_batch
+    }
+
+    /// Height divided by width of the current dimensions or 1 if they are not usable
+    fn current_aspect_ratio(&self) -> f64 {
+        let aspect_ratio = self.dim_mm_y / self.dim_mm_x;
+        if aspect_ratio.is_finite() && aspect_ratio > 0.0 {
+            aspect_ratio
+        } else {
+            1.0
+        }
+    }
+
+    fn keeps_whole_repeats(&self) -> bool {
+        self.aspect_lock != AspectLock::Off && self.keep_whole_repeats
+    }
+
+    fn locked_repeat_count(&self, repeat_count: f64) -> f64 {
+        if self.keeps_whole_repeats() {
+            repeat_count.round().max(1.0)
+        } else {
+            repeat_count
+        }
+    }
+
+    /// Same as `locked_repeat_count` but also applies the snap policy. This is used whenever a
+    /// repeat count is derived from a physical dimension.
+    fn snapped_repeat_count(&self, repeat_count: f64) -> f64 {
+        if self.snap_policy == SnapPolicy::WholeRepeats {
+            repeat_count.round().max(1.0)
+        } else {
+            self.locked_repeat_count(repeat_count)
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        !(self.repeat_x <= 0.0
+            || self.repeat_y <= 0.0
+            || self.dim_mm_x <= 0.0
+            || self.dim_mm_y <= 0.0
+            || self.repeat_x.is_nan()
+            || self.repeat_y.is_nan()
+            || self.dim_mm_x.is_nan()
+            || self.dim_mm_y.is_nan()
+            || !(self.bleed_mm >= 0.0)
+            || !self.poster.is_valid()
+            || !self.wallpaper.is_valid()
+            || !self.mural.is_valid()
+            || !self.gutter.is_valid()
+            || !self.toss.is_valid())
+    }
+
+    /// Returns the repeat counts and physical dimensions in millimeter for the given image.
+    /// Depending on the size mode either the repeat counts or the physical dimensions are kept and
+    /// the other values are derived from the image. Murals always cover the exact wall dimensions.
+    fn repeats_and_dimensions_for_image(&self, image: &InputImage) -> (f64, f64, f64, f64) {
+        let (input_width, input_height, pixel_per_mm) =
+            image.width_height_pixel_per_mm(&self.gutter);
+        if self.mural.enabled {
+            return (
+                self.mural.wall_width_mm * pixel_per_mm / input_width,
+                self.mural.wall_height_mm * pixel_per_mm / input_height,
+                self.mural.wall_width_mm,
+                self.mural.wall_height_mm,
             );
+        }
+        match self.size_mode {
+            SizeMode::RepeatCount => (
+                self.repeat_x,
+                self.repeat_y,
+                self.repeat_x * input_width / pixel_per_mm,
+                self.repeat_y * input_height / pixel_per_mm,
+            ),
+            SizeMode::Dimension => {
+                let repeat_x =
+                    self.snapped_repeat_count(self.dim_mm_x * pixel_per_mm / input_width);
+                let repeat_y =
+                    self.snapped_repeat_count(self.dim_mm_y * pixel_per_mm / input_height);
+                (
+                    repeat_x,
+                    repeat_y,
+                    repeat_x * input_width / pixel_per_mm,
+                    repeat_y * input_height / pixel_per_mm,
+                )
+            }
+        }
+    }
+}
 
-            let result = Column::new()
-                .spacing(10)
-                .padding(20)
-                .align_items(Align::Center)
-                .push(input_image_stats)
-                .push(input_fields)
-                .push(output_image_stats)
-                .push(
-                    Button::new(&mut self.start_button_widget, Text::new("Create Pattern"))
-                        .on_press(GuiEvent::PressedStartButton),
-                );
+/// Size, placement and output filepath of the pattern for a given image
+#[derive(Clone)]
+struct PatternGeometry {
+    /// The size including bleed and print marks
+    pixel_width: i32,
+    pixel_height: i32,
+    /// The pixel size before rounding. Used to detect sizes that do not fit into `i32`.
+    exact_pixel_width: f64,
+    exact_pixel_height: f64,
+    /// The size including bleed and print marks
+    width_mm: f64,
+    height_mm: f64,
+    /// Bleed per side rounded to whole pixels
+    bleed_pixels: i32,
+    bleed_mm: f64,
+    /// Margin for the print marks per side rounded to whole pixels
+    margin_pixels: i32,
+    margin_mm: f64,
+    print_marks: Option<PrintMarks>,
+    /// Set if the pattern is split into printer paper sized sheets
+    poster: Option<PosterLayout>,
+    /// Set if the pattern is split into wallpaper or mural panels
+    panels: Option<PanelLayout>,
+    /// Derives the pixel density in the metadata from the physical size instead of the input
+    /// image so that the printed size is exact
+    exact_physical_size: bool,
+    placement: TilePlacement,
+    /// Position of the top left output pixel in the repeated ground image of the flatten options
+    ground_origin_x: i32,
+    ground_origin_y: i32,
+    /// Can differ from the output format of the settings if the pattern is too large for it
+    output_format: OutputFormat,
+    output_filepath: String,
+}
 
-            // Add processing state message
-            match self.process_state {
-                ProcessState::Idle => result,
-                ProcessState::Running => result
-                    .push(iced::Space::with_height(iced::Length::Units(20)))
-                    .push(
-                        Text::new("Creating pattern ...")
-                            .horizontal_alignment(iced::HorizontalAlignment::Center)
-                            .vertical_alignment(iced::VerticalAlignment::Bottom)
-                            .size(30)
-                            .color(iced::Color::from_rgb(0.0, 0.0, 0.5))
-                            .width(FillPortion(1)),
-                    ),
-                ProcessState::Finished => result
-                    .push(iced::Space::with_height(iced::Length::Units(20)))
-                    .push(
-                        Text::new("Finished creating pattern. Enjoy!")
-                            .horizontal_alignment(iced::HorizontalAlignment::Center)
-                            .vertical_alignment(iced::VerticalAlignment::Bottom)
-                            .size(30)
-                            .color(iced::Color::from_rgb(0.0, 0.5, 0.0))
-                            .width(FillPortion(1)),
-                    ),
+/// Png patterns whose uncompressed size exceeds this are written as BigTIFF instead because they
+/// would not fit into memory comfortably and many programs cannot read them
+const PNG_MAX_RAW_SIZE_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// The largest width and height png allows. This is also the limit of our pixel coordinates.
+const OUTPUT_MAX_DIMENSION: f64 = 2_147_483_647.0;
+
+/// Rough upper bounds for the resources needed to write a pattern
+#[derive(Debug, Clone)]
+struct OutputSizeEstimate {
+    /// Size of the uncompressed pattern
+    raw_size_bytes: u64,
+    /// Uncompressed image data that is actually encoded. This is only the tile for formats that
+    /// repeat the tile themselves.
+    encoded_size_bytes: u64,
+    /// Memory needed while compositing and writing the pattern
+    memory_bytes: u64,
+    /// Size of the output file if the image data does not compress at all
+    file_size_bytes: u64,
+}
+
+fn uncompressed_size_bytes(pixel_width: i32, pixel_height: i32, bytes_per_pixel: usize) -> u64 {
+    (pixel_width.max(0) as u64)
+        .saturating_mul(pixel_height.max(0) as u64)
+        .saturating_mul(bytes_per_pixel as u64)
+}
+
+/// Fails if the given amount of memory exceeds the address space or the memory that is currently
+/// available. `subject` names what needs the memory, i.e. "The pattern for 'a.png'".
+fn check_available_memory(subject: &str, memory_bytes: u64) -> Result<(), String> {
+    if memory_bytes > usize::MAX as u64 {
+        return Err(format!(
+            "{} would need {} of memory which exceeds the address space",
+            subject,
+            pretty_print_bytes(memory_bytes)
+        ));
+    }
+    match sys_info::mem_info() {
+        Ok(memory_info) => {
+            let available_memory_bytes =
+                (memory_info.avail + memory_info.swap_free).saturating_mul(1024);
+            if memory_bytes > available_memory_bytes {
+                return Err(format!(
+                    "{} would need about {} of memory but only {} are available",
+                    subject,
+                    pretty_print_bytes(memory_bytes),
+                    pretty_print_bytes(available_memory_bytes)
+                ));
             }
+        }
+        Err(error) => log::warn!("Could not determine available memory : {}", error),
+    }
+    Ok(())
+}
+
+/// Returns the date that the slug line of the print marks shows. It is taken once per job and
+/// passed on so that all patterns of a batch carry the same date.
+fn job_date_text() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+impl PatternGeometry {
+    fn new(image: &InputImage, settings: &PatternSettings, job_date: &str) -> PatternGeometry {
+        let (repeat_x, repeat_y, dim_mm_x, dim_mm_y) =
+            settings.repeats_and_dimensions_for_image(image);
+        let (trim_pixel_width, trim_pixel_height, output_filepath_without_extension) = image
+            .output_image_pixel_width_height_filepath(
+                &settings.output_dir(),
+                repeat_x,
+                repeat_y,
+                dim_mm_x,
+                dim_mm_y,
+                &settings.gutter,
+            );
+        let pixel_per_mm = image.pixel_per_mm();
+        let bleed_pixels = (settings.bleed_mm * pixel_per_mm).round() as i32;
+        let bleed_mm = bleed_pixels as f64 / pixel_per_mm;
+        let margin_pixels = if settings.print_marks {
+            (PRINT_MARKS_MARGIN_MM * pixel_per_mm).round() as i32
         } else {
-            // We have no image loaded
-            Column::new()
+            0
+        };
+        let margin_mm = margin_pixels as f64 / pixel_per_mm;
+        let border_pixels = bleed_pixels + margin_pixels;
+        let pixel_width = trim_pixel_width.saturating_add(2 * border_pixels);
+        let pixel_height = trim_pixel_height.saturating_add(2 * border_pixels);
+
+        let print_marks = if settings.print_marks {
+            let slug_text = format!(
+                "{}   Repeat {}x{}   {}x{}mm   {} DPI   {}",
+                system::path_to_filename(&image.filepath),
+                pretty_print_float(repeat_x),
+                pretty_print_float(repeat_y),
+                pretty_print_float(dim_mm_x),
+                pretty_print_float(dim_mm_y),
+                pretty_print_float(image.ppi.unwrap_or(DEFAULT_PPI)),
+                job_date
+            );
+            Some(PrintMarks::new(
+                dim_mm_x, dim_mm_y, bleed_mm, margin_mm, slug_text,
+            ))
+        } else {
+            None
+        };
+
+        let poster =
+            settings
+                .poster
+                .paper_size
+                .dimensions_mm()
+                .map(|(paper_width_mm, paper_height_mm)| {
+                    PosterLayout::new(
+                        pixel_width,
+                        pixel_height,
+                        pixel_per_mm,
+                        paper_width_mm,
+                        paper_height_mm,
+                        settings.poster.overlap_mm,
+                    )
+                });
+
+        let height_mm = dim_mm_y + 2.0 * (bleed_mm + margin_mm);
+        let panels = if settings.mural.enabled {
+            Some(PanelLayout::for_mural(
+                trim_pixel_width,
+                height_mm,
+                pixel_per_mm,
+                settings.mural.panel_width_mm,
+                settings.mural.overlap_mm,
+            ))
+        } else if settings.wallpaper.enabled {
+            let drop_pixels = match settings.wallpaper.match_type {
+                WallpaperMatch::Straight => 0,
+                WallpaperMatch::Drop => {
+                    (image.bitmap.height + settings.gutter.pixels(pixel_per_mm).1) / 2
+                }
+            };
+            Some(PanelLayout::for_wallpaper(
+                trim_pixel_width,
+                height_mm,
+                pixel_per_mm,
+                settings.wallpaper.roll_width_mm,
+                settings.wallpaper.roll_length_mm,
+                drop_pixels,
+            ))
+        } else {
+            None
+        };
+
+        // NOTE: Poster sheets are small enough for png regardless of the pattern size. Panels are
+        //       written separately so only the size of a panel matters.
+        let written_pixel_width = match &panels {
+            Some(panels) => panels.panel_pixel_width,
+            None => pixel_width,
+        };
+        let output_format = if settings.output_format == OutputFormat::Png
+            && poster.is_none()
+            && uncompressed_size_bytes(
+                written_pixel_width,
+                pixel_height,
+                image.bitmap.bytes_per_pixel(),
+            ) > PNG_MAX_RAW_SIZE_BYTES
+        {
+            OutputFormat::BigTiff
+        } else {
+            settings.output_format
+        };
+        let output_filepath = output_filepath_without_extension + output_format.extension();
+        let (cell_width, cell_height, _) = image.width_height_pixel_per_mm(&settings.gutter);
+        let placement = TilePlacement::new(
+            settings.tile_layout,
+            settings.snap_policy,
+            cell_width as i32,
+            cell_height as i32,
+            trim_pixel_width,
+            trim_pixel_height,
+        )
+        .with_gutter(&settings.effective_gutter(), pixel_per_mm)
+        .with_variation(image.tile_variation(&settings.variation))
+        .with_bleed(border_pixels, image.bitmap.width, image.bitmap.height);
+        PatternGeometry {
+            pixel_width,
+            pixel_height,
+            exact_pixel_width: repeat_x * cell_width + 2.0 * border_pixels as f64,
+            exact_pixel_height: repeat_y * cell_height + 2.0 * border_pixels as f64,
+            width_mm: dim_mm_x + 2.0 * (bleed_mm + margin_mm),
+            height_mm,
+            bleed_pixels,
+            bleed_mm,
+            margin_pixels,
+            margin_mm,
+            print_marks,
+            poster,
+            panels,
+            exact_physical_size: false,
+            placement,
+            ground_origin_x: 0,
+            ground_origin_y: 0,
+            output_format,
+            output_filepath,
+        }
+    }
+
+    /// The geometry of a single wallpaper or mural panel. Panels span the full pattern height
+    /// including the bleed which serves as trim allowance at the top and bottom.
+    fn panel_geometry(
+        &self,
+        image: &InputImage,
+        panels: &PanelLayout,
+        panel_index: usize,
+    ) -> PatternGeometry {
+        let border_pixels = self.bleed_pixels + self.margin_pixels;
+        let output_filepath_without_extension = self
+            .output_filepath
+            .trim_end_matches(self.output_format.extension());
+        // NOTE: Murals get their own directory so that their panels can be named `panel_01.png`
+        //       without clashing with the panels of other images
+        let output_filepath = if panels.is_mural {
+            system::path_join(
+                &format!("{}__mural", output_filepath_without_extension),
+                &format!(
+                    "panel_{:02}{}",
+                    panel_index + 1,
+                    self.output_format.extension()
+                ),
+            )
+        } else {
+            format!(
+                "{}__panel_{:02}{}",
+                output_filepath_without_extension,
+                panel_index + 1,
+                self.output_format.extension()
+            )
+        };
+        PatternGeometry {
+            pixel_width: panels.panel_pixel_width,
+            pixel_height: self.pixel_height,
+            exact_pixel_width: panels.panel_pixel_width as f64,
+            exact_pixel_height: self.exact_pixel_height,
+            width_mm: panels.panel_width_mm,
+            height_mm: self.height_mm,
+            bleed_pixels: 0,
+            bleed_mm: 0.0,
+            margin_pixels: 0,
+            margin_mm: 0.0,
+            print_marks: None,
+            poster: None,
+            panels: None,
+            exact_physical_size: panels.is_mural,
+            placement: self.placement.shifted(
+                border_pixels + panels.panel_offset_x(panel_index),
+                panels.panel_offset_y(panel_index),
+                image.bitmap.width,
+                image.bitmap.height,
+            ),
+            ground_origin_x: self.ground_origin_x
+                + border_pixels
+                + panels.panel_offset_x(panel_index),
+            ground_origin_y: self.ground_origin_y + panels.panel_offset_y(panel_index),
+            output_format: self.output_format,
+            output_filepath,
+        }
+    }
+
+    /// The drop of a panel only continues the repeat of its left neighbor if the seam between
+    /// them lies on a tile edge
+    fn panel_edges_are_tile_edges(&self, image: &InputImage, panels: &PanelLayout) -> bool {
+        let cell_width = image.bitmap.width + self.placement.gutter_x;
+        (0..panels.panel_count).all(|panel_index| {
+            let panel_placement = self.panel_geometry(image, panels, panel_index).placement;
+            panel_placement.offset_x % cell_width == 0
+        })
+    }
+
+    /// Png pixel density that makes the image print with exactly its physical size
+    fn png_exact_phys_chunk(&self) -> Vec<u8> {
+        png_phys_chunk_from_pixel_per_meter(
+            self.pixel_width as f64 / (self.width_mm / 1000.0),
+            self.pixel_height as f64 / (self.height_mm / 1000.0),
+        )
+    }
+
+    /// The filepath that the GUI shows for the pattern. Patterns that are split into several
+    /// files show their first file.
+    fn first_output_filepath(&self, image: &InputImage) -> String {
+        if let Some(panels) = &self.panels {
+            self.panel_geometry(image, panels, 0).output_filepath
+        } else if self.poster.is_some() && self.output_format == OutputFormat::Png {
+            self.poster_page_filepath(0)
+        } else {
+            self.output_filepath.clone()
+        }
+    }
+
+    /// Png posters are written as numbered series `<name>__page_01.png`, `<name>__page_02.png`, ..
+    fn poster_page_filepath(&self, page_index: usize) -> String {
+        format!(
+            "{}__page_{:02}{}",
+            self.output_filepath
+                .trim_end_matches(self.output_format.extension()),
+            page_index + 1,
+            self.output_format.extension()
+        )
+    }
+
+    /// The label that is printed into the border of a poster sheet
+    fn poster_sheet_label(&self, image: &InputImage, sheet_index: usize) -> String {
+        let (sheet_count, sheet) = match &self.poster {
+            Some(poster) => (poster.sheets.len(), poster.sheets[sheet_index]),
+            None => return String::new(),
+        };
+        format!(
+            "{}   Page {}/{}   Row {}, Column {}",
+            system::path_to_filename(&image.filepath),
+            sheet_index + 1,
+            sheet_count,
+            sheet.row + 1,
+            sheet.column + 1
+        )
+    }
+
+    fn has_trim_box(&self) -> bool {
+        self.bleed_pixels > 0 || self.margin_pixels > 0
+    }
+
+    /// Png has no standard chunk for the trim box so we describe it in a `Comment` text chunk
+    fn png_trim_text_chunk(&self) -> Vec<u8> {
+        let border_pixels = self.bleed_pixels + self.margin_pixels;
+        let border_mm = self.bleed_mm + self.margin_mm;
+        let text = format!(
+            "Comment\0Trim box: x={} y={} width={} height={} pixels ({}x{}mm), bleed {}mm per side",
+            border_pixels,
+            border_pixels,
+            self.pixel_width - 2 * border_pixels,
+            self.pixel_height - 2 * border_pixels,
+            pretty_print_float(self.width_mm - 2.0 * border_mm),
+            pretty_print_float(self.height_mm - 2.0 * border_mm),
+            pretty_print_float(self.bleed_mm)
+        );
+        text.into_bytes()
+    }
+
+    fn page_size(&self) -> PageSize {
+        PageSize {
+            width_mm: self.width_mm,
+            height_mm: self.height_mm,
+            bleed_mm: self.bleed_mm,
+            margin_mm: self.margin_mm,
+        }
+    }
+
+    /// The ground image that the composited pattern is flattened onto if there is one
+    fn ground_layer<'a>(&self, image: &'a InputImage) -> Option<GroundLayer<'a>> {
+        image.ground.as_ref().map(|ground| {
+            GroundLayer::new(
+                ground,
+                TransferFunction::from_png_metadata(&image.png_metadata),
+                self.pixel_width,
+                self.ground_origin_x,
+                self.ground_origin_y,
+            )
+        })
+    }
+
+    fn raster_print_marks(&self, image: &InputImage) -> Option<RasterPrintMarks> {
+        let pixel_per_mm = image.pixel_per_mm();
+        self.print_marks
+            .as_ref()
+            .map(|print_marks| print_marks.rasterized(pixel_per_mm, self.pixel_width))
+    }
+
+    fn size_estimate(&self, image: &InputImage) -> OutputSizeEstimate {
+        let bytes_per_pixel = image.bitmap.bytes_per_pixel();
+        let raw_size_bytes =
+            uncompressed_size_bytes(self.pixel_width, self.pixel_height, bytes_per_pixel);
+        let tile_size_bytes = image.bitmap.data.len() as u64;
+        if let Some(panels) = &self.panels {
+            // Panels are written one after another
+            let panel_estimate = self.panel_geometry(image, panels, 0).size_estimate(image);
+            let panel_count = panels.panel_count as u64;
+            return OutputSizeEstimate {
+                raw_size_bytes: panel_estimate.raw_size_bytes.saturating_mul(panel_count),
+                encoded_size_bytes: panel_estimate
+                    .encoded_size_bytes
+                    .saturating_mul(panel_count),
+                memory_bytes: panel_estimate.memory_bytes,
+                file_size_bytes: panel_estimate.file_size_bytes.saturating_mul(panel_count),
+            };
+        }
+        if let Some(poster) = &self.poster {
+            // Only one sheet is in memory at a time
+            let sheet_size_bytes = uncompressed_size_bytes(
+                poster.sheet_pixel_width,
+                poster.sheet_pixel_height,
+                bytes_per_pixel,
+            );
+            let encoded_size_bytes = sheet_size_bytes.saturating_mul(poster.sheets.len() as u64);
+            let memory_bytes = if self.output_format.is_pdf() {
+                sheet_size_bytes.saturating_mul(3)
+            } else {
+                sheet_size_bytes
+            };
+            return OutputSizeEstimate {
+                raw_size_bytes,
+                encoded_size_bytes,
+                memory_bytes,
+                file_size_bytes: encoded_size_bytes,
+            };
+        }
+        let encoded_size_bytes = if self.output_format.repeats_tile_itself() {
+            tile_size_bytes
+        } else {
+            raw_size_bytes
+        };
+        let (memory_bytes, file_size_bytes) = match self.output_format {
+            // The composited pattern is kept in memory while it is streamed to disk
+            OutputFormat::Png => (raw_size_bytes, raw_size_bytes),
+            // The composited pattern is split into color and alpha and compressed in memory
+            OutputFormat::Pdf => (raw_size_bytes.saturating_mul(3), raw_size_bytes),
+            OutputFormat::PdfTilingPattern => (3 * tile_size_bytes, tile_size_bytes),
+            // The embedded tile grows by a third due to base64 encoding
+            OutputFormat::Svg => (3 * tile_size_bytes, 2 * tile_size_bytes),
+            // Only one row of tiles and its compressed data is in memory at a time
+            OutputFormat::BigTiff => (
+                uncompressed_size_bytes(
+                    self.pixel_width,
+                    2 * bigtiff::TILE_SIZE as i32,
+                    bytes_per_pixel,
+                ),
+                raw_size_bytes,
+            ),
+        };
+        OutputSizeEstimate {
+            raw_size_bytes,
+            encoded_size_bytes,
+            memory_bytes,
+            file_size_bytes,
+        }
+    }
+
+    /// Returns an error if the pattern cannot be represented or would not fit into the available
+    /// memory or disk space. Must be called before allocating anything for the pattern.
+    fn check_output_size(
+        &self,
+        image: &InputImage,
+        settings: &PatternSettings,
+    ) -> Result<(), String> {
+        if self.pixel_width < 1 || self.pixel_height < 1 {
+            return Err(format!(
+                "The pattern for '{}' would be smaller than one pixel",
+                image.filepath
+            ));
+        }
+        if self.exact_pixel_width > OUTPUT_MAX_DIMENSION
+            || self.exact_pixel_height > OUTPUT_MAX_DIMENSION
+        {
+            return Err(format!(
+                "The pattern for '{}' would be {:.0}x{:.0} pixels but at most {:.0} pixels per side \
+                 are supported",
+                image.filepath,
+                self.exact_pixel_width,
+                self.exact_pixel_height,
+                OUTPUT_MAX_DIMENSION
+            ));
+        }
+
+        if let Some(panels) = &self.panels {
+            if self.print_marks.is_some() || self.poster.is_some() {
+                return Err(format!(
+                    "The panels for '{}' cannot be combined with print marks or a poster",
+                    image.filepath
+                ));
+            }
+            if settings.mural.enabled && settings.wallpaper.enabled {
+                return Err(format!(
+                    "The pattern for '{}' can either be a mural or a wallpaper but not both",
+                    image.filepath
+                ));
+            }
+            if panels.panels_per_roll == Some(0) {
+                return Err(format!(
+                    "The wallpaper panels for '{}' are {}mm long which does not fit onto a roll of \
+                     {}mm",
+                    image.filepath,
+                    pretty_print_float(panels.panel_height_mm),
+                    pretty_print_float(settings.wallpaper.roll_length_mm)
+                ));
+            }
+            if panels.drop_pixels > 0 {
+                if settings.tile_layout == TileLayout::HalfBrick {
+                    return Err(format!(
+                        "The drop match for the wallpaper panels of '{}' cannot be combined with \
+                         the half brick layout",
+                        image.filepath
+                    ));
+                }
+                if !self.panel_edges_are_tile_edges(image, panels) {
+                    let (cell_width, _, pixel_per_mm) =
+                        image.width_height_pixel_per_mm(&settings.gutter);
+                    return Err(format!(
+                        "The drop match needs every wallpaper panel of '{}' to start at a tile \
+                         edge. The roll width has to be a multiple of the {}mm wide tile and the \
+                         tiles cannot be centered.",
+                        image.filepath,
+                        pretty_print_float(cell_width / pixel_per_mm)
+                    ));
+                }
+            }
+        }
+        if let Some(missing) = settings.gutter_color_type_conflict(image.pixel_per_mm()) {
+            return Err(format!(
+                "The gutter of '{}' cannot be shown with the {:?} output color type as it needs {}",
+                image.filepath, settings.output_color_type, missing
+            ));
+        }
+        if settings.flattens_onto_ground_image() != image.ground.is_some() {
+            return Err(format!(
+                "The ground image for '{}' does not match the flatten options, please load the \
+                 image again",
+                image.filepath
+            ));
+        }
+        if settings.flattens_onto_ground_image() {
+            match settings.output_color_type {
+                OutputColorType::Original | OutputColorType::Smallest | OutputColorType::Rgb => {}
+                _ => {
+                    return Err(format!(
+                        "The pattern for '{}' is flattened onto a ground image which gives RGB \
+                         output and cannot be written with the {:?} output color type",
+                        image.filepath, settings.output_color_type
+                    ));
+                }
+            }
+            if self.output_format.repeats_tile_itself() && self.poster.is_none() {
+                return Err(format!(
+                    "The pattern for '{}' can only be flattened onto a ground image as png, pdf or \
+                     BigTIFF as svg and pdf tiling patterns repeat a single tile",
+                    image.filepath
+                ));
+            }
+        }
+        if settings.variation.enabled
+            && self.output_format.repeats_tile_itself()
+            && self.poster.is_none()
+        {
+            return Err(format!(
+                "The varied tiles for '{}' can only be written as png, pdf or BigTIFF as svg and \
+                 pdf tiling patterns repeat a single tile",
+                image.filepath
+            ));
+        }
+        if let Some(poster) = &self.poster {
+            match self.output_format {
+                OutputFormat::Png | OutputFormat::Pdf | OutputFormat::PdfTilingPattern => {}
+                OutputFormat::Svg | OutputFormat::BigTiff => {
+                    return Err(format!(
+                        "The poster for '{}' can only be written as png or pdf",
+                        image.filepath
+                    ));
+                }
+            }
+            if !poster.has_room_for_overlap() {
+                return Err(format!(
+                    "The poster overlap for '{}' does not fit onto a sheet at {} DPI",
+                    image.filepath,
+                    pretty_print_float(image.ppi.unwrap_or(DEFAULT_PPI))
+                ));
+            }
+        }
+
+        let estimate = self.size_estimate(image);
+        check_available_memory(
+            &format!("The pattern for '{}'", image.filepath),
+            estimate.memory_bytes,
+        )?;
+
+        let output_dir = settings.output_dir();
+        match fs2::available_space(&output_dir) {
+            Ok(available_disk_bytes) => {
+                if estimate.file_size_bytes > available_disk_bytes {
+                    return Err(format!(
+                        "The pattern for '{}' could need up to {} of disk space but only {} are \
+                         available in '{}'",
+                        image.filepath,
+                        pretty_print_bytes(estimate.file_size_bytes),
+                        pretty_print_bytes(available_disk_bytes),
+                        output_dir
+                    ));
+                }
+            }
+            Err(error) => log::warn!(
+                "Could not determine available disk space in '{}' : {}",
+                output_dir,
+                error
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of pixels at the start of the pattern that are compressed to estimate the file size
+const COMPRESSION_SAMPLE_MAX_PIXEL_COUNT: i32 = 1024 * 1024;
+
+/// What the GUI shows about a pattern before it is created
+#[derive(Debug, Clone)]
+struct OutputEstimate {
+    size: OutputSizeEstimate,
+    /// `None` if the sample could not be compressed
+    compressed_file_size_bytes: Option<u64>,
+    /// `None` if no pattern with the same output format was created before
+    duration_seconds: Option<f64>,
+    /// Result of `PatternGeometry::check_output_size` which queries the system for the available
+    /// memory and disk space
+    size_check_result: Result<(), String>,
+}
+
+impl OutputEstimate {
+    fn new(image: &InputImage, settings: &PatternSettings) -> OutputEstimate {
+        let geometry = PatternGeometry::new(image, settings, &job_date_text());
+        let size_check_result = geometry.check_output_size(image, settings);
+        let size = geometry.size_estimate(image);
+        let compressed_file_size_bytes = estimate_compression_ratio(image, settings, &geometry)
+            .map(|compression_ratio| {
+                let compressed_size_bytes = compression_ratio * size.encoded_size_bytes as f64;
+                if geometry.output_format == OutputFormat::Svg {
+                    // Base64 encoding
+                    (compressed_size_bytes * 4.0 / 3.0) as u64
+                } else {
+                    compressed_size_bytes as u64
+                }
+            });
+        let duration_seconds = TimingHistory::load()
+            .estimated_duration_seconds(geometry.output_format, size.encoded_size_bytes);
+        OutputEstimate {
+            size,
+            compressed_file_size_bytes,
+            duration_seconds,
+            size_check_result,
+        }
+    }
+}
+
+/// Compresses the first rows of the pattern (or of the tile for formats that repeat the tile
+/// themselves) and returns the ratio of compressed to uncompressed size
+fn estimate_compression_ratio(
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+) -> Option<f64> {
+    let (pattern_width, pattern_height, placement) =
+        if geometry.output_format.repeats_tile_itself() && geometry.poster.is_none() {
+            let (cell_width, cell_height, _) = image.width_height_pixel_per_mm(&settings.gutter);
+            (
+                cell_width as i32,
+                cell_height as i32,
+                geometry.placement.single_cell(),
+            )
+        } else {
+            (
+                geometry.pixel_width,
+                geometry.pixel_height,
+                geometry.placement,
+            )
+        };
+    if pattern_width < 1 || pattern_height < 1 {
+        return None;
+    }
+
+    // NOTE: If a single row is larger than the sample we only take its beginning
+    let sample_width = pattern_width.min(COMPRESSION_SAMPLE_MAX_PIXEL_COUNT);
+    let sample_height = (COMPRESSION_SAMPLE_MAX_PIXEL_COUNT / sample_width).min(pattern_height);
+    let ground = geometry.ground_layer(image);
+    let rgba_tile;
+    let tile = if ground.is_some() {
+        rgba_tile = image.bitmap.converted(OutputColorType::Rgba).ok()?;
+        &rgba_tile
+    } else {
+        &image.bitmap
+    };
+    let gutter_tile;
+    let tile = if placement.has_gutter() {
+        gutter_tile = tile_for_gutter_background(tile.clone(), placement.background_color).ok()?;
+        &gutter_tile
+    } else {
+        tile
+    };
+    // NOTE: The variants are left out as the sample is only used to estimate the compression
+    let placement = placement.with_variation(TileVariation::default());
+    let mut sample = tile.new_with_same_format(sample_width as u32, sample_height as u32);
+    copy_pixels_tiled(tile, &placement, pattern_width, &mut sample.data, 0);
+    let output_color_type = match &ground {
+        Some(ground) => {
+            ground.draw(tile, &mut sample.data, 0);
+            OutputColorType::Rgb
+        }
+        None => settings.output_color_type,
+    };
+
+    let uncompressed_sample_size = sample.data.len();
+    let sample = sample.converted(output_color_type).ok()?;
+    let mut compressed_sample = Vec::new();
+    encode_png_to_writer(
+        &sample,
+        &mut compressed_sample,
+        &PngMetadataChunks::new(),
+        &settings.png_encoder,
+    )
+    .ok()?;
+
+    Some(compressed_sample.len() as f64 / uncompressed_sample_size as f64)
+}
+
+/// Returns the input image converted to the output color type together with the png metadata
+/// that fits the converted image. Variants of the tile variation are stacked below it into a tile
+/// sheet.
+fn prepare_tile(
+    image: &InputImage,
+    settings: &PatternSettings,
+) -> Result<(PixelBuffer, PngMetadataChunks), String> {
+    // NOTE: The tile sheet is converted as a whole so that all variants share one color type
+    //       and palette
+    let tile_sheet;
+    let bitmap = if image.variants.is_empty() {
+        &image.bitmap
+    } else {
+        let mut tiles = vec![image.bitmap.clone()];
+        tiles.extend(image.variants.iter().cloned());
+        tile_sheet = variation::tile_sheet(&tiles).map_err(|error| {
+            format!(
+                "Could not combine the variants of '{}' : {}",
+                image.filepath, error
+            )
+        })?;
+        &tile_sheet
+    };
+    let tile = prepare_tile_bitmap(image, bitmap, settings)?;
+    let mut png_metadata = image.output_png_metadata(&settings.metadata);
+    if tile.color_type.is_grayscale() != image.source_color_type.is_grayscale() {
+        // An embedded ICC profile must match the color type of the image
+        png_metadata.remove("iCCP");
+    }
+    Ok((tile, png_metadata))
+}
+
+/// Converts the input image or its tile sheet into the output tile
+fn prepare_tile_bitmap(
+    image: &InputImage,
+    bitmap: &PixelBuffer,
+    settings: &PatternSettings,
+) -> Result<PixelBuffer, String> {
+    // NOTE: We flatten first as the output color type may not be able to hold the transparency.
+    //       A ground image is composited after the tiles are placed instead so that it continues
+    //       across tile edges (see `GroundLayer`). The tile needs to be RGBA until then.
+    let converted = if settings.flattens_onto_ground_image() {
+        bitmap.converted(OutputColorType::Rgba)
+    } else if settings.flatten.enabled {
+        flattened_tile(image, settings, bitmap).converted(settings.output_color_type)
+    } else {
+        bitmap.converted(settings.output_color_type)
+    };
+    let mut tile =
+        converted.map_err(|error| format!("Could not convert '{}' : {}", image.filepath, error))?;
+    let gutter = settings.effective_gutter();
+    if gutter.pixels(image.pixel_per_mm()) != (0, 0) {
+        tile = tile_for_gutter_background(tile, gutter.background_color)?;
+    }
+    Ok(tile)
+}
+
+/// Composites the tile onto the background color of the flatten options. The tile is blended in
+/// linear light if the input image has an `sRGB` or `gAMA` chunk.
+fn flattened_tile(
+    image: &InputImage,
+    settings: &PatternSettings,
+    tile: &PixelBuffer,
+) -> PixelBuffer {
+    let _timer = ct_lib::TimerScoped::new_scoped("Flattening", true);
+    let transfer = TransferFunction::from_png_metadata(&image.png_metadata);
+    flatten::flattened(tile, settings.flatten.background_color, transfer)
+}
+
+/// Drops the alpha channel of a pattern that was made opaque by compositing it onto the ground
+/// image. Other patterns are returned unchanged.
+fn without_ground_alpha(image: &InputImage, pattern: PixelBuffer) -> Result<PixelBuffer, String> {
+    if image.ground.is_none() {
+        return Ok(pattern);
+    }
+    pattern.converted(OutputColorType::Rgb).map_err(|error| {
+        format!(
+            "Could not convert the pattern of '{}' : {}",
+            image.filepath, error
+        )
+    })
+}
+
+/// Returns the tile in a color type without palette that uses an alpha channel instead of a
+/// transparent color key
+fn tile_with_direct_color(tile: PixelBuffer) -> Result<PixelBuffer, String> {
+    let output_color_type = if tile.transparency.is_some() {
+        if tile.color_type.is_grayscale() {
+            OutputColorType::GrayAlpha
+        } else {
+            OutputColorType::Rgba
+        }
+    } else if tile.color_type == ColorType::Indexed {
+        OutputColorType::Rgb
+    } else {
+        return Ok(tile);
+    };
+    tile.converted(output_color_type)
+}
+
+/// Returns the tile in a color type that can show the gutter background. A transparent gutter
+/// needs an alpha channel and a colored gutter needs color samples.
+fn tile_for_gutter_background(
+    tile: PixelBuffer,
+    background_color: Option<[u8; 3]>,
+) -> Result<PixelBuffer, String> {
+    let tile = tile_with_direct_color(tile)?;
+    let has_alpha = tile.color_type == ColorType::GrayAlpha || tile.color_type == ColorType::Rgba;
+    let needs_alpha = background_color.is_none() && !has_alpha;
+    let needs_color = tile.color_type.is_grayscale()
+        && background_color
+            .map(|[r, g, b]| r != g || g != b)
+            .unwrap_or(false);
+    if !needs_alpha && !needs_color {
+        return Ok(tile);
+    }
+    let output_color_type = match (
+        has_alpha || needs_alpha,
+        needs_color || !tile.color_type.is_grayscale(),
+    ) {
+        (true, true) => OutputColorType::Rgba,
+        (true, false) => OutputColorType::GrayAlpha,
+        (false, true) => OutputColorType::Rgb,
+        (false, false) => OutputColorType::Gray,
+    };
+    tile.converted(output_color_type)
+}
+
+/// Returns the tile together with its gutter for output formats that repeat the tile themselves
+fn tile_with_gutter(tile: PixelBuffer, placement: &TilePlacement) -> PixelBuffer {
+    if !placement.has_gutter() {
+        return tile;
+    }
+    create_pattern_bitmap(
+        &tile,
+        tile.width + placement.gutter_x,
+        tile.height + placement.gutter_y,
+        &placement.single_cell(),
+        None,
+        None,
+    )
+}
+
+/// Physical description of the repeat for output formats that repeat the tile themselves instead
+/// of containing the composited pattern. All lengths are given in millimeter.
+struct TilingGeometry {
+    layout: TileLayout,
+    tile_width_mm: f64,
+    tile_height_mm: f64,
+    /// Position inside the tile that is shown at the top left corner of the output
+    offset_x_mm: f64,
+    offset_y_mm: f64,
+}
+
+impl TilingGeometry {
+    fn new(image: &InputImage, placement: &TilePlacement) -> TilingGeometry {
+        let pixel_per_mm = image.pixel_per_mm();
+        let width = image.bitmap.width + placement.gutter_x;
+        let height = image.bitmap.height + placement.gutter_y;
+        TilingGeometry {
+            layout: placement.layout,
+            tile_width_mm: width as f64 / pixel_per_mm,
+            tile_height_mm: height as f64 / pixel_per_mm,
+            offset_x_mm: placement.offset_x as f64 / pixel_per_mm,
+            offset_y_mm: placement.offset_y as f64 / pixel_per_mm,
+        }
+    }
+
+    /// Returns the size of the smallest rectangular cell that repeats the layout and the top left
+    /// corners of the tiles inside of it. The y axis points down.
+    fn cell_tile_positions(&self) -> (f64, f64, Vec<(f64, f64)>) {
+        let tile_width = self.tile_width_mm;
+        let tile_height = self.tile_height_mm;
+        // NOTE: A cell contains two tiles for the offset layouts. The second tile is drawn twice
+        //       so that it covers the cell completely after being moved by half a tile.
+        match self.layout {
+            TileLayout::Grid => (tile_width, tile_height, vec![(0.0, 0.0)]),
+            TileLayout::HalfDrop => (
+                2.0 * tile_width,
+                tile_height,
+                vec![
+                    (0.0, 0.0),
+                    (tile_width, -tile_height / 2.0),
+                    (tile_width, tile_height / 2.0),
+                ],
+            ),
+            TileLayout::HalfBrick => (
+                tile_width,
+                2.0 * tile_height,
+                vec![
+                    (0.0, 0.0),
+                    (-tile_width / 2.0, tile_height),
+                    (tile_width / 2.0, tile_height),
+                ],
+            ),
+        }
+    }
+}
+
+/// A composited pattern that is ready to be written to disk
+struct ComposedPattern {
+    bitmap: PixelBuffer,
+    png_metadata: PngMetadataChunks,
+}
+
+fn compose_pattern(
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+) -> Result<ComposedPattern, String> {
+    let (mut tile, mut png_metadata) = prepare_tile(image, settings)?;
+    if geometry.has_trim_box() {
+        png_metadata.insert("tEXt".to_string(), geometry.png_trim_text_chunk());
+    }
+    if geometry.exact_physical_size {
+        png_metadata.insert("pHYs".to_string(), geometry.png_exact_phys_chunk());
+    }
+    if geometry.print_marks.is_some() {
+        tile = tile_with_direct_color(tile)?;
+    }
+    let bitmap = create_pattern_bitmap(
+        &tile,
+        geometry.pixel_width,
+        geometry.pixel_height,
+        &geometry.placement,
+        geometry.ground_layer(image).as_ref(),
+        geometry.raster_print_marks(image).as_ref(),
+    );
+    let bitmap = without_ground_alpha(image, bitmap)?;
+    Ok(ComposedPattern {
+        bitmap,
+        png_metadata,
+    })
+}
+
+/// Composites the sheets of a poster one after another and passes each of them to `write_sheet`
+/// together with its index so that only one sheet is in memory at a time
+fn compose_poster_sheets(
+    image: &InputImage,
+    geometry: &PatternGeometry,
+    poster: &PosterLayout,
+    tile: PixelBuffer,
+    mut write_sheet: impl FnMut(usize, &PixelBuffer) -> Result<(), String>,
+) -> Result<(), String> {
+    // NOTE: The paper colored sheet border needs an image without palette or color key
+    let tile = tile_with_direct_color(tile)?;
+    let pixel_per_mm = image.pixel_per_mm();
+    let pattern_print_marks = geometry.raster_print_marks(image);
+    let ground = geometry.ground_layer(image);
+    for (sheet_index, sheet) in poster.sheets.iter().enumerate() {
+        let sheet_marks = poster
+            .sheet_marks(sheet, geometry.poster_sheet_label(image, sheet_index))
+            .rasterized(pixel_per_mm, poster.sheet_pixel_width);
+        let bitmap = create_poster_sheet_bitmap(
+            &tile,
+            geometry,
+            ground.as_ref(),
+            pattern_print_marks.as_ref(),
+            poster,
+            sheet,
+            &sheet_marks,
+        );
+        let bitmap = without_ground_alpha(image, bitmap)?;
+        write_sheet(sheet_index, &bitmap)?;
+    }
+    Ok(())
+}
+
+/// Writes the sheets of a poster as numbered png series. Returns the filepath of the first sheet
+/// on success.
+fn write_poster_png(
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+    poster: &PosterLayout,
+) -> Result<String, String> {
+    let (tile, png_metadata) = prepare_tile(image, settings)?;
+    compose_poster_sheets(image, geometry, poster, tile, |sheet_index, sheet| {
+        write_pattern_png(
+            &geometry.poster_page_filepath(sheet_index),
+            sheet,
+            &png_metadata,
+            &settings.png_encoder,
+        )
+    })?;
+    Ok(geometry.poster_page_filepath(0))
+}
+
+/// Adds the pattern for the given image as a new page to the given pdf. Depending on the output
+/// format the page either contains the composited pattern or the tile once together with a pdf
+/// tiling pattern that repeats it. Posters are added as one page per sheet.
+fn add_pattern_pdf_page(
+    pdf_writer: &mut PdfWriter,
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+) -> Result<(), String> {
+    let (mut tile, png_metadata) = prepare_tile(image, settings)?;
+    let icc_profile = png_metadata
+        .get("iCCP")
+        .and_then(|chunk| icc_profile_from_iccp_chunk(chunk));
+
+    if let Some(poster) = &geometry.poster {
+        let page_size = PageSize {
+            width_mm: poster.paper_width_mm,
+            height_mm: poster.paper_height_mm,
+            bleed_mm: 0.0,
+            margin_mm: 0.0,
+        };
+        return compose_poster_sheets(image, geometry, poster, tile, |_, sheet| {
+            pdf_writer.add_page(sheet, icc_profile, &page_size)
+        });
+    }
+
+    if geometry.output_format == OutputFormat::PdfTilingPattern {
+        let tiling = TilingGeometry::new(image, &geometry.placement);
+        let tile = tile_with_gutter(tile, &geometry.placement);
+        pdf_writer.add_tiling_pattern_page(
+            &tile,
+            icc_profile,
+            &tiling,
+            &geometry.page_size(),
+            geometry.print_marks.as_ref(),
+        )
+    } else {
+        if geometry.print_marks.is_some() {
+            tile = tile_with_direct_color(tile)?;
+        }
+        let bitmap = create_pattern_bitmap(
+            &tile,
+            geometry.pixel_width,
+            geometry.pixel_height,
+            &geometry.placement,
+            geometry.ground_layer(image).as_ref(),
+            geometry.raster_print_marks(image).as_ref(),
+        );
+        let bitmap = without_ground_alpha(image, bitmap)?;
+        pdf_writer.add_page(&bitmap, icc_profile, &geometry.page_size())
+    }
+}
+
+/// Writes an svg that repeats the tile with a `<pattern>` element. The tile is either embedded or
+/// written as separate png file next to the svg.
+fn write_pattern_svg(
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+) -> Result<(), String> {
+    let tiling = TilingGeometry::new(image, &geometry.placement);
+    let (tile, png_metadata) = prepare_tile(image, settings)?;
+    let tile = tile_with_gutter(tile, &geometry.placement);
+
+    if settings.svg_link_tile {
+        let tile_filepath = format!(
+            "{}__tile.png",
+            geometry
+                .output_filepath
+                .trim_end_matches(geometry.output_format.extension())
+        );
+        encode_png(&tile, &tile_filepath, &png_metadata, &settings.png_encoder).map_err(
+            |error| {
+                format!(
+                    "Could not write png file to '{}' : {}",
+                    tile_filepath, error
+                )
+            },
+        )?;
+        let tile_filename = system::path_to_filename(&tile_filepath);
+        svg::write_pattern_svg(
+            &geometry.output_filepath,
+            svg::SvgTileSource::Linked(&tile_filename),
+            &tiling,
+            geometry.width_mm,
+            geometry.height_mm,
+            geometry.print_marks.as_ref(),
+        )?;
+    } else {
+        let mut png_data = Vec::new();
+        encode_png_to_writer(&tile, &mut png_data, &png_metadata, &settings.png_encoder).map_err(
+            |error| format!("Could not encode tile of '{}' : {}", image.filepath, error),
+        )?;
+        svg::write_pattern_svg(
+            &geometry.output_filepath,
+            svg::SvgTileSource::Embedded(&png_data),
+            &tiling,
+            geometry.width_mm,
+            geometry.height_mm,
+            geometry.print_marks.as_ref(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the pattern with the given geometry in its output format. Returns the output filepath
+/// on success.
+fn write_pattern_file(
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+) -> Result<String, String> {
+    match geometry.output_format {
+        OutputFormat::Pdf | OutputFormat::PdfTilingPattern => {
+            let _timer = ct_lib::TimerScoped::new_scoped("Writing", true);
+            let mut pdf_writer = PdfWriter::create(&geometry.output_filepath)?;
+            add_pattern_pdf_page(&mut pdf_writer, image, settings, geometry)?;
+            pdf_writer.finish()?;
+        }
+        OutputFormat::Svg => write_pattern_svg(image, settings, geometry)?,
+        OutputFormat::BigTiff => {
+            if settings.output_format != OutputFormat::BigTiff {
+                log::info!(
+                    "Writing '{}' as BigTIFF because it is too large for png",
+                    geometry.output_filepath
+                );
+            }
+            write_pattern_bigtiff(image, settings, geometry)?;
+        }
+        OutputFormat::Png => {
+            if let Some(poster) = &geometry.poster {
+                return write_poster_png(image, settings, geometry, poster);
+            }
+            let pattern = compose_pattern(image, settings, geometry)?;
+            write_pattern_png(
+                &geometry.output_filepath,
+                &pattern.bitmap,
+                &pattern.png_metadata,
+                &settings.png_encoder,
+            )?;
+        }
+    }
+    Ok(geometry.output_filepath.clone())
+}
+
+/// Creates the directory that the given file goes into if it does not exist yet
+fn create_output_directory_for(filepath: &str) -> Result<(), String> {
+    let directory = system::path_without_filename(filepath);
+    std::fs::create_dir_all(&directory)
+        .map_err(|error| format!("Could not create directory '{}' : {}", directory, error))
+}
+
+/// Writes every wallpaper or mural panel into its own numbered file. Returns the filepath of the
+/// first panel on success.
+fn write_panels(
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+    panels: &PanelLayout,
+) -> Result<String, String> {
+    let mut first_panel_filepath = String::new();
+    for panel_index in 0..panels.panel_count {
+        let panel_geometry = geometry.panel_geometry(image, panels, panel_index);
+        if panel_index == 0 {
+            create_output_directory_for(&panel_geometry.output_filepath)?;
+        }
+        let panel_filepath = write_pattern_file(image, settings, &panel_geometry)?;
+        if panel_index == 0 {
+            first_panel_filepath = panel_filepath;
+        }
+    }
+    Ok(first_panel_filepath)
+}
+
+/// Writes a pattern for the given image using the given settings.
+/// Returns the output filepath on success.
+fn create_pattern_for_image(
+    image: &InputImage,
+    settings: &PatternSettings,
+    job_date: &str,
+) -> Result<String, String> {
+    let geometry = PatternGeometry::new(image, settings, job_date);
+    geometry.check_output_size(image, settings)?;
+    let encoded_size_bytes = geometry.size_estimate(image).encoded_size_bytes;
+
+    let _timer = ct_lib::TimerScoped::new_scoped("Creating pattern", true);
+    let pattern_timer = PatternTimer::start(geometry.output_format, encoded_size_bytes);
+    let output_filepath = match &geometry.panels {
+        Some(panels) => write_panels(image, settings, &geometry, panels),
+        None => write_pattern_file(image, settings, &geometry),
+    }?;
+    pattern_timer.record();
+    Ok(output_filepath)
+}
+
+/// Writes the pattern as tiled BigTIFF. The pattern is composited row by row while writing so that
+/// it never needs to be in memory completely.
+fn write_pattern_bigtiff(
+    image: &InputImage,
+    settings: &PatternSettings,
+    geometry: &PatternGeometry,
+) -> Result<(), String> {
+    let (mut tile, png_metadata) = prepare_tile(image, settings)?;
+    // NOTE: TIFF has no equivalent to the png transparency chunk so we use an alpha channel
+    if tile.transparency.is_some() || geometry.print_marks.is_some() {
+        tile = tile_with_direct_color(tile)?;
+    }
+    let print_marks = geometry.raster_print_marks(image);
+    let (paper_pixel, ink_pixel) = if print_marks.is_some() {
+        (tile.opaque_gray_pixel(255), tile.opaque_gray_pixel(0))
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    // NOTE: A pattern that is flattened onto a ground image stays opaque RGBA as the rows are
+    //       written right after compositing
+    let ground = geometry.ground_layer(image);
+    let icc_profile = png_metadata
+        .get("iCCP")
+        .and_then(|chunk| icc_profile_from_iccp_chunk(chunk));
+    let info = bigtiff::BigTiffImageInfo {
+        width: geometry.pixel_width as usize,
+        height: geometry.pixel_height as usize,
+        format: &tile,
+        ppi: if settings.metadata.copy_dpi {
+            image.ppi
+        } else {
+            None
+        },
+        icc_profile,
+    };
+
+    let _timer = ct_lib::TimerScoped::new_scoped("Compositing and writing", true);
+    bigtiff::write_tiled_bigtiff(
+        &geometry.output_filepath,
+        &info,
+        |start_index, row_buffer| {
+            copy_pixels_tiled(
+                &tile,
+                &geometry.placement,
+                geometry.pixel_width,
+                row_buffer,
+                start_index,
+            );
+            if let Some(ground) = &ground {
+                ground.draw(&tile, row_buffer, start_index);
+            }
+            if let Some(print_marks) = &print_marks {
+                print_marks.draw(row_buffer, start_index, &paper_pixel, &ink_pixel);
+            }
+        },
+    )
+}
+
+/// Loads the given image together with the other motifs of the settings and applies the
+/// settings that change how the image is measured
+fn load_input_image(
+    image_filepath: &str,
+    settings: &PatternSettings,
+) -> Result<InputImage, String> {
+    let mut image = InputImage::new(image_filepath)?
+        .with_motifs(settings)?
+        .with_variants(settings)?
+        .with_ground(settings)?;
+    image.set_ppi_override(settings.ppi_override);
+    Ok(image)
+}
+
+/// Loads the given image and writes a pattern for it using the given settings.
+/// Returns the output filepath on success.
+fn create_pattern_from_file(
+    image_filepath: &str,
+    settings: &PatternSettings,
+    job_date: &str,
+) -> Result<String, String> {
+    let image = load_input_image(image_filepath, settings)?;
+    create_pattern_for_image(&image, settings, job_date)
+}
+
+/// Loads the given image and adds its pattern as a new page to the given pdf.
+/// Returns the pdf filepath on success.
+fn add_pattern_pdf_page_from_file(
+    image_filepath: &str,
+    settings: &PatternSettings,
+    job_date: &str,
+    pdf_writer: &Mutex<PdfWriter>,
+) -> Result<String, String> {
+    let image = load_input_image(image_filepath, settings)?;
+    let geometry = PatternGeometry::new(&image, settings, job_date);
+    geometry.check_output_size(&image, settings)?;
+    let encoded_size_bytes = geometry.size_estimate(&image).encoded_size_bytes;
+
+    let mut pdf_writer = pdf_writer
+        .lock()
+        .map_err(|_| "Could not access pdf file of a previously failed batch".to_string())?;
+    let _timer = ct_lib::TimerScoped::new_scoped("Adding pdf page", true);
+    let pattern_timer = PatternTimer::start(geometry.output_format, encoded_size_bytes);
+    match &geometry.panels {
+        Some(panels) => {
+            for panel_index in 0..panels.panel_count {
+                let panel_geometry = geometry.panel_geometry(&image, panels, panel_index);
+                add_pattern_pdf_page(&mut pdf_writer, &image, settings, &panel_geometry)?;
+            }
+        }
+        None => add_pattern_pdf_page(&mut pdf_writer, &image, settings, &geometry)?,
+    }
+    pattern_timer.record();
+    Ok(pdf_writer.filepath().to_string())
+}
+
+/// Completes the pdf that contains all patterns of a batch. Returns the pdf filepath on success.
+fn finish_batch_pdf(pdf_writer: Mutex<PdfWriter>) -> Result<String, String> {
+    let pdf_writer = pdf_writer
+        .into_inner()
+        .map_err(|_| "Could not access pdf file of a previously failed batch".to_string())?;
+    let pdf_filepath = pdf_writer.filepath().to_string();
+    if pdf_writer.page_count() == 0 {
+        drop(pdf_writer);
+        std::fs::remove_file(&pdf_filepath).ok();
+        return Err(format!(
+            "Could not write pdf file '{}' : No pattern was created",
+            pdf_filepath
+        ));
+    }
+    pdf_writer.finish()?;
+    Ok(pdf_filepath)
+}
+
+/// Returns the filepath of the pdf that contains all patterns of a batch, i.e.
+/// `<output_dir>/<first_image_name>__<image_count>_patterns.pdf`
+fn batch_pdf_filepath(settings: &PatternSettings, image_filepaths: &[String]) -> String {
+    get_image_output_filepath(
+        &settings.output_dir(),
+        image_filepaths
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default(),
+        &format!("__{}_patterns.pdf", image_filepaths.len()),
+    )
+}
+
+/// Writes the pattern for the given image once for each compression level and filter mode and
+/// logs the resulting timings and file sizes. The written files are removed afterwards.
+fn benchmark_png_encoder_for_file(
+    image_filepath: &str,
+    settings: &PatternSettings,
+) -> Result<(), String> {
+    let image = load_input_image(image_filepath, settings)?;
+    let geometry = PatternGeometry::new(&image, settings, &job_date_text());
+    geometry.check_output_size(&image, settings)?;
+    // NOTE: Panels are written one by one so we only benchmark the first one
+    let geometry = match &geometry.panels {
+        Some(panels) => {
+            let panel_geometry = geometry.panel_geometry(&image, panels, 0);
+            create_output_directory_for(&panel_geometry.output_filepath)?;
+            panel_geometry
+        }
+        None => geometry,
+    };
+    let pattern = compose_pattern(&image, settings, &geometry)?;
+    let benchmark_filepath = format!(
+        "{}__benchmark.png",
+        geometry
+            .output_filepath
+            .trim_end_matches(geometry.output_format.extension())
+    );
+
+    log::info!("Png encoder benchmark for '{}':", image_filepath);
+    for &compression_level in &[
+        PngCompressionLevel::Fast,
+        PngCompressionLevel::Default,
+        PngCompressionLevel::High,
+    ] {
+        for &filter_mode in &[PngFilterMode::Adaptive, PngFilterMode::None] {
+            let encoder_options = PngEncoderOptions {
+                compression_level,
+                filter_mode,
+                ..settings.png_encoder
+            };
+            // NOTE: This logs the encoder options and the encoding time
+            write_pattern_png(
+                &benchmark_filepath,
+                &pattern.bitmap,
+                &pattern.png_metadata,
+                &encoder_options,
+            )?;
+            let filesize = std::fs::metadata(&benchmark_filepath)
+                .map(|metadata| metadata.len())
+                .unwrap_or(0);
+            log::info!("Wrote {:.2}MB", filesize as f64 / (1024.0 * 1024.0));
+        }
+    }
+    std::fs::remove_file(&benchmark_filepath).ok();
+
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Batch processing
+
+#[derive(Debug, Clone)]
+enum BatchStatus {
+    Queued,
+    Running,
+    Succeeded { output_filepath: String },
+    Failed { error_message: String },
+}
+
+struct BatchEntry {
+    filepath: String,
+    status: BatchStatus,
+}
+
+impl BatchEntry {
+    fn new(filepath: &str) -> BatchEntry {
+        BatchEntry {
+            filepath: filepath.to_string(),
+            status: BatchStatus::Queued,
+        }
+    }
+}
+
+fn batch_success_and_failure_count(entries: &[BatchEntry]) -> (usize, usize) {
+    let success_count = entries
+        .iter()
+        .filter(|entry| matches!(entry.status, BatchStatus::Succeeded { .. }))
+        .count();
+    let failure_count = entries
+        .iter()
+        .filter(|entry| matches!(entry.status, BatchStatus::Failed { .. }))
+        .count();
+    (success_count, failure_count)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// GUI
+
+use iced::{
+    button, scrollable, text_input, Align, Application, Button, Checkbox, Column, Command, Element,
+    Length::FillPortion, Radio, Row, Scrollable, Settings, Subscription, Text, TextInput,
+};
+
+const LABEL_SIZE_DEFAULT: u16 = 20;
+const LABEL_SIZE_INVALID: u16 = 25;
+const COLOR_DEFAULT: iced::Color = iced::Color::BLACK;
+const COLOR_INVALID: iced::Color = iced::Color::from_rgb(1.0, 0.0, 0.0);
+const DEFAULT_PPI: f64 = 72.0;
+/// The GUI state is written to disk at most once in this interval
+const GUI_STATE_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+enum GuiEvent {
+    ChangedRepeatCountX(String),
+    ChangedRepeatCountY(String),
+    ChangedDimensionMillimeterX(String),
+    ChangedDimensionMillimeterY(String),
+    ChangedPpiOverride(String),
+    ChangedBleed(String),
+    ChangedPosterOverlap(String),
+    ChangedWallpaperRollWidth(String),
+    ChangedWallpaperRollLength(String),
+    ChangedGutterX(String),
+    ChangedGutterY(String),
+    ChangedBackgroundColor(String),
+    ChangedMuralWallWidth(String),
+    ChangedMuralWallHeight(String),
+    ChangedMuralPanelWidth(String),
+    ChangedMuralOverlap(String),
+    ChangedTossCellWidth(String),
+    ChangedTossCellHeight(String),
+    ChangedTossSpacing(String),
+    ChangedTossSeed(String),
+    ChangedVariationSeed(String),
+    ChangedLengthUnit(LengthUnit),
+    ChangedOutputDir(String),
+    ChangedAspectLock(AspectLock),
+    ChangedSnapPolicy(SnapPolicy),
+    ChangedTileLayout(TileLayout),
+    ChangedGutterUnit(GutterUnit),
+    ChangedOutputColorType(OutputColorType),
+    ChangedOutputFormat(OutputFormat),
+    ChangedPosterPaperSize(PosterPaperSize),
+    ChangedWallpaperMatch(WallpaperMatch),
+    ToggledWallpaper(bool),
+    ToggledMural(bool),
+    ToggledToss(bool),
+    ToggledTossRotation(bool),
+    ToggledVariation(bool),
+    ToggledVariationMirror(bool),
+    ToggledVariationRotate(bool),
+    ToggledPdfSingleFileForBatch(bool),
+    ToggledSvgLinkTile(bool),
+    ToggledPrintMarks(bool),
+    ToggledFlatten(bool),
+    ChangedFlattenColor(String),
+    ChangedGroundImage(String),
+    ChangedPngCompressionLevel(PngCompressionLevel),
+    ChangedPngFilterMode(PngFilterMode),
+    ChangedPngChunkSize(String),
+    ChangedPngThreadCount(String),
+    ToggledKeepWholeRepeats(bool),
+    PressedStartButton,
+    PressedSaveJobButton,
+    PressedRecentFileButton(usize),
+    PressedPresetButton(usize),
+    ChangedMotifLayout(String),
+    ChangedMotifFilepath(usize, String),
+    ChangedVariantFilepath(usize, String),
+    ChangedPresetName(String),
+    PressedSavePresetButton,
+    FinishedBatchEntry(usize, Result<String, String>),
+    /// The estimate for the given image generation and settings
+    FinishedOutputEstimate(u64, PatternSettings, OutputEstimate),
+    FinishedToss(u64, String, Result<InputImage, String>),
+    WindowEvent(iced_native::Event),
+}
+
+enum ProcessState {
+    Idle,
+    Running,
+    Finished,
+}
+impl Default for ProcessState {
+    fn default() -> Self {
+        ProcessState::Idle
+    }
+}
+
+#[derive(Default)]
+struct RepeatyGui {
+    /// Shared with the background tasks that compute the output estimate
+    image: Option<Arc<InputImage>>,
+    /// Incremented whenever another image is loaded so that results of background tasks for the
+    /// previous image can be ignored
+    image_generation: u64,
+    settings: PatternSettings,
+
+    /// Contains all files of the last drag and drop operation. If there is more than one entry we
+    /// are in batch mode and `image` is only used as a preview for the first entry.
+    batch_entries: Vec<BatchEntry>,
+    file_drop_starts_new_batch: bool,
+    /// Collects the patterns of the running batch if they are written into a single pdf
+    batch_pdf_writer: Option<Arc<Mutex<PdfWriter>>>,
+    /// Date for the slug line of all patterns of the running batch
+    batch_date: String,
+    /// Estimate for the current image together with the settings it was computed for
+    output_estimate: Option<(PatternSettings, OutputEstimate)>,
+    /// The estimate is computed in the background as it compresses a sample of the pattern
+    output_estimate_is_running: bool,
+    /// Image that is waiting to be loaded with tossed motifs. Tossing runs in the background as it
+    /// can take a while for large cells. Only one toss runs at a time and only the latest request
+    /// is kept.
+    requested_toss_filepath: Option<String>,
+    toss_is_running: bool,
+
+    repeat_x_text: String,
+    repeat_y_text: String,
+
+    dim_mm_x_text: String,
+    dim_mm_y_text: String,
+
+    ppi_override_text: String,
+    motif_layout_text: String,
+    /// Filepaths of the motifs `B`, `C` and `D`
+    motif_filepath_texts: [String; 3],
+    bleed_text: String,
+    gutter_x_text: String,
+    gutter_y_text: String,
+    background_color_text: String,
+    flatten_color_text: String,
+    ground_image_text: String,
+    poster_overlap_text: String,
+    wallpaper_roll_width_text: String,
+    wallpaper_roll_length_text: String,
+    mural_wall_width_text: String,
+    mural_wall_height_text: String,
+    mural_panel_width_text: String,
+    mural_overlap_text: String,
+    toss_cell_width_text: String,
+    toss_cell_height_text: String,
+    toss_spacing_text: String,
+    toss_seed_text: String,
+    variation_seed_text: String,
+    /// Filepaths of the variant tiles
+    variant_filepath_texts: [String; 3],
+    output_dir_text: String,
+    png_chunk_size_text: String,
+    png_thread_count_text: String,
+
+    length_unit: LengthUnit,
+    recent_filepaths: Vec<String>,
+    last_saved_gui_state: GuiState,
+    last_gui_state_save_time: Option<std::time::Instant>,
+    /// Built-in presets followed by the user presets
+    presets: Vec<Preset>,
+    preset_name_text: String,
+
+    start_button_widget: button::State,
+    save_job_button_widget: button::State,
+    recent_file_button_widgets: Vec<button::State>,
+    preset_button_widgets: Vec<button::State>,
+    save_preset_button_widget: button::State,
+    preset_name_widget: text_input::State,
+
+    repeat_x_widget: text_input::State,
+    repeat_y_widget: text_input::State,
+
+    dim_mm_x_widget: text_input::State,
+    dim_mm_y_widget: text_input::State,
+
+    ppi_override_widget: text_input::State,
+    motif_layout_widget: text_input::State,
+    motif_filepath_widgets: [text_input::State; 3],
+    bleed_widget: text_input::State,
+    gutter_x_widget: text_input::State,
+    gutter_y_widget: text_input::State,
+    background_color_widget: text_input::State,
+    flatten_color_widget: text_input::State,
+    ground_image_widget: text_input::State,
+    poster_overlap_widget: text_input::State,
+    wallpaper_roll_width_widget: text_input::State,
+    wallpaper_roll_length_widget: text_input::State,
+    mural_wall_width_widget: text_input::State,
+    mural_wall_height_widget: text_input::State,
+    mural_panel_width_widget: text_input::State,
+    mural_overlap_widget: text_input::State,
+    toss_cell_width_widget: text_input::State,
+    toss_cell_height_widget: text_input::State,
+    toss_spacing_widget: text_input::State,
+    toss_seed_widget: text_input::State,
+    variation_seed_widget: text_input::State,
+    variant_filepath_widgets: [text_input::State; 3],
+    output_dir_widget: text_input::State,
+    png_chunk_size_widget: text_input::State,
+    png_thread_count_widget: text_input::State,
+
+    batch_scroll_widget: scrollable::State,
+
+    process_state: ProcessState,
+
+    current_error: Option<String>,
+    current_info: Option<String>,
+}
+
+impl RepeatyGui {
+    fn new(arguments: CommandlineArguments) -> RepeatyGui {
+        let mut result = RepeatyGui::default();
+        result.restore_gui_state(GuiState::load());
+        result.reload_presets();
+
+        if let Some(job_filepath) = &arguments.job_filepath {
+            result.load_job(job_filepath);
+        }
+        if let Some(preset) = &arguments.preset {
+            preset.apply_to(&mut result.settings);
+        }
+        arguments
+            .png_encoder
+            .apply_to(&mut result.settings.png_encoder);
+        result.refresh_optional_text_fields();
+        // NOTE: Images given on the commandline replace the inputs of the job like they do
+        //       without gui
+        if !arguments.input_paths.is_empty() {
+            result.batch_entries.clear();
+            result.set_image(None);
+        }
+        for input_path in &arguments.input_paths {
+            result.add_dropped_path(input_path);
+        }
+
+        result
+    }
+
+    fn restore_gui_state(&mut self, gui_state: GuiState) {
+        self.settings = gui_state.settings.clone();
+        self.length_unit = gui_state.length_unit;
+        self.recent_filepaths = gui_state.recent_filepaths.clone();
+        self.recent_file_button_widgets = self
+            .recent_filepaths
+            .iter()
+            .map(|_| button::State::default())
+            .collect();
+        self.refresh_optional_text_fields();
+        self.last_saved_gui_state = gui_state;
+    }
+
+    fn current_gui_state(&self) -> GuiState {
+        GuiState {
+            length_unit: self.length_unit,
+            recent_filepaths: self.recent_filepaths.clone(),
+            settings: self.settings.clone(),
+        }
+    }
+
+    fn set_image(&mut self, image: Option<InputImage>) {
+        self.image = image.map(Arc::new);
+        self.image_generation += 1;
+        self.output_estimate = None;
+    }
+
+    /// Applies the settings that change how the loaded image is measured
+    fn apply_settings_to_image(&mut self) {
+        if let Some(image) = &mut self.image {
+            let image = Arc::make_mut(image);
+            image.set_ppi_override(self.settings.ppi_override);
+        }
+    }
+
+    /// Starts recomputing the output estimate in the background if the settings changed since it
+    /// was computed. Only one estimate is computed at a time. The estimate is reset whenever a new
+    /// image is loaded or a pattern was created.
+    fn request_output_estimate(&mut self) -> Command<GuiEvent> {
+        if self.output_estimate_is_running {
+            return Command::none();
+        }
+        let image = match &self.image {
+            Some(image) => image.clone(),
+            None => return Command::none(),
+        };
+        let is_outdated = match &self.output_estimate {
+            Some((estimate_settings, _)) => *estimate_settings != self.settings,
+            None => true,
+        };
+        if !is_outdated {
+            return Command::none();
+        }
+
+        self.output_estimate_is_running = true;
+        let image_generation = self.image_generation;
+        let settings = self.settings.clone();
+        Command::perform(
+            async move {
+                let estimate = OutputEstimate::new(&image, &settings);
+                (settings, estimate)
+            },
+            move |(settings, estimate)| {
+                GuiEvent::FinishedOutputEstimate(image_generation, settings, estimate)
+            },
+        )
+    }
+
+    fn save_gui_state_if_changed(&mut self) {
+        let gui_state = self.current_gui_state();
+        if gui_state != self.last_saved_gui_state {
+            gui_state.save();
+            self.last_saved_gui_state = gui_state;
+        }
+    }
+
+    /// Saves the GUI state right away if it changed unless it was already saved within the save
+    /// interval. This keeps typing into a text field from rewriting the state file on every
+    /// keystroke. A change that falls into the interval is saved by the first event after it, i.e.
+    /// when the mouse moves.
+    /// NOTE: iced does not report closing the window so we cannot wait for it
+    fn save_gui_state_throttled(&mut self) {
+        let interval_elapsed = match self.last_gui_state_save_time {
+            Some(save_time) => save_time.elapsed() >= GUI_STATE_SAVE_INTERVAL,
+            None => true,
+        };
+        if interval_elapsed && self.current_gui_state() != self.last_saved_gui_state {
+            self.save_gui_state_if_changed();
+            self.last_gui_state_save_time = Some(std::time::Instant::now());
+        }
+    }
+
+    fn add_recent_filepath(&mut self, filepath: &str) {
+        gui_state::add_recent_filepath(&mut self.recent_filepaths, filepath);
+        self.recent_file_button_widgets = self
+            .recent_filepaths
+            .iter()
+            .map(|_| button::State::default())
+            .collect();
+    }
+
+    fn refresh_optional_text_fields(&mut self) {
+        self.ppi_override_text = self
+            .settings
+            .ppi_override
+            .map(pretty_print_float)
+            .unwrap_or_default();
+        self.bleed_text = pretty_print_float(self.settings.bleed_mm);
+        self.motif_layout_text = self.settings.motif_layout.clone();
+        for (index, text) in self.motif_filepath_texts.iter_mut().enumerate() {
+            *text = self.settings.motifs.get(index).cloned().unwrap_or_default();
+        }
+        self.gutter_x_text = pretty_print_float(self.settings.gutter.horizontal);
+        self.gutter_y_text = pretty_print_float(self.settings.gutter.vertical);
+        self.background_color_text = self
+            .settings
+            .gutter
+            .background_color
+            .map(hex_color_text)
+            .unwrap_or_default();
+        self.flatten_color_text = hex_color_text(self.settings.flatten.background_color);
+        self.ground_image_text = self
+            .settings
+            .flatten
+            .ground_image
+            .clone()
+            .unwrap_or_default();
+        self.poster_overlap_text = pretty_print_float(self.settings.poster.overlap_mm);
+        self.wallpaper_roll_width_text = pretty_print_float(self.settings.wallpaper.roll_width_mm);
+        self.wallpaper_roll_length_text =
+            pretty_print_float(self.settings.wallpaper.roll_length_mm);
+        self.mural_wall_width_text = pretty_print_float(self.settings.mural.wall_width_mm);
+        self.mural_wall_height_text = pretty_print_float(self.settings.mural.wall_height_mm);
+        self.mural_panel_width_text = pretty_print_float(self.settings.mural.panel_width_mm);
+        self.mural_overlap_text = pretty_print_float(self.settings.mural.overlap_mm);
+        self.toss_cell_width_text = pretty_print_float(self.settings.toss.cell_width_mm);
+        self.toss_cell_height_text = pretty_print_float(self.settings.toss.cell_height_mm);
+        self.toss_spacing_text = pretty_print_float(self.settings.toss.spacing_mm);
+        self.toss_seed_text = self.settings.toss.seed.to_string();
+        self.variation_seed_text = self.settings.variation.seed.to_string();
+        for (index, text) in self.variant_filepath_texts.iter_mut().enumerate() {
+            *text = self
+                .settings
+                .variation
+                .variants
+                .get(index)
+                .cloned()
+                .unwrap_or_default();
+        }
+        self.output_dir_text = self.settings.output_dir.clone().unwrap_or_default();
+        self.png_chunk_size_text = self.settings.png_encoder.chunk_size_kib.to_string();
+        self.png_thread_count_text = self.settings.png_encoder.thread_count.to_string();
+    }
+
+    fn reload_presets(&mut self) {
+        self.presets = preset::all_presets();
+        self.preset_button_widgets = self
+            .presets
+            .iter()
+            .map(|_| button::State::default())
+            .collect();
+    }
+
+    /// Fills in the values of the preset and derives the repeat counts from its dimensions
+    fn apply_preset(&mut self, preset_index: usize) {
+        let preset = match self.presets.get(preset_index) {
+            Some(preset) => preset.clone(),
+            None => return,
+        };
+        preset.apply_to(&mut self.settings);
+        self.apply_settings_to_image();
+        self.set_dim_mm_x(preset.dim_mm_x);
+        self.set_dim_mm_y(preset.dim_mm_y);
+        self.dim_mm_x_text = self.dimension_text(self.settings.dim_mm_x);
+        self.dim_mm_y_text = self.dimension_text(self.settings.dim_mm_y);
+        self.refresh_optional_text_fields();
+        self.process_state = ProcessState::Idle;
+        self.preset_name_text = preset.name.clone();
+        self.current_info = Some(format!("Applied preset '{}'", preset.name));
+    }
+
+    fn save_preset(&mut self) {
+        let preset = Preset::from_settings(self.preset_name_text.trim(), &self.settings);
+        let preset_name = preset.name.clone();
+        match preset::save_user_preset(preset) {
+            Ok(()) => {
+                self.reload_presets();
+                self.current_error = None;
+                self.current_info = Some(format!("Saved preset '{}'", preset_name));
+            }
+            Err(error_message) => self.current_error = Some(error_message),
+        }
+    }
+
+    fn dimension_text(&self, dim_mm: f64) -> String {
+        pretty_print_float(self.length_unit.from_millimeter(dim_mm))
+    }
+
+    fn load_job(&mut self, job_filepath: &str) {
+        let job = match PatternJob::load_from_file(job_filepath) {
+            Ok(job) => job,
+            Err(error_message) => {
+                self.current_error = Some(error_message);
+                return;
+            }
+        };
+
+        self.settings = job.settings;
+        self.refresh_optional_text_fields();
+        self.add_recent_filepath(job_filepath);
+        self.set_image(None);
+        self.batch_entries.clear();
+        for input_path in &job.inputs {
+            self.add_dropped_path(input_path);
+        }
+        self.current_info = Some(format!("Loaded job '{}'", job_filepath));
+    }
+
+    fn save_job(&mut self) {
+        let first_input_filepath = match self.batch_entries.first() {
+            Some(entry) => entry.filepath.clone(),
+            None => return,
+        };
+        let job = PatternJob {
+            inputs: self
+                .batch_entries
+                .iter()
+                .map(|entry| entry.filepath.clone())
+                .collect(),
+            settings: self.settings.clone(),
+        };
+        let job_filepath = get_image_output_filepath(
+            &self.settings.output_dir(),
+            &first_input_filepath,
+            "__repeaty_job",
+        ) + ".toml";
+
+        match job.save_to_file(&job_filepath) {
+            Ok(()) => {
+                self.current_error = None;
+                self.current_info = Some(format!("Saved job to '{}'", job_filepath));
+            }
+            Err(error_message) => self.current_error = Some(error_message),
+        }
+    }
+
+    fn is_batch_mode(&self) -> bool {
+        self.batch_entries.len() > 1
+    }
+
+    fn add_dropped_path(&mut self, path: &str) {
+        if is_job_filepath(path) {
+            self.load_job(path);
+            return;
+        }
+
+        if self.file_drop_starts_new_batch {
+            self.file_drop_starts_new_batch = false;
+            self.batch_entries.clear();
+        }
+
+        let is_first_drop = self.batch_entries.is_empty();
+        for filepath in collect_image_filepaths(path) {
+            self.batch_entries.push(BatchEntry::new(&filepath));
+        }
+
+        if is_first_drop {
+            if let Some(first_entry) = self.batch_entries.first() {
+                let first_filepath = first_entry.filepath.clone();
+                self.load_image(&first_filepath);
+            } else {
+                self.current_error = Some(format!("No png images found in '{}'", path));
+            }
+        }
+        self.process_state = ProcessState::Idle;
+    }
+
+    /// Loads the first image of the batch again, i.e. after the motifs changed
+    fn reload_image(&mut self) {
+        if let Some(first_entry) = self.batch_entries.first() {
+            let first_filepath = first_entry.filepath.clone();
+            self.load_image(&first_filepath);
+        }
+    }
+
+    /// The tossed cell replaces the input image so it needs to be tossed again
+    fn reload_tossed_image(&mut self) {
+        self.process_state = ProcessState::Idle;
+        if self.settings.toss.enabled {
+            self.reload_image();
+        }
+    }
+
+    /// Images with tossed motifs are loaded in the background (see `request_toss`)
+    fn load_image(&mut self, image_filepath: &str) {
+        if self.settings.toss.enabled {
+            self.requested_toss_filepath = Some(image_filepath.to_string());
+            return;
+        }
+        self.requested_toss_filepath = None;
+        let image_result = load_input_image(image_filepath, &self.settings);
+        self.finish_loading_image(image_filepath, image_result);
+    }
+
+    /// Starts loading the requested image with tossed motifs in the background unless a toss is
+    /// already running. The next request is picked up once it finished.
+    fn request_toss(&mut self) -> Command<GuiEvent> {
+        if self.toss_is_running {
+            return Command::none();
+        }
+        let image_filepath = match self.requested_toss_filepath.take() {
+            Some(image_filepath) => image_filepath,
+            None => return Command::none(),
+        };
+
+        self.toss_is_running = true;
+        let image_generation = self.image_generation;
+        let settings = self.settings.clone();
+        Command::perform(
+            async move {
+                let image_result = load_input_image(&image_filepath, &settings);
+                (image_filepath, image_result)
+            },
+            move |(image_filepath, image_result)| {
+                GuiEvent::FinishedToss(image_generation, image_filepath, image_result)
+            },
+        )
+    }
+
+    fn finish_loading_image(
+        &mut self,
+        image_filepath: &str,
+        image_result: Result<InputImage, String>,
+    ) {
+        let image = match image_result {
+            Ok(image) => image,
+            Err(error_message) => {
+                self.current_error = Some(error_message);
+                return;
+            }
+        };
+        self.current_error = None;
+
+        self.set_image(Some(image));
+        self.process_state = ProcessState::Idle;
+        self.add_recent_filepath(image_filepath);
+        self.reapply_settings();
+    }
+
+    /// Re-computes the derived values of the current settings for the loaded image and refreshes
+    /// all text fields
+    fn reapply_settings(&mut self) {
+        self.apply_settings_to_image();
+
+        // NOTE: Job files and presets may not contain a locked aspect ratio so we lock the loaded
+        //       dimensions instead
+        let locked_aspect_ratio = self.settings.locked_aspect_ratio;
+        if !(locked_aspect_ratio.is_finite() && locked_aspect_ratio > 0.0) {
+            self.settings.locked_aspect_ratio = self.settings.current_aspect_ratio();
+        }
+
+        if self.settings.is_valid() {
+            match self.settings.size_mode {
+                SizeMode::RepeatCount => {
+                    self.set_repeat_x(self.settings.repeat_x);
+                    self.set_repeat_y(self.settings.repeat_y);
+                }
+                SizeMode::Dimension => {
+                    self.set_dim_mm_x(self.settings.dim_mm_x);
+                    self.set_dim_mm_y(self.settings.dim_mm_y);
+                }
+            }
+        } else {
+            self.set_repeat_x(5.0);
+            self.set_repeat_y(5.0);
+        }
+
+        self.repeat_x_text = pretty_print_float(self.settings.repeat_x);
+        self.repeat_y_text = pretty_print_float(self.settings.repeat_y);
+        self.dim_mm_x_text = self.dimension_text(self.settings.dim_mm_x);
+        self.dim_mm_y_text = self.dimension_text(self.settings.dim_mm_y);
+    }
+
+    fn set_repeat_x(&mut self, value: f64) {
+        if let Some(image) = &self.image {
+            let (input_width, _input_height, pixel_per_mm) =
+                image.width_height_pixel_per_mm(&self.settings.gutter);
+
+            self.settings.size_mode = SizeMode::RepeatCount;
+            self.settings.repeat_x = self.settings.locked_repeat_count(value);
+            if self.settings.repeat_x != value {
+                self.repeat_x_text = pretty_print_float(self.settings.repeat_x);
+            }
+            self.settings.dim_mm_x = self.settings.repeat_x * input_width / pixel_per_mm;
+            self.dim_mm_x_text = self.dimension_text(self.settings.dim_mm_x);
+
+            self.process_state = ProcessState::Idle;
+            self.apply_aspect_lock(true);
+        }
+    }
+    fn set_repeat_y(&mut self, value: f64) {
+        if let Some(image) = &self.image {
+            let (_input_width, input_height, pixel_per_mm) =
+                image.width_height_pixel_per_mm(&self.settings.gutter);
+
+            self.settings.size_mode = SizeMode::RepeatCount;
+            self.settings.repeat_y = self.settings.locked_repeat_count(value);
+            if self.settings.repeat_y != value {
+                self.repeat_y_text = pretty_print_float(self.settings.repeat_y);
+            }
+            self.settings.dim_mm_y = self.settings.repeat_y * input_height / pixel_per_mm;
+            self.dim_mm_y_text = self.dimension_text(self.settings.dim_mm_y);
+
+            self.process_state = ProcessState::Idle;
+            self.apply_aspect_lock(false);
+        }
+    }
+    fn set_dim_mm_x(&mut self, value: f64) {
+        if let Some(image) = &self.image {
+            let (input_width, _input_height, pixel_per_mm) =
+                image.width_height_pixel_per_mm(&self.settings.gutter);
+
+            self.settings.size_mode = SizeMode::Dimension;
+            self.settings.repeat_x = self
+                .settings
+                .snapped_repeat_count(value * pixel_per_mm / input_width);
+            self.settings.dim_mm_x = self.settings.repeat_x * input_width / pixel_per_mm;
+            self.repeat_x_text = pretty_print_float(self.settings.repeat_x);
+
+            self.process_state = ProcessState::Idle;
+            self.apply_aspect_lock(true);
+        }
+    }
+    fn set_dim_mm_y(&mut self, value: f64) {
+        if let Some(image) = &self.image {
+            let (_input_width, input_height, pixel_per_mm) =
+                image.width_height_pixel_per_mm(&self.settings.gutter);
+
+            self.settings.size_mode = SizeMode::Dimension;
+            self.settings.repeat_y = self
+                .settings
+                .snapped_repeat_count(value * pixel_per_mm / input_height);
+            self.settings.dim_mm_y = self.settings.repeat_y * input_height / pixel_per_mm;
+            self.repeat_y_text = pretty_print_float(self.settings.repeat_y);
+
+            self.process_state = ProcessState::Idle;
+            self.apply_aspect_lock(false);
+        }
+    }
+
+    /// Updates the values of the other axis after the horizontal or vertical values were changed
+    /// by the user and refreshes its text fields
+    fn apply_aspect_lock(&mut self, horizontal_values_changed: bool) {
+        let (input_width, input_height, pixel_per_mm) = match &self.image {
+            Some(image) => image.width_height_pixel_per_mm(&self.settings.gutter),
+            None => return,
+        };
+
+        match self.settings.aspect_lock {
+            AspectLock::Off => return,
+            AspectLock::RepeatCount => {
+                if horizontal_values_changed {
+                    self.settings.repeat_y = self.settings.repeat_x;
+                    self.settings.dim_mm_y = self.settings.repeat_y * input_height / pixel_per_mm;
+                } else {
+                    self.settings.repeat_x = self.settings.repeat_y;
+                    self.settings.dim_mm_x = self.settings.repeat_x * input_width / pixel_per_mm;
+                }
+            }
+            AspectLock::AspectRatio => {
+                let aspect_ratio = self.settings.locked_aspect_ratio;
+                if horizontal_values_changed {
+                    let dim_mm_y = self.settings.dim_mm_x * aspect_ratio;
+                    self.settings.repeat_y = self
+                        .settings
+                        .snapped_repeat_count(dim_mm_y * pixel_per_mm / input_height);
+                    self.settings.dim_mm_y = self.settings.repeat_y * input_height / pixel_per_mm;
+                } else {
+                    let dim_mm_x = self.settings.dim_mm_y / aspect_ratio;
+                    self.settings.repeat_x = self
+                        .settings
+                        .snapped_repeat_count(dim_mm_x * pixel_per_mm / input_width);
+                    self.settings.dim_mm_x = self.settings.repeat_x * input_width / pixel_per_mm;
+                }
+            }
+        }
+
+        if horizontal_values_changed {
+            self.repeat_y_text = pretty_print_float(self.settings.repeat_y);
+            self.dim_mm_y_text = self.dimension_text(self.settings.dim_mm_y);
+        } else {
+            self.repeat_x_text = pretty_print_float(self.settings.repeat_x);
+            self.dim_mm_x_text = self.dimension_text(self.settings.dim_mm_x);
+        }
+    }
+
+    fn set_aspect_lock(&mut self, aspect_lock: AspectLock) {
+        self.settings.aspect_lock = aspect_lock;
+        if aspect_lock == AspectLock::AspectRatio {
+            self.settings.locked_aspect_ratio = self.settings.current_aspect_ratio();
+        }
+        self.reapply_locked_values();
+    }
+
+    fn set_keep_whole_repeats(&mut self, keep_whole_repeats: bool) {
+        self.settings.keep_whole_repeats = keep_whole_repeats;
+        self.reapply_locked_values();
+    }
+
+    /// Makes the current horizontal values the reference for the vertical values
+    fn reapply_locked_values(&mut self) {
+        if !self.settings.is_valid() {
+            return;
+        }
+        let size_mode = self.settings.size_mode;
+        match size_mode {
+            SizeMode::RepeatCount => self.set_repeat_x(self.settings.repeat_x),
+            SizeMode::Dimension => self.set_dim_mm_x(self.settings.dim_mm_x),
+        }
+        self.repeat_x_text = pretty_print_float(self.settings.repeat_x);
+        self.dim_mm_x_text = self.dimension_text(self.settings.dim_mm_x);
+    }
+
+    fn start_batch(&mut self) -> Command<GuiEvent> {
+        for entry in self.batch_entries.iter_mut() {
+            entry.status = BatchStatus::Queued;
+        }
+        self.current_error = None;
+
+        self.batch_date = job_date_text();
+        self.batch_pdf_writer = None;
+        if self.settings.writes_single_pdf_for_batch() {
+            let image_filepaths: Vec<String> = self
+                .batch_entries
+                .iter()
+                .map(|entry| entry.filepath.clone())
+                .collect();
+            let pdf_filepath = batch_pdf_filepath(&self.settings, &image_filepaths);
+            match PdfWriter::create(&pdf_filepath) {
+                Ok(pdf_writer) => self.batch_pdf_writer = Some(Arc::new(Mutex::new(pdf_writer))),
+                Err(error_message) => {
+                    self.current_error = Some(error_message);
+                    return Command::none();
+                }
+            }
+        }
+
+        self.process_state = ProcessState::Running;
+        self.start_next_batch_entry()
+    }
+
+    /// NOTE: We process the entries one after another because every single pattern is already
+    ///       composited and encoded in parallel
+    fn start_next_batch_entry(&mut self) -> Command<GuiEvent> {
+        let next_index = self
+            .batch_entries
+            .iter()
+            .position(|entry| matches!(entry.status, BatchStatus::Queued));
+
+        if let Some(index) = next_index {
+            let entry = &mut self.batch_entries[index];
+            entry.status = BatchStatus::Running;
+
+            let image_filepath = entry.filepath.clone();
+            let settings = self.settings.clone();
+            let batch_date = self.batch_date.clone();
+            let batch_pdf_writer = self.batch_pdf_writer.clone();
+            Command::perform(
+                async move {
+                    match batch_pdf_writer {
+                        Some(pdf_writer) => add_pattern_pdf_page_from_file(
+                            &image_filepath,
+                            &settings,
+                            &batch_date,
+                            &pdf_writer,
+                        ),
+                        None => create_pattern_from_file(&image_filepath, &settings, &batch_date),
+                    }
+                },
+                move |result| GuiEvent::FinishedBatchEntry(index, result),
+            )
+        } else {
+            if let Some(pdf_writer) = self.batch_pdf_writer.take() {
+                let result = match Arc::try_unwrap(pdf_writer) {
+                    Ok(pdf_writer) => finish_batch_pdf(pdf_writer),
+                    Err(_) => Err("Could not finish pdf file that is still in use".to_string()),
+                };
+                match result {
+                    Ok(pdf_filepath) => {
+                        self.current_info =
+                            Some(format!("Wrote all patterns to '{}'", pdf_filepath))
+                    }
+                    Err(error_message) => {
+                        log::error!("{}", error_message);
+                        self.current_error = Some(error_message);
+                    }
+                }
+            }
+
+            let (success_count, failure_count) =
+                batch_success_and_failure_count(&self.batch_entries);
+            log::info!(
+                "Finished batch with {} successes and {} failures",
+                success_count,
+                failure_count
+            );
+            self.process_state = ProcessState::Finished;
+            self.output_estimate = None;
+            Command::none()
+        }
+    }
+
+    fn handle_gui_event(&mut self, message: GuiEvent) -> Command<GuiEvent> {
+        match message {
+            GuiEvent::ChangedRepeatCountX(value_str) => {
+                self.repeat_x_text = value_str;
+                if let Some(value) = self.repeat_x_text.parse::<f64>().ok() {
+                    self.set_repeat_x(value);
+                }
+            }
+            GuiEvent::ChangedRepeatCountY(value_str) => {
+                self.repeat_y_text = value_str;
+                if let Some(value) = self.repeat_y_text.parse::<f64>().ok() {
+                    self.set_repeat_y(value);
+                }
+            }
+            GuiEvent::ChangedDimensionMillimeterX(value_str) => {
+                self.dim_mm_x_text = value_str;
+                if let Some(value) = self.dim_mm_x_text.parse::<f64>().ok() {
+                    self.set_dim_mm_x(self.length_unit.to_millimeter(value));
+                }
+            }
+            GuiEvent::ChangedDimensionMillimeterY(value_str) => {
+                self.dim_mm_y_text = value_str;
+                if let Some(value) = self.dim_mm_y_text.parse::<f64>().ok() {
+                    self.set_dim_mm_y(self.length_unit.to_millimeter(value));
+                }
+            }
+            GuiEvent::ChangedPpiOverride(value_str) => {
+                self.ppi_override_text = value_str;
+                let ppi_override = parse_number_field(&self.ppi_override_text, |value| value > 0.0);
+                if self.ppi_override_text.is_empty() || ppi_override.is_some() {
+                    self.settings.ppi_override = ppi_override;
+                    self.reapply_settings();
+                }
+            }
+            GuiEvent::ChangedBleed(value_str) => {
+                self.bleed_text = value_str;
+                if let Some(bleed_mm) = parse_number_field(&self.bleed_text, |value| value >= 0.0) {
+                    self.settings.bleed_mm = bleed_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedPosterOverlap(value_str) => {
+                self.poster_overlap_text = value_str;
+                if let Some(overlap_mm) = parse_number_field(&self.poster_overlap_text, |value| {
+                    value >= 0.0 && value <= POSTER_MAX_OVERLAP_MM
+                }) {
+                    self.settings.poster.overlap_mm = overlap_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedLengthUnit(length_unit) => {
+                self.length_unit = length_unit;
+                self.dim_mm_x_text = self.dimension_text(self.settings.dim_mm_x);
+                self.dim_mm_y_text = self.dimension_text(self.settings.dim_mm_y);
+            }
+            GuiEvent::ChangedOutputDir(value_str) => {
+                self.output_dir_text = value_str;
+                self.settings.output_dir = if self.output_dir_text.is_empty() {
+                    None
+                } else {
+                    Some(self.output_dir_text.clone())
+                };
+            }
+            GuiEvent::ChangedAspectLock(aspect_lock) => {
+                self.set_aspect_lock(aspect_lock);
+            }
+            GuiEvent::ToggledKeepWholeRepeats(keep_whole_repeats) => {
+                self.set_keep_whole_repeats(keep_whole_repeats);
+            }
+            GuiEvent::ChangedSnapPolicy(snap_policy) => {
+                self.settings.snap_policy = snap_policy;
+                self.process_state = ProcessState::Idle;
+                if snap_policy == SnapPolicy::WholeRepeats {
+                    self.reapply_settings();
+                }
+            }
+            GuiEvent::ChangedTileLayout(tile_layout) => {
+                self.settings.tile_layout = tile_layout;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedGutterX(value_str) => {
+                self.gutter_x_text = value_str;
+                if let Some(gutter) = parse_number_field(&self.gutter_x_text, |value| value >= 0.0)
+                {
+                    self.settings.gutter.horizontal = gutter;
+                    self.process_state = ProcessState::Idle;
+                    self.reapply_settings();
+                }
+            }
+            GuiEvent::ChangedGutterY(value_str) => {
+                self.gutter_y_text = value_str;
+                if let Some(gutter) = parse_number_field(&self.gutter_y_text, |value| value >= 0.0)
+                {
+                    self.settings.gutter.vertical = gutter;
+                    self.process_state = ProcessState::Idle;
+                    self.reapply_settings();
+                }
+            }
+            GuiEvent::ChangedGutterUnit(unit) => {
+                self.settings.gutter.unit = unit;
+                self.process_state = ProcessState::Idle;
+                self.reapply_settings();
+            }
+            GuiEvent::ChangedBackgroundColor(value_str) => {
+                self.background_color_text = value_str;
+                let background_color = if self.background_color_text.trim().is_empty() {
+                    Some(None)
+                } else {
+                    parse_hex_color(&self.background_color_text).map(Some)
+                };
+                if let Some(background_color) = background_color {
+                    self.settings.gutter.background_color = background_color;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedOutputColorType(output_color_type) => {
+                self.settings.output_color_type = output_color_type;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedOutputFormat(output_format) => {
+                self.settings.output_format = output_format;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedWallpaperRollWidth(value_str) => {
+                self.wallpaper_roll_width_text = value_str;
+                if let Some(roll_width_mm) =
+                    parse_number_field(&self.wallpaper_roll_width_text, |value| value > 0.0)
+                {
+                    self.settings.wallpaper.roll_width_mm = roll_width_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedWallpaperRollLength(value_str) => {
+                self.wallpaper_roll_length_text = value_str;
+                if let Some(roll_length_mm) =
+                    parse_number_field(&self.wallpaper_roll_length_text, |value| value > 0.0)
+                {
+                    self.settings.wallpaper.roll_length_mm = roll_length_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedWallpaperMatch(match_type) => {
+                self.settings.wallpaper.match_type = match_type;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ToggledWallpaper(enabled) => {
+                self.settings.wallpaper.enabled = enabled;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedMuralWallWidth(value_str) => {
+                self.mural_wall_width_text = value_str;
+                if let Some(wall_width_mm) =
+                    parse_number_field(&self.mural_wall_width_text, |value| value > 0.0)
+                {
+                    self.settings.mural.wall_width_mm = wall_width_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedMuralWallHeight(value_str) => {
+                self.mural_wall_height_text = value_str;
+                if let Some(wall_height_mm) =
+                    parse_number_field(&self.mural_wall_height_text, |value| value > 0.0)
+                {
+                    self.settings.mural.wall_height_mm = wall_height_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedMuralPanelWidth(value_str) => {
+                self.mural_panel_width_text = value_str;
+                if let Some(panel_width_mm) =
+                    parse_number_field(&self.mural_panel_width_text, |value| {
+                        value > self.settings.mural.overlap_mm
+                    })
+                {
+                    self.settings.mural.panel_width_mm = panel_width_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedMuralOverlap(value_str) => {
+                self.mural_overlap_text = value_str;
+                if let Some(overlap_mm) = parse_number_field(&self.mural_overlap_text, |value| {
+                    value >= 0.0 && value < self.settings.mural.panel_width_mm
+                }) {
+                    self.settings.mural.overlap_mm = overlap_mm;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ToggledMural(enabled) => {
+                self.settings.mural.enabled = enabled;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ToggledToss(enabled) => {
+                self.settings.toss.enabled = enabled;
+                self.process_state = ProcessState::Idle;
+                self.reload_image();
+            }
+            GuiEvent::ToggledTossRotation(rotate) => {
+                self.settings.toss.rotate = rotate;
+                self.reload_tossed_image();
+            }
+            GuiEvent::ChangedTossCellWidth(value_str) => {
+                self.toss_cell_width_text = value_str;
+                if let Some(cell_width_mm) =
+                    parse_number_field(&self.toss_cell_width_text, |value| value > 0.0)
+                {
+                    self.settings.toss.cell_width_mm = cell_width_mm;
+                    self.reload_tossed_image();
+                }
+            }
+            GuiEvent::ChangedTossCellHeight(value_str) => {
+                self.toss_cell_height_text = value_str;
+                if let Some(cell_height_mm) =
+                    parse_number_field(&self.toss_cell_height_text, |value| value > 0.0)
+                {
+                    self.settings.toss.cell_height_mm = cell_height_mm;
+                    self.reload_tossed_image();
+                }
+            }
+            GuiEvent::ChangedTossSpacing(value_str) => {
+                self.toss_spacing_text = value_str;
+                if let Some(spacing_mm) =
+                    parse_number_field(&self.toss_spacing_text, |value| value >= 0.0)
+                {
+                    self.settings.toss.spacing_mm = spacing_mm;
+                    self.reload_tossed_image();
+                }
+            }
+            GuiEvent::ToggledVariation(enabled) => {
+                self.settings.variation.enabled = enabled;
+                self.process_state = ProcessState::Idle;
+                self.reload_image();
+            }
+            GuiEvent::ToggledVariationMirror(mirror) => {
+                self.settings.variation.mirror = mirror;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ToggledVariationRotate(rotate) => {
+                self.settings.variation.rotate = rotate;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedVariationSeed(value_str) => {
+                self.variation_seed_text = value_str;
+                if let Ok(seed) = self.variation_seed_text.parse::<u64>() {
+                    self.settings.variation.seed = seed;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedVariantFilepath(index, value_str) => {
+                self.variant_filepath_texts[index] = value_str;
+                // NOTE: Empty fields are dropped so that they do not end up in job files
+                self.settings.variation.variants = self
+                    .variant_filepath_texts
+                    .iter()
+                    .filter(|text| !text.is_empty())
+                    .cloned()
+                    .collect();
+                self.process_state = ProcessState::Idle;
+                if self.settings.variation.enabled {
+                    self.reload_image();
+                }
+            }
+            GuiEvent::ChangedTossSeed(value_str) => {
+                self.toss_seed_text = value_str;
+                if let Ok(seed) = self.toss_seed_text.parse::<u64>() {
+                    self.settings.toss.seed = seed;
+                    self.reload_tossed_image();
+                }
+            }
+            GuiEvent::ChangedPosterPaperSize(paper_size) => {
+                self.settings.poster.paper_size = paper_size;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ToggledPdfSingleFileForBatch(pdf_single_file_for_batch) => {
+                self.settings.pdf_single_file_for_batch = pdf_single_file_for_batch;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ToggledFlatten(enabled) => {
+                self.settings.flatten.enabled = enabled;
+                self.process_state = ProcessState::Idle;
+                if self.settings.flatten.ground_image.is_some() {
+                    self.reload_image();
+                }
+            }
+            GuiEvent::ChangedFlattenColor(value_str) => {
+                self.flatten_color_text = value_str;
+                if let Some(color) = parse_hex_color(&self.flatten_color_text) {
+                    self.settings.flatten.background_color = color;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedGroundImage(value_str) => {
+                self.ground_image_text = value_str;
+                self.settings.flatten.ground_image = if self.ground_image_text.is_empty() {
+                    None
+                } else {
+                    Some(self.ground_image_text.clone())
+                };
+                self.process_state = ProcessState::Idle;
+                if self.settings.flatten.enabled {
+                    self.reload_image();
+                }
+            }
+            GuiEvent::ToggledPrintMarks(print_marks) => {
+                self.settings.print_marks = print_marks;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ToggledSvgLinkTile(svg_link_tile) => {
+                self.settings.svg_link_tile = svg_link_tile;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedPngCompressionLevel(compression_level) => {
+                self.settings.png_encoder.compression_level = compression_level;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedPngFilterMode(filter_mode) => {
+                self.settings.png_encoder.filter_mode = filter_mode;
+                self.process_state = ProcessState::Idle;
+            }
+            GuiEvent::ChangedPngChunkSize(value_str) => {
+                self.png_chunk_size_text = value_str;
+                if let Ok(chunk_size_kib) = self.png_chunk_size_text.parse::<usize>() {
+                    self.settings.png_encoder.chunk_size_kib = chunk_size_kib;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::ChangedPngThreadCount(value_str) => {
+                self.png_thread_count_text = value_str;
+                if let Ok(thread_count) = self.png_thread_count_text.parse::<usize>() {
+                    self.settings.png_encoder.thread_count = thread_count;
+                    self.process_state = ProcessState::Idle;
+                }
+            }
+            GuiEvent::PressedSaveJobButton => {
+                self.save_job();
+            }
+            GuiEvent::ChangedMotifLayout(value_str) => {
+                self.motif_layout_text = value_str;
+                let is_valid = self.motif_layout_text.trim().is_empty()
+                    || MotifLayout::parse(&self.motif_layout_text).is_ok();
+                if is_valid {
+                    self.settings.motif_layout = self.motif_layout_text.trim().to_string();
+                    self.process_state = ProcessState::Idle;
+                    self.reload_image();
+                }
+            }
+            GuiEvent::ChangedMotifFilepath(index, value_str) => {
+                self.motif_filepath_texts[index] = value_str;
+                // NOTE: Trailing empty motifs are dropped so that they do not end up in job files
+                let mut motifs = self.motif_filepath_texts.to_vec();
+                while motifs.last().map(String::is_empty).unwrap_or(false) {
+                    motifs.pop();
+                }
+                self.settings.motifs = motifs;
+                self.process_state = ProcessState::Idle;
+                if !self.settings.motif_layout.is_empty() || self.settings.toss.enabled {
+                    self.reload_image();
+                }
+            }
+            GuiEvent::PressedPresetButton(index) => {
+                self.apply_preset(index);
+            }
+            GuiEvent::ChangedPresetName(value_str) => {
+                self.preset_name_text = value_str;
+            }
+            GuiEvent::PressedSavePresetButton => {
+                self.save_preset();
+            }
+            GuiEvent::PressedRecentFileButton(index) => {
+                if let Some(filepath) = self.recent_filepaths.get(index).cloned() {
+                    self.file_drop_starts_new_batch = true;
+                    self.add_dropped_path(&filepath);
+                }
+            }
+            GuiEvent::PressedStartButton => {
+                if let ProcessState::Running = self.process_state {
+                    return Command::none();
+                }
+                self.current_info = None;
+                if let Some(image) = &self.image {
+                    if !self.settings.is_valid() || !self.settings.png_encoder.is_valid() {
+                        self.current_error =
+                            Some("Some of the input values above are incorrect".to_string());
+                    } else if self.is_batch_mode() {
+                        return self.start_batch();
+                    } else {
+                        self.process_state = ProcessState::Running;
+
+                        if let Err(error_message) =
+                            create_pattern_for_image(image, &self.settings, &job_date_text())
+                        {
+                            self.current_error = Some(error_message);
+                            self.process_state = ProcessState::Idle;
+                        } else {
+                            self.current_error = None;
+                            self.process_state = ProcessState::Finished;
+                            self.output_estimate = None;
+                        }
+                    }
+                }
+            }
+            GuiEvent::FinishedOutputEstimate(image_generation, settings, estimate) => {
+                self.output_estimate_is_running = false;
+                if image_generation == self.image_generation {
+                    self.output_estimate = Some((settings, estimate));
+                }
+            }
+            GuiEvent::FinishedToss(image_generation, image_filepath, image_result) => {
+                self.toss_is_running = false;
+                // NOTE: A result is outdated if another image was loaded in the meantime or if
+                //       the toss settings changed again
+                if image_generation == self.image_generation
+                    && self.requested_toss_filepath.is_none()
+                {
+                    self.finish_loading_image(&image_filepath, image_result);
+                }
+            }
+            GuiEvent::FinishedBatchEntry(index, result) => {
+                if let Some(entry) = self.batch_entries.get_mut(index) {
+                    entry.status = match result {
+                        Ok(output_filepath) => BatchStatus::Succeeded { output_filepath },
+                        Err(error_message) => {
+                            log::error!(
+                                "Batch entry '{}' failed: {}",
+                                entry.filepath,
+                                error_message
+                            );
+                            BatchStatus::Failed { error_message }
+                        }
+                    };
+                }
+                return self.start_next_batch_entry();
+            }
+            GuiEvent::WindowEvent(window_event) => match window_event {
+                iced_native::Event::Window(window_event) => match window_event {
+                    iced_native::window::Event::FileHovered(_) => {
+                        // NOTE: A drag and drop operation with multiple files sends all hover
+                        //       events before the first drop event
+                        self.file_drop_starts_new_batch = true;
+                    }
+                    iced_native::window::Event::FileDropped(filepath) => {
+                        if let ProcessState::Running = self.process_state {
+                            return Command::none();
+                        }
+                        self.add_dropped_path(&filepath.to_string_borrowed_or_panic());
+                    }
+                    _ => {}
+                },
+                iced_native::Event::Keyboard(key_event) => match key_event {
+                    iced_native::input::keyboard::Event::Input { key_code, .. } => {
+                        if key_code == iced_native::input::keyboard::KeyCode::Escape {
+                            self.save_gui_state_if_changed();
+                            std::process::exit(0);
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            },
+        }
+
+        Command::none()
+    }
+}
+
+impl Application for RepeatyGui {
+    type Executor = iced::executor::Default;
+    type Message = GuiEvent;
+    type Flags = CommandlineArguments;
+
+    fn new(flags: CommandlineArguments) -> (RepeatyGui, Command<Self::Message>) {
+        let mut result = RepeatyGui::new(flags);
+        let estimate_command = result.request_output_estimate();
+        let toss_command = result.request_toss();
+        (result, Command::batch(vec![estimate_command, toss_command]))
+    }
+
+    fn title(&self) -> String {
+        String::from(main_launcher_info::LAUNCHER_WINDOW_TITLE)
+    }
+
+    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        let command = self.handle_gui_event(message);
+        let estimate_command = self.request_output_estimate();
+        let toss_command = self.request_toss();
+        self.save_gui_state_throttled();
+        Command::batch(vec![command, estimate_command, toss_command])
+    }
+
+    fn subscription(&self) -> Subscription<GuiEvent> {
+        iced_native::subscription::events().map(GuiEvent::WindowEvent)
+    }
+
+    fn view(&mut self) -> Element<Self::Message> {
+        let is_batch_mode = self.is_batch_mode();
+        let result = if let Some(image) = &self.image {
+            // We have an image already loaded
+
+            let input_image_stats = draw_input_image_stats(image);
+            let output_estimate = self.output_estimate.as_ref().map(|(_, estimate)| estimate);
+            let output_image_stats =
+                draw_output_image_stats(image, &self.settings, output_estimate);
+            let input_fields = draw_textinput_fields(
+                &self.repeat_x_text,
+                &self.repeat_y_text,
+                &self.dim_mm_x_text,
+                &self.dim_mm_y_text,
+                self.length_unit,
+                &mut self.repeat_x_widget,
+                &mut self.repeat_y_widget,
+                &mut self.dim_mm_x_widget,
+                &mut self.dim_mm_y_widget,
+            );
+            let length_unit_selection = draw_length_unit_selection(self.length_unit);
+            let snap_policy_selection = draw_snap_policy_selection(self.settings.snap_policy);
+            let tile_layout_selection = draw_tile_layout_selection(self.settings.tile_layout);
+            let output_color_type_selection =
+                draw_output_color_type_selection(self.settings.output_color_type);
+            let output_format_selection = draw_output_format_selection(&self.settings);
+            let png_encoder_options = draw_png_encoder_options(
+                &self.settings.png_encoder,
+                &self.png_chunk_size_text,
+                &self.png_thread_count_text,
+                &mut self.png_chunk_size_widget,
+                &mut self.png_thread_count_widget,
+            );
+            let aspect_lock_selection = draw_aspect_lock_selection(
+                self.settings.aspect_lock,
+                self.settings.keep_whole_repeats,
+            );
+            let ppi_override_field =
+                draw_ppi_override_field(&self.ppi_override_text, &mut self.ppi_override_widget);
+            let motif_options = draw_motif_options(
+                &self.motif_layout_text,
+                &self.motif_filepath_texts,
+                !self.motif_layout_text.trim().is_empty() || self.settings.toss.enabled,
+                &mut self.motif_layout_widget,
+                &mut self.motif_filepath_widgets,
+            );
+            let toss_options = draw_toss_options(
+                &self.settings.toss,
+                [
+                    &self.toss_cell_width_text,
+                    &self.toss_cell_height_text,
+                    &self.toss_spacing_text,
+                    &self.toss_seed_text,
+                ],
+                [
+                    &mut self.toss_cell_width_widget,
+                    &mut self.toss_cell_height_widget,
+                    &mut self.toss_spacing_widget,
+                    &mut self.toss_seed_widget,
+                ],
+            );
+            let variation_options = draw_variation_options(
+                &self.settings.variation,
+                &self.variation_seed_text,
+                &self.variant_filepath_texts,
+                &mut self.variation_seed_widget,
+                &mut self.variant_filepath_widgets,
+            );
+            let gutter_options = draw_gutter_options(
+                self.settings.gutter.unit,
+                &self.gutter_x_text,
+                &self.gutter_y_text,
+                &self.background_color_text,
+                &mut self.gutter_x_widget,
+                &mut self.gutter_y_widget,
+                &mut self.background_color_widget,
+            );
+            let flatten_options = draw_flatten_options(
+                self.settings.flatten.enabled,
+                &self.flatten_color_text,
+                &self.ground_image_text,
+                &mut self.flatten_color_widget,
+                &mut self.ground_image_widget,
+            );
+            let print_options = draw_print_options(
+                &self.bleed_text,
+                self.settings.print_marks,
+                &mut self.bleed_widget,
+            );
+            let poster_options = draw_poster_options(
+                &self.settings.poster,
+                &self.poster_overlap_text,
+                &mut self.poster_overlap_widget,
+            );
+            let wallpaper_options = draw_wallpaper_options(
+                &self.settings.wallpaper,
+                &self.wallpaper_roll_width_text,
+                &self.wallpaper_roll_length_text,
+                &mut self.wallpaper_roll_width_widget,
+                &mut self.wallpaper_roll_length_widget,
+            );
+            let mural_options = draw_mural_options(
+                &self.settings.mural,
+                [
+                    &self.mural_wall_width_text,
+                    &self.mural_wall_height_text,
+                    &self.mural_panel_width_text,
+                    &self.mural_overlap_text,
+                ],
+                [
+                    &mut self.mural_wall_width_widget,
+                    &mut self.mural_wall_height_widget,
+                    &mut self.mural_panel_width_widget,
+                    &mut self.mural_overlap_widget,
+                ],
+            );
+            let output_dir_field =
+                draw_output_dir_field(&self.output_dir_text, &mut self.output_dir_widget);
+            let preset_selection = draw_preset_selection(
+                &self.presets,
+                &self.preset_name_text,
+                &mut self.preset_button_widgets,
+                &mut self.preset_name_widget,
+                &mut self.save_preset_button_widget,
+            );
+
+            let start_button_label = if is_batch_mode {
+                format!("Create {} Patterns", self.batch_entries.len())
+            } else {
+                "Create Pattern".to_string()
+            };
+
+            let result = Column::new()
+                .spacing(10)
+                .padding(20)
+                .align_items(Align::Center)
+                .push(input_image_stats)
+                .push(preset_selection)
+                .push(motif_options)
+                .push(toss_options)
+                .push(input_fields)
+                .push(aspect_lock_selection)
+                .push(snap_policy_selection)
+                .push(tile_layout_selection)
+                .push(variation_options)
+                .push(gutter_options)
+                .push(output_color_type_selection)
+                .push(flatten_options)
+                .push(output_format_selection);
+            let result = if self.settings.output_format == OutputFormat::Png {
+                result.push(png_encoder_options)
+            } else {
+                result
+            };
+            let result = result
+                .push(length_unit_selection)
+                .push(ppi_override_field)
+                .push(print_options)
+                .push(poster_options)
+                .push(wallpaper_options)
+                .push(mural_options)
+                .push(output_dir_field)
+                .push(output_image_stats);
+            let result = if is_batch_mode {
+                result.push(draw_batch_entries(
+                    &self.batch_entries,
+                    &mut self.batch_scroll_widget,
+                ))
+            } else {
+                result
+            };
+            let result = result.push(
+                Row::new()
+                    .spacing(20)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(&mut self.start_button_widget, Text::new(start_button_label))
+                            .on_press(GuiEvent::PressedStartButton),
+                    )
+                    .push(
+                        Button::new(&mut self.save_job_button_widget, Text::new("Save Job"))
+                            .on_press(GuiEvent::PressedSaveJobButton),
+                    ),
+            );
+
+            let (success_count, failure_count) =
+                batch_success_and_failure_count(&self.batch_entries);
+            let (running_message, finished_message) = if is_batch_mode {
+                (
+                    format!(
+                        "Creating patterns ({}/{}) ...",
+                        success_count + failure_count + 1,
+                        self.batch_entries.len()
+                    ),
+                    format!(
+                        "Finished creating patterns: {} succeeded, {} failed",
+                        success_count, failure_count
+                    ),
+                )
+            } else {
+                (
+                    "Creating pattern ...".to_string(),
+                    "Finished creating pattern. Enjoy!".to_string(),
+                )
+            };
+
+            // Add processing state message
+            match self.process_state {
+                ProcessState::Idle => result,
+                ProcessState::Running => result
+                    .push(iced::Space::with_height(iced::Length::Units(20)))
+                    .push(
+                        Text::new(running_message)
+                            .horizontal_alignment(iced::HorizontalAlignment::Center)
+                            .vertical_alignment(iced::VerticalAlignment::Bottom)
+                            .size(30)
+                            .color(iced::Color::from_rgb(0.0, 0.0, 0.5))
+                            .width(FillPortion(1)),
+                    ),
+                ProcessState::Finished => result
+                    .push(iced::Space::with_height(iced::Length::Units(20)))
+                    .push(
+                        Text::new(finished_message)
+                            .horizontal_alignment(iced::HorizontalAlignment::Center)
+                            .vertical_alignment(iced::VerticalAlignment::Bottom)
+                            .size(30)
+                            .color(iced::Color::from_rgb(0.0, 0.5, 0.0))
+                            .width(FillPortion(1)),
+                    ),
+            }
+        } else {
+            // We have no image loaded
+            Column::new()
+                .spacing(10)
+                .padding(20)
+                .align_items(Align::Center)
+                .push(
+                    Text::new("Please drag and drop an image into this window")
+                        .horizontal_alignment(iced::HorizontalAlignment::Center)
+                        .vertical_alignment(iced::VerticalAlignment::Center)
+                        .size(30)
+                        .width(FillPortion(1))
+                        .height(FillPortion(1)),
+                )
+                .push(draw_recent_files(
+                    &self.recent_filepaths,
+                    &mut self.recent_file_button_widgets,
+                ))
+        };
+
+        // Add info message if necessary
+        let result = if let Some(info_message) = &self.current_info {
+            result.push(
+                Text::new(info_message.to_string())
+                    .horizontal_alignment(iced::HorizontalAlignment::Center)
+                    .size(LABEL_SIZE_DEFAULT)
+                    .color(COLOR_DEFAULT)
+                    .width(FillPortion(1)),
+            )
+        } else {
+            result
+        };
+
+        // Add error message if necessary
+        if let Some(error_message) = &self.current_error {
+            result
+                .push(iced::Space::with_height(iced::Length::Units(20)))
+                .push(
+                    Text::new(format!("Error: {}", error_message))
+                        .horizontal_alignment(iced::HorizontalAlignment::Center)
+                        .vertical_alignment(iced::VerticalAlignment::Bottom)
+                        .size(30)
+                        .color(iced::Color::from_rgb(0.8, 0.0, 0.1))
+                        .width(FillPortion(1)),
+                )
+                .into()
+        } else {
+            result.into()
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Draw Elements
+
+fn get_label_size_and_color(text: &str) -> (iced::Color, u16) {
+    if let Some(value) = text.parse::<f64>().ok() {
+        if value > 0.0 {
+            return (COLOR_DEFAULT, LABEL_SIZE_DEFAULT);
+        }
+    }
+    (COLOR_INVALID, LABEL_SIZE_INVALID)
+}
+fn get_ppi_label_size_and_color(ppi: f64) -> (iced::Color, u16) {
+    if (ppi - 300.0).abs() <= 0.1 {
+        (COLOR_DEFAULT, LABEL_SIZE_DEFAULT)
+    } else {
+        (COLOR_INVALID, LABEL_SIZE_INVALID)
+    }
+}
+fn get_validity_label_size_and_color(is_valid: bool) -> (iced::Color, u16) {
+    if is_valid {
+        (COLOR_DEFAULT, LABEL_SIZE_DEFAULT)
+    } else {
+        (COLOR_INVALID, LABEL_SIZE_INVALID)
+    }
+}
+/// Returns the number of a numeric input field if it parses and passes the given check
+fn parse_number_field(text: &str, is_valid: impl Fn(f64) -> bool) -> Option<f64> {
+    text.parse::<f64>().ok().filter(|&value| is_valid(value))
+}
+/// Parses colors of the form `#rrggbb` with an optional leading `#`
+fn parse_hex_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+fn hex_color_text(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+fn pretty_print_float(value: f64) -> String {
+    if (value - value.round()).abs() < 0.01 {
+        format!("{:.0}", value.round())
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+fn draw_input_image_stats<'a>(image: &InputImage) -> Column<'a, GuiEvent> {
+    let ppi = image.ppi.unwrap_or(DEFAULT_PPI);
+    let (ppi_label_color, ppi_label_size) = get_ppi_label_size_and_color(ppi);
+
+    Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .width(FillPortion(1))
+        .push(
+            Text::new("Input image:".to_string())
+                .horizontal_alignment(iced::HorizontalAlignment::Left)
+                .size(LABEL_SIZE_DEFAULT + 5)
+                .color(COLOR_DEFAULT),
+        )
+        .push(
+            Text::new(image.filepath.to_string())
+                .size(LABEL_SIZE_DEFAULT)
+                .color(COLOR_DEFAULT),
+        )
+        .push(
+            Text::new(format!(
+                "{}x{} {}",
+                image.bitmap.width,
+                image.bitmap.height,
+                image.bitmap.format_description()
+            ))
+            .horizontal_alignment(iced::HorizontalAlignment::Left)
+            .size(LABEL_SIZE_DEFAULT),
+        )
+        .push(
+            Text::new(format!("DPI: {}", pretty_print_float(ppi)))
+                .horizontal_alignment(iced::HorizontalAlignment::Left)
+                .size(ppi_label_size)
+                .color(ppi_label_color),
+        )
+}
+
+fn draw_output_image_stats<'a>(
+    image: &InputImage,
+    settings: &PatternSettings,
+    output_estimate: Option<&OutputEstimate>,
+) -> Column<'a, GuiEvent> {
+    let geometry = PatternGeometry::new(image, settings, &job_date_text());
+    let output_filepath = geometry.first_output_filepath(image);
+    let ppi = image.ppi.unwrap_or(DEFAULT_PPI);
+    let (ppi_label_color, ppi_label_size) = get_ppi_label_size_and_color(ppi);
+
+    let result = Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .width(FillPortion(1))
+        .push(
+            Text::new("Output image:".to_string())
+                .horizontal_alignment(iced::HorizontalAlignment::Left)
+                .size(LABEL_SIZE_DEFAULT + 5)
+                .color(COLOR_DEFAULT),
+        )
+        .push(
+            Text::new(system::path_to_filename(&output_filepath))
+                .size(LABEL_SIZE_DEFAULT)
+                .color(COLOR_DEFAULT),
+        )
+        .push(
+            Text::new(format!(
+                "{}x{}",
+                geometry.pixel_width, geometry.pixel_height
+            ))
+            .horizontal_alignment(iced::HorizontalAlignment::Left)
+            .size(LABEL_SIZE_DEFAULT),
+        )
+        .push(
+            Text::new(if geometry.has_trim_box() {
+                format!(
+                    "{}x{}mm (with {}mm bleed and {}mm margin)",
+                    pretty_print_float(geometry.width_mm),
+                    pretty_print_float(geometry.height_mm),
+                    pretty_print_float(geometry.bleed_mm),
+                    pretty_print_float(geometry.margin_mm)
+                )
+            } else {
+                format!(
+                    "{}x{}mm",
+                    pretty_print_float(geometry.width_mm),
+                    pretty_print_float(geometry.height_mm)
+                )
+            })
+            .horizontal_alignment(iced::HorizontalAlignment::Left)
+            .size(LABEL_SIZE_DEFAULT),
+        )
+        .push(
+            Text::new(format!("DPI: {}", ppi))
+                .horizontal_alignment(iced::HorizontalAlignment::Left)
+                .size(ppi_label_size)
+                .color(ppi_label_color),
+        );
+
+    let mut estimate_lines = Vec::new();
+    if let Some(note) = settings.color_type_note(image.pixel_per_mm()) {
+        estimate_lines.push(note.to_string());
+    }
+    if let Some(panels) = &geometry.panels {
+        estimate_lines.push(if panels.is_mural {
+            format!(
+                "Mural: {} panels of {}x{}mm with {}mm overlap",
+                panels.panel_count,
+                pretty_print_float(panels.panel_width_mm),
+                pretty_print_float(panels.panel_height_mm),
+                pretty_print_float(panels.overlap_mm)
+            )
+        } else {
+            format!(
+                "Wallpaper: {} panels of {}x{}mm, {}",
+                panels.panel_count,
+                pretty_print_float(panels.panel_width_mm),
+                pretty_print_float(panels.panel_height_mm),
+                match (panels.roll_count(), panels.panels_per_roll) {
+                    (Some(roll_count), Some(panels_per_roll)) =>
+                        format!("{} rolls with {} panels each", roll_count, panels_per_roll),
+                    _ => "panels are longer than the roll".to_string(),
+                }
+            )
+        });
+    }
+    if let Some(poster) = &geometry.poster {
+        estimate_lines.push(format!(
+            "Poster: {} sheets ({} columns, {} rows, {})",
+            poster.sheets.len(),
+            poster.column_count,
+            poster.row_count,
+            if poster.is_landscape() {
+                "landscape"
+            } else {
+                "portrait"
+            }
+        ));
+    }
+    if let Some(estimate) = output_estimate {
+        estimate_lines.push(format!(
+            "Uncompressed: {}",
+            pretty_print_bytes(estimate.size.raw_size_bytes)
+        ));
+        estimate_lines.push(match estimate.compressed_file_size_bytes {
+            Some(file_size_bytes) => {
+                format!(
+                    "Expected file size: ~{}",
+                    pretty_print_bytes(file_size_bytes)
+                )
+            }
+            None => format!(
+                "Expected file size: up to {}",
+                pretty_print_bytes(estimate.size.file_size_bytes)
+            ),
+        });
+        estimate_lines.push(format!(
+            "Peak memory: ~{}",
+            pretty_print_bytes(estimate.size.memory_bytes)
+        ));
+        estimate_lines.push(match estimate.duration_seconds {
+            Some(duration_seconds) => {
+                format!(
+                    "Expected time: ~{}",
+                    pretty_print_duration(duration_seconds)
+                )
+            }
+            None => "Expected time: unknown".to_string(),
+        });
+    }
+    let result = estimate_lines.into_iter().fold(result, |result, line| {
+        result.push(
+            Text::new(line)
+                .horizontal_alignment(iced::HorizontalAlignment::Left)
+                .size(LABEL_SIZE_DEFAULT),
+        )
+    });
+
+    match output_estimate.map(|estimate| &estimate.size_check_result) {
+        None | Some(Ok(())) => result,
+        Some(Err(error)) => result.push(
+            Text::new(error)
+                .horizontal_alignment(iced::HorizontalAlignment::Left)
+                .size(LABEL_SIZE_DEFAULT)
+                .color(COLOR_INVALID),
+        ),
+    }
+}
+
+fn draw_batch_entries<'a>(
+    entries: &[BatchEntry],
+    scroll_widget: &'a mut scrollable::State,
+) -> Scrollable<'a, GuiEvent> {
+    let mut result = Scrollable::new(scroll_widget)
+        .spacing(5)
+        .padding(10)
+        .width(FillPortion(1))
+        .height(iced::Length::Units(150));
+
+    for entry in entries {
+        let filename = system::path_to_filename(&entry.filepath);
+        let (text, color) = match &entry.status {
+            BatchStatus::Queued => (format!("Queued: {}", filename), COLOR_DEFAULT),
+            BatchStatus::Running => (
+                format!("Running: {}", filename),
+                iced::Color::from_rgb(0.0, 0.0, 0.5),
+            ),
+            BatchStatus::Succeeded { output_filepath } => (
+                format!(
+                    "Done: {} -> {}",
+                    filename,
+                    system::path_to_filename(output_filepath)
+                ),
+                iced::Color::from_rgb(0.0, 0.5, 0.0),
+            ),
+            BatchStatus::Failed { error_message } => (
+                format!("Failed: {} : {}", filename, error_message),
+                COLOR_INVALID,
+            ),
+        };
+        result = result.push(Text::new(text).size(LABEL_SIZE_DEFAULT).color(color));
+    }
+
+    result
+}
+
+fn draw_textinput_field<'a, OnChangeEvent>(
+    label_text: &str,
+    input_text: &str,
+    input_widget: &'a mut iced::text_input::State,
+    on_change: OnChangeEvent,
+) -> Row<'a, GuiEvent>
+where
+    OnChangeEvent: 'static + Fn(String) -> GuiEvent,
+{
+    let (label_color, label_size) = get_label_size_and_color(&input_text);
+    let repeat_count_x_label = Text::new(label_text.to_string() + ": ")
+        .size(label_size)
+        .color(label_color)
+        .width(FillPortion(1));
+    let repeat_count_x_input = TextInput::new(input_widget, "", &input_text, on_change)
+        .padding(15)
+        .size(label_size)
+        .width(FillPortion(1));
+
+    Row::new()
+        .padding(20)
+        .align_items(Align::Center)
+        .push(repeat_count_x_label)
+        .push(repeat_count_x_input)
+}
+
+fn draw_length_unit_selection<'a>(length_unit: LengthUnit) -> Row<'a, GuiEvent> {
+    let mut result = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Unit:").size(LABEL_SIZE_DEFAULT));
+    for &unit in &[
+        LengthUnit::Millimeter,
+        LengthUnit::Centimeter,
+        LengthUnit::Inch,
+    ] {
+        result = result.push(Radio::new(
+            unit,
+            unit.abbreviation(),
+            Some(length_unit),
+            GuiEvent::ChangedLengthUnit,
+        ));
+    }
+    result
+}
+
+fn draw_aspect_lock_selection<'a>(
+    aspect_lock: AspectLock,
+    keep_whole_repeats: bool,
+) -> Row<'a, GuiEvent> {
+    let mut result = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Lock:").size(LABEL_SIZE_DEFAULT));
+    for &(lock, label) in &[
+        (AspectLock::Off, "Off"),
+        (AspectLock::RepeatCount, "Same repeat"),
+        (AspectLock::AspectRatio, "Aspect ratio"),
+    ] {
+        result = result.push(Radio::new(
+            lock,
+            label,
+            Some(aspect_lock),
+            GuiEvent::ChangedAspectLock,
+        ));
+    }
+    if aspect_lock != AspectLock::Off {
+        result = result.push(Checkbox::new(
+            keep_whole_repeats,
+            "Whole repeats",
+            GuiEvent::ToggledKeepWholeRepeats,
+        ));
+    }
+    result
+}
+
+fn draw_snap_policy_selection<'a>(snap_policy: SnapPolicy) -> Row<'a, GuiEvent> {
+    let mut result = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Edges:").size(LABEL_SIZE_DEFAULT));
+    for &(policy, label) in &[
+        (SnapPolicy::Off, "Cut right/bottom"),
+        (SnapPolicy::WholeRepeats, "Whole repeats"),
+        (SnapPolicy::Centered, "Centered"),
+    ] {
+        result = result.push(Radio::new(
+            policy,
+            label,
+            Some(snap_policy),
+            GuiEvent::ChangedSnapPolicy,
+        ));
+    }
+    result
+}
+
+fn draw_tile_layout_selection<'a>(tile_layout: TileLayout) -> Row<'a, GuiEvent> {
+    let mut result = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Layout:").size(LABEL_SIZE_DEFAULT));
+    for &(layout, label) in &[
+        (TileLayout::Grid, "Grid"),
+        (TileLayout::HalfDrop, "Half drop"),
+        (TileLayout::HalfBrick, "Half brick"),
+    ] {
+        result = result.push(Radio::new(
+            layout,
+            label,
+            Some(tile_layout),
+            GuiEvent::ChangedTileLayout,
+        ));
+    }
+    result
+}
+
+fn draw_gutter_options<'a>(
+    gutter_unit: GutterUnit,
+    gutter_x_text: &str,
+    gutter_y_text: &str,
+    background_color_text: &str,
+    gutter_x_widget: &'a mut iced::text_input::State,
+    gutter_y_widget: &'a mut iced::text_input::State,
+    background_color_widget: &'a mut iced::text_input::State,
+) -> Column<'a, GuiEvent> {
+    let mut unit_selection = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Gutter unit:").size(LABEL_SIZE_DEFAULT));
+    for &(unit, label) in &[
+        (GutterUnit::Millimeter, "Millimeter"),
+        (GutterUnit::Pixel, "Pixel"),
+    ] {
+        unit_selection = unit_selection.push(Radio::new(
+            unit,
+            label,
+            Some(gutter_unit),
+            GuiEvent::ChangedGutterUnit,
+        ));
+    }
+
+    let is_valid_gutter = |text: &str| parse_number_field(text, |value| value >= 0.0).is_some();
+    let (gutter_x_color, gutter_x_size) =
+        get_validity_label_size_and_color(is_valid_gutter(gutter_x_text));
+    let (gutter_y_color, gutter_y_size) =
+        get_validity_label_size_and_color(is_valid_gutter(gutter_y_text));
+    let (background_color, background_size) = get_validity_label_size_and_color(
+        background_color_text.trim().is_empty() || parse_hex_color(background_color_text).is_some(),
+    );
+    let gutter_fields = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(
+            Text::new("Gutter horizontal: ")
+                .size(gutter_x_size)
+                .color(gutter_x_color)
+                .width(FillPortion(1)),
+        )
+        .push(
+            TextInput::new(
+                gutter_x_widget,
+                "0",
+                gutter_x_text,
+                GuiEvent::ChangedGutterX,
+            )
+            .padding(15)
+            .size(gutter_x_size)
+            .width(FillPortion(1)),
+        )
+        .push(
+            Text::new("Gutter vertical: ")
+                .size(gutter_y_size)
+                .color(gutter_y_color)
+                .width(FillPortion(1)),
+        )
+        .push(
+            TextInput::new(
+                gutter_y_widget,
+                "0",
+                gutter_y_text,
+                GuiEvent::ChangedGutterY,
+            )
+            .padding(15)
+            .size(gutter_y_size)
+            .width(FillPortion(1)),
+        );
+    let background_field = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(
+            Text::new("Background (#rrggbb, empty for transparent): ")
+                .size(background_size)
+                .color(background_color)
+                .width(FillPortion(1)),
+        )
+        .push(
+            TextInput::new(
+                background_color_widget,
+                "transparent",
+                background_color_text,
+                GuiEvent::ChangedBackgroundColor,
+            )
+            .padding(15)
+            .size(background_size)
+            .width(FillPortion(1)),
+        );
+
+    Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .push(unit_selection)
+        .push(gutter_fields)
+        .push(background_field)
+}
+
+fn draw_output_color_type_selection<'a>(output_color_type: OutputColorType) -> Row<'a, GuiEvent> {
+    let mut result = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Color type:").size(LABEL_SIZE_DEFAULT));
+    for &(color_type, label) in &[
+        (OutputColorType::Original, "Original"),
+        (OutputColorType::Smallest, "Smallest"),
+        (OutputColorType::Gray, "Gray"),
+        (OutputColorType::GrayAlpha, "Gray+Alpha"),
+        (OutputColorType::Rgb, "RGB"),
+        (OutputColorType::Rgba, "RGBA"),
+        (OutputColorType::Indexed, "Indexed"),
+    ] {
+        result = result.push(Radio::new(
+            color_type,
+            label,
+            Some(output_color_type),
+            GuiEvent::ChangedOutputColorType,
+        ));
+    }
+    result
+}
+
+fn draw_output_format_selection<'a>(settings: &PatternSettings) -> Row<'a, GuiEvent> {
+    let mut result = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Format:").size(LABEL_SIZE_DEFAULT));
+    for &(format, label) in &[
+        (OutputFormat::Png, "PNG"),
+        (OutputFormat::Pdf, "PDF"),
+        (OutputFormat::PdfTilingPattern, "PDF (tiling pattern)"),
+        (OutputFormat::Svg, "SVG"),
+        (OutputFormat::BigTiff, "BigTIFF"),
+    ] {
+        result = result.push(Radio::new(
+            format,
+            label,
+            Some(settings.output_format),
+            GuiEvent::ChangedOutputFormat,
+        ));
+    }
+    if settings.output_format.is_pdf() {
+        result = result.push(Checkbox::new(
+            settings.pdf_single_file_for_batch,
+            "One pdf for batch",
+            GuiEvent::ToggledPdfSingleFileForBatch,
+        ));
+    }
+    if settings.output_format == OutputFormat::Svg {
+        result = result.push(Checkbox::new(
+            settings.svg_link_tile,
+            "Link tile instead of embedding",
+            GuiEvent::ToggledSvgLinkTile,
+        ));
+    }
+    result
+}
+
+fn draw_png_encoder_options<'a>(
+    png_encoder: &PngEncoderOptions,
+    chunk_size_text: &str,
+    thread_count_text: &str,
+    chunk_size_widget: &'a mut iced::text_input::State,
+    thread_count_widget: &'a mut iced::text_input::State,
+) -> Column<'a, GuiEvent> {
+    let mut compression_selection = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Compression:").size(LABEL_SIZE_DEFAULT));
+    for &(compression_level, label) in &[
+        (PngCompressionLevel::Fast, "Fast"),
+        (PngCompressionLevel::Default, "Default"),
+        (PngCompressionLevel::High, "High"),
+    ] {
+        compression_selection = compression_selection.push(Radio::new(
+            compression_level,
+            label,
+            Some(png_encoder.compression_level),
+            GuiEvent::ChangedPngCompressionLevel,
+        ));
+    }
+
+    let mut filter_selection = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Filter:").size(LABEL_SIZE_DEFAULT));
+    for &(filter_mode, label) in &[
+        (PngFilterMode::Adaptive, "Adaptive"),
+        (PngFilterMode::None, "None"),
+        (PngFilterMode::Sub, "Sub"),
+        (PngFilterMode::Up, "Up"),
+        (PngFilterMode::Average, "Average"),
+        (PngFilterMode::Paeth, "Paeth"),
+    ] {
+        filter_selection = filter_selection.push(Radio::new(
+            filter_mode,
+            label,
+            Some(png_encoder.filter_mode),
+            GuiEvent::ChangedPngFilterMode,
+        ));
+    }
+
+    let chunk_size_is_valid = chunk_size_text
+        .parse::<usize>()
+        .map(|chunk_size_kib| chunk_size_kib >= PNG_MIN_CHUNK_SIZE_KIB)
+        .unwrap_or(false);
+    let thread_count_is_valid = thread_count_text.parse::<usize>().is_ok();
+
+    let (chunk_size_color, chunk_size_size) =
+        get_validity_label_size_and_color(chunk_size_is_valid);
+    let (thread_count_color, thread_count_size) =
+        get_validity_label_size_and_color(thread_count_is_valid);
+    let chunk_size_and_threads = Row::new()
+        .padding(20)
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(
+            Text::new("Chunk size (KiB): ")
+                .size(chunk_size_size)
+                .color(chunk_size_color)
+                .width(FillPortion(1)),
+        )
+        .push(
+            TextInput::new(
+                chunk_size_widget,
+                "",
+                chunk_size_text,
+                GuiEvent::ChangedPngChunkSize,
+            )
+            .padding(15)
+            .size(chunk_size_size)
+            .width(FillPortion(1)),
+        )
+        .push(
+            Text::new("Threads: ")
+                .size(thread_count_size)
+                .color(thread_count_color)
+                .width(FillPortion(1)),
+        )
+        .push(
+            TextInput::new(
+                thread_count_widget,
+                "0 = all",
+                thread_count_text,
+                GuiEvent::ChangedPngThreadCount,
+            )
+            .padding(15)
+            .size(thread_count_size)
+            .width(FillPortion(1)),
+        );
+
+    Column::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(compression_selection)
+        .push(filter_selection)
+        .push(chunk_size_and_threads)
+}
+
+fn draw_output_dir_field<'a>(
+    output_dir_text: &str,
+    output_dir_widget: &'a mut iced::text_input::State,
+) -> Row<'a, GuiEvent> {
+    // NOTE: An empty text is valid here and means that we write next to the executable
+    let (label_color, label_size) = get_validity_label_size_and_color(
+        output_dir_text.is_empty() || std::path::Path::new(output_dir_text).is_dir(),
+    );
+    let label = Text::new("Output directory: ")
+        .size(label_size)
+        .color(label_color)
+        .width(FillPortion(1));
+    let input = TextInput::new(
+        output_dir_widget,
+        "next to executable",
+        output_dir_text,
+        GuiEvent::ChangedOutputDir,
+    )
+    .padding(15)
+    .size(label_size)
+    .width(FillPortion(3));
+
+    Row::new()
+        .padding(20)
+        .align_items(Align::Center)
+        .push(label)
+        .push(input)
+}
+
+fn draw_recent_files<'a>(
+    recent_filepaths: &[String],
+    button_widgets: &'a mut [button::State],
+) -> Column<'a, GuiEvent> {
+    let mut result = Column::new()
+        .spacing(5)
+        .padding(20)
+        .align_items(Align::Center);
+    if recent_filepaths.is_empty() {
+        return result;
+    }
+
+    result = result.push(
+        Text::new("Recent files:")
+            .size(LABEL_SIZE_DEFAULT + 5)
+            .color(COLOR_DEFAULT),
+    );
+    for (index, (filepath, button_widget)) in recent_filepaths
+        .iter()
+        .zip(button_widgets.iter_mut())
+        .enumerate()
+    {
+        result = result.push(
+            Button::new(
+                button_widget,
+                Text::new(filepath.to_string()).size(LABEL_SIZE_DEFAULT),
+            )
+            .on_press(GuiEvent::PressedRecentFileButton(index)),
+        );
+    }
+    result
+}
+
+fn draw_preset_selection<'a>(
+    presets: &[Preset],
+    preset_name_text: &str,
+    button_widgets: &'a mut [button::State],
+    preset_name_widget: &'a mut iced::text_input::State,
+    save_button_widget: &'a mut button::State,
+) -> Column<'a, GuiEvent> {
+    let mut preset_buttons = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(Text::new("Presets:").size(LABEL_SIZE_DEFAULT));
+    for (index, (preset, button_widget)) in
+        presets.iter().zip(button_widgets.iter_mut()).enumerate()
+    {
+        preset_buttons = preset_buttons.push(
+            Button::new(
+                button_widget,
+                Text::new(preset.name.clone()).size(LABEL_SIZE_DEFAULT),
+            )
+            .on_press(GuiEvent::PressedPresetButton(index)),
+        );
+    }
+
+    let save_preset = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(
+            Text::new("Preset name: ")
+                .size(LABEL_SIZE_DEFAULT)
+                .width(FillPortion(1)),
+        )
+        .push(
+            TextInput::new(
+                preset_name_widget,
+                "My preset",
+                preset_name_text,
+                GuiEvent::ChangedPresetName,
+            )
+            .padding(15)
+            .size(LABEL_SIZE_DEFAULT)
+            .width(FillPortion(2)),
+        )
+        .push(
+            Button::new(save_button_widget, Text::new("Save Preset"))
+                .on_press(GuiEvent::PressedSavePresetButton),
+        );
+
+    Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .push(preset_buttons)
+        .push(save_preset)
+}
+
+fn draw_motif_options<'a>(
+    motif_layout_text: &str,
+    motif_filepath_texts: &[String; 3],
+    shows_motif_filepaths: bool,
+    motif_layout_widget: &'a mut iced::text_input::State,
+    motif_filepath_widgets: &'a mut [iced::text_input::State; 3],
+) -> Column<'a, GuiEvent> {
+    // NOTE: An empty layout is valid here and means that the input image is used alone
+    let (label_color, label_size) = get_validity_label_size_and_color(
+        motif_layout_text.trim().is_empty() || MotifLayout::parse(motif_layout_text).is_ok(),
+    );
+    let layout_field = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(
+            Text::new("Motif layout: ")
+                .size(label_size)
+                .color(label_color)
+                .width(FillPortion(1)),
+        )
+        .push(
+            TextInput::new(
+                motif_layout_widget,
+                "e.g. AB/BA",
+                motif_layout_text,
+                GuiEvent::ChangedMotifLayout,
+            )
+            .padding(15)
+            .size(label_size)
+            .width(FillPortion(1)),
+        );
+
+    let mut result = Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .push(layout_field);
+    if !shows_motif_filepaths {
+        return result;
+    }
+    for (index, (text, widget)) in motif_filepath_texts
+        .iter()
+        .zip(motif_filepath_widgets.iter_mut())
+        .enumerate()
+    {
+        result = result.push(
+            Row::new()
                 .spacing(10)
-                .padding(20)
                 .align_items(Align::Center)
                 .push(
-                    Text::new("Please drag and drop an image into this window")
-                        .horizontal_alignment(iced::HorizontalAlignment::Center)
-                        .vertical_alignment(iced::VerticalAlignment::Center)
-                        .size(30)
-                        .width(FillPortion(1))
-                        .height(FillPortion(1)),
-                )
-        };
-
-        // Add error message if necessary
-        if let Some(error_message) = &self.current_error {
-            result
-                .push(iced::Space::with_height(iced::Length::Units(20)))
-                .push(
-                    Text::new(format!("Error: {}", error_message))
-                        .horizontal_alignment(iced::HorizontalAlignment::Center)
-                        .vertical_alignment(iced::VerticalAlignment::Bottom)
-                        .size(30)
-                        .color(iced::Color::from_rgb(0.8, 0.0, 0.1))
+                    Text::new(format!("Motif {}: ", motifs::motif_name(index + 1)))
+                        .size(LABEL_SIZE_DEFAULT)
                         .width(FillPortion(1)),
                 )
-                .into()
-        } else {
-            result.into()
-        }
+                .push(
+                    TextInput::new(widget, "path/to/motif.png", text, move |value| {
+                        GuiEvent::ChangedMotifFilepath(index, value)
+                    })
+                    .padding(15)
+                    .size(LABEL_SIZE_DEFAULT)
+                    .width(FillPortion(1)),
+                ),
+        );
     }
+    result
 }
 
-////////////////////////////////////////////////////////////////////////////////////////////////////
-// Draw Elements
-
-fn get_label_size_and_color(text: &str) -> (iced::Color, u16) {
-    if let Some(value) = text.parse::<f64>().ok() {
-        if value > 0.0 {
-            return (COLOR_DEFAULT, LABEL_SIZE_DEFAULT);
-        }
-    }
-    (COLOR_INVALID, LABEL_SIZE_INVALID)
-}
-fn get_ppi_label_size_and_color(ppi: f64) -> (iced::Color, u16) {
-    if (ppi - 300.0).abs() <= 0.1 {
+fn draw_ppi_override_field<'a>(
+    ppi_override_text: &str,
+    ppi_override_widget: &'a mut iced::text_input::State,
+) -> Row<'a, GuiEvent> {
+    // NOTE: An empty text is valid here and means that we use the DPI of the image metadata
+    let (label_color, label_size) = if ppi_override_text.is_empty() {
         (COLOR_DEFAULT, LABEL_SIZE_DEFAULT)
     } else {
-        (COLOR_INVALID, LABEL_SIZE_INVALID)
-    }
-}
-fn pretty_print_float(value: f64) -> String {
-    if (value - value.round()).abs() < 0.01 {
-        format!("{:.0}", value.round())
-    } else {
-        format!("{:.2}", value)
-    }
+        get_label_size_and_color(ppi_override_text)
+    };
+    let label = Text::new("DPI override: ")
+        .size(label_size)
+        .color(label_color)
+        .width(FillPortion(1));
+    let input = TextInput::new(
+        ppi_override_widget,
+        "from image",
+        ppi_override_text,
+        GuiEvent::ChangedPpiOverride,
+    )
+    .padding(15)
+    .size(label_size)
+    .width(FillPortion(1));
+
+    Row::new()
+        .padding(20)
+        .align_items(Align::Center)
+        .push(label)
+        .push(input)
 }
 
-fn draw_input_image_stats<'a>(image: &InputImage) -> Column<'a, GuiEvent> {
-    let ppi = image.ppi.unwrap_or(DEFAULT_PPI);
-    let (ppi_label_color, ppi_label_size) = get_ppi_label_size_and_color(ppi);
+fn draw_print_options<'a>(
+    bleed_text: &str,
+    print_marks: bool,
+    bleed_widget: &'a mut iced::text_input::State,
+) -> Row<'a, GuiEvent> {
+    // NOTE: Zero is valid here and means that there is no bleed
+    let (label_color, label_size) = get_validity_label_size_and_color(
+        parse_number_field(bleed_text, |value| value >= 0.0).is_some(),
+    );
+    let label = Text::new("Bleed per side (mm): ")
+        .size(label_size)
+        .color(label_color)
+        .width(FillPortion(1));
+    let input = TextInput::new(bleed_widget, "0", bleed_text, GuiEvent::ChangedBleed)
+        .padding(15)
+        .size(label_size)
+        .width(FillPortion(1));
 
-    Column::new()
-        .spacing(10)
+    Row::new()
         .padding(20)
+        .spacing(20)
         .align_items(Align::Center)
-        .width(FillPortion(1))
+        .push(label)
+        .push(input)
+        .push(Checkbox::new(
+            print_marks,
+            "Print marks",
+            GuiEvent::ToggledPrintMarks,
+        ))
+}
+
+fn draw_flatten_options<'a>(
+    enabled: bool,
+    color_text: &str,
+    ground_image_text: &str,
+    color_widget: &'a mut iced::text_input::State,
+    ground_image_widget: &'a mut iced::text_input::State,
+) -> Column<'a, GuiEvent> {
+    let (color_label_color, color_label_size) =
+        get_validity_label_size_and_color(parse_hex_color(color_text).is_some());
+    let color_field = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Checkbox::new(
+            enabled,
+            "Flatten transparency",
+            GuiEvent::ToggledFlatten,
+        ))
         .push(
-            Text::new("Input image:".to_string())
-                .horizontal_alignment(iced::HorizontalAlignment::Left)
-                .size(LABEL_SIZE_DEFAULT + 5)
-                .color(COLOR_DEFAULT),
+            Text::new("Background (#rrggbb): ")
+                .size(color_label_size)
+                .color(color_label_color)
+                .width(FillPortion(1)),
         )
         .push(
-            Text::new(image.filepath.to_string())
-                .size(LABEL_SIZE_DEFAULT)
-                .color(COLOR_DEFAULT),
-        )
+            TextInput::new(
+                color_widget,
+                "#ffffff",
+                color_text,
+                GuiEvent::ChangedFlattenColor,
+            )
+            .padding(15)
+            .size(color_label_size)
+            .width(FillPortion(1)),
+        );
+    let ground_image_field = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
         .push(
-            Text::new(format!("{}x{}", image.bitmap.width, image.bitmap.height))
-                .horizontal_alignment(iced::HorizontalAlignment::Left)
-                .size(LABEL_SIZE_DEFAULT),
+            Text::new("Ground image (optional): ")
+                .size(LABEL_SIZE_DEFAULT)
+                .width(FillPortion(1)),
         )
         .push(
-            Text::new(format!("DPI: {}", pretty_print_float(ppi)))
-                .horizontal_alignment(iced::HorizontalAlignment::Left)
-                .size(ppi_label_size)
-                .color(ppi_label_color),
-        )
-}
-
-fn draw_output_image_stats<'a>(
-    image: &InputImage,
-    repeat_x: f64,
-    repeat_y: f64,
-    dim_mm_x: f64,
-    dim_mm_y: f64,
-) -> Column<'a, GuiEvent> {
-    let (output_image_pixel_width, output_image_pixel_height, png_output_filepath) =
-        image.output_image_pixel_width_height_filepath(repeat_x, repeat_y, dim_mm_x, dim_mm_y);
-    let ppi = image.ppi.unwrap_or(DEFAULT_PPI);
-    let (ppi_label_color, ppi_label_size) = get_ppi_label_size_and_color(ppi);
+            TextInput::new(
+                ground_image_widget,
+                "path/to/ground.png",
+                ground_image_text,
+                GuiEvent::ChangedGroundImage,
+            )
+            .padding(15)
+            .size(LABEL_SIZE_DEFAULT)
+            .width(FillPortion(1)),
+        );
 
     Column::new()
         .spacing(10)
         .padding(20)
         .align_items(Align::Center)
-        .width(FillPortion(1))
+        .push(color_field)
+        .push(ground_image_field)
+}
+
+fn draw_poster_options<'a>(
+    poster: &PosterOptions,
+    overlap_text: &str,
+    overlap_widget: &'a mut iced::text_input::State,
+) -> Row<'a, GuiEvent> {
+    let mut result = Row::new()
+        .padding(20)
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Text::new("Poster:").size(LABEL_SIZE_DEFAULT));
+    for &(paper_size, label) in &[
+        (PosterPaperSize::Off, "Off"),
+        (PosterPaperSize::A4, "A4"),
+        (PosterPaperSize::A3, "A3"),
+        (PosterPaperSize::Letter, "Letter"),
+    ] {
+        result = result.push(Radio::new(
+            paper_size,
+            label,
+            Some(poster.paper_size),
+            GuiEvent::ChangedPosterPaperSize,
+        ));
+    }
+
+    let (label_color, label_size) = get_validity_label_size_and_color(
+        parse_number_field(overlap_text, |value| {
+            value >= 0.0 && value <= POSTER_MAX_OVERLAP_MM
+        })
+        .is_some(),
+    );
+    result
         .push(
-            Text::new("Output image:".to_string())
-                .horizontal_alignment(iced::HorizontalAlignment::Left)
-                .size(LABEL_SIZE_DEFAULT + 5)
-                .color(COLOR_DEFAULT),
+            Text::new("Overlap (mm): ")
+                .size(label_size)
+                .color(label_color)
+                .width(FillPortion(1)),
         )
         .push(
-            Text::new(system::path_to_filename(&png_output_filepath))
-                .size(LABEL_SIZE_DEFAULT)
-                .color(COLOR_DEFAULT),
+            TextInput::new(
+                overlap_widget,
+                "10",
+                overlap_text,
+                GuiEvent::ChangedPosterOverlap,
+            )
+            .padding(15)
+            .size(label_size)
+            .width(FillPortion(1)),
         )
+}
+
+/// Draws a label and its text input side by side. The label is highlighted if the text is invalid.
+fn draw_validated_number_field<'a>(
+    label: &str,
+    placeholder: &str,
+    text: &str,
+    is_valid: bool,
+    widget: &'a mut iced::text_input::State,
+    event: fn(String) -> GuiEvent,
+) -> Row<'a, GuiEvent> {
+    let (color, size) = get_validity_label_size_and_color(is_valid);
+    Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
         .push(
-            Text::new(format!(
-                "{}x{}",
-                output_image_pixel_width, output_image_pixel_height
-            ))
-            .horizontal_alignment(iced::HorizontalAlignment::Left)
-            .size(LABEL_SIZE_DEFAULT),
+            Text::new(label.to_string())
+                .size(size)
+                .color(color)
+                .width(FillPortion(1)),
         )
         .push(
-            Text::new(format!("DPI: {}", ppi))
-                .horizontal_alignment(iced::HorizontalAlignment::Left)
-                .size(ppi_label_size)
-                .color(ppi_label_color),
+            TextInput::new(widget, placeholder, text, event)
+                .padding(15)
+                .size(size)
+                .width(FillPortion(1)),
         )
 }
 
-fn draw_textinput_field<'a, OnChangeEvent>(
-    label_text: &str,
-    input_text: &str,
-    input_widget: &'a mut iced::text_input::State,
-    on_change: OnChangeEvent,
-) -> Row<'a, GuiEvent>
-where
-    OnChangeEvent: 'static + Fn(String) -> GuiEvent,
-{
-    let (label_color, label_size) = get_label_size_and_color(&input_text);
-    let repeat_count_x_label = Text::new(label_text.to_string() + ": ")
-        .size(label_size)
-        .color(label_color)
-        .width(FillPortion(1));
-    let repeat_count_x_input = TextInput::new(input_widget, "", &input_text, on_change)
-        .padding(15)
-        .size(label_size)
-        .width(FillPortion(1));
+fn draw_wallpaper_options<'a>(
+    wallpaper: &WallpaperOptions,
+    roll_width_text: &str,
+    roll_length_text: &str,
+    roll_width_widget: &'a mut iced::text_input::State,
+    roll_length_widget: &'a mut iced::text_input::State,
+) -> Column<'a, GuiEvent> {
+    let mut match_selection = Row::new()
+        .spacing(20)
+        .align_items(Align::Center)
+        .push(Checkbox::new(
+            wallpaper.enabled,
+            "Wallpaper panels",
+            GuiEvent::ToggledWallpaper,
+        ))
+        .push(Text::new("Match:").size(LABEL_SIZE_DEFAULT));
+    for &(match_type, label) in &[
+        (WallpaperMatch::Straight, "Straight"),
+        (WallpaperMatch::Drop, "Drop"),
+    ] {
+        match_selection = match_selection.push(Radio::new(
+            match_type,
+            label,
+            Some(wallpaper.match_type),
+            GuiEvent::ChangedWallpaperMatch,
+        ));
+    }
 
-    Row::new()
+    let is_positive = |text: &str| parse_number_field(text, |value| value > 0.0).is_some();
+    let roll_dimensions = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(draw_validated_number_field(
+            "Roll width (mm): ",
+            "530",
+            roll_width_text,
+            is_positive(roll_width_text),
+            roll_width_widget,
+            GuiEvent::ChangedWallpaperRollWidth,
+        ))
+        .push(draw_validated_number_field(
+            "Roll length (mm): ",
+            "10050",
+            roll_length_text,
+            is_positive(roll_length_text),
+            roll_length_widget,
+            GuiEvent::ChangedWallpaperRollLength,
+        ));
+
+    Column::new()
+        .spacing(10)
         .padding(20)
         .align_items(Align::Center)
-        .push(repeat_count_x_label)
-        .push(repeat_count_x_input)
+        .push(match_selection)
+        .push(roll_dimensions)
+}
+
+fn draw_mural_options<'a>(
+    mural: &MuralOptions,
+    texts: [&str; 4],
+    widgets: [&'a mut iced::text_input::State; 4],
+) -> Column<'a, GuiEvent> {
+    let [wall_width_widget, wall_height_widget, panel_width_widget, overlap_widget] = widgets;
+    let is_positive = |text: &str| parse_number_field(text, |value| value > 0.0).is_some();
+    let overlap_is_valid = parse_number_field(texts[3], |value| {
+        value >= 0.0 && value < mural.panel_width_mm
+    })
+    .is_some();
+
+    let wall_dimensions = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(draw_validated_number_field(
+            "Wall width (mm): ",
+            "4000",
+            texts[0],
+            is_positive(texts[0]),
+            wall_width_widget,
+            GuiEvent::ChangedMuralWallWidth,
+        ))
+        .push(draw_validated_number_field(
+            "Wall height (mm): ",
+            "2600",
+            texts[1],
+            is_positive(texts[1]),
+            wall_height_widget,
+            GuiEvent::ChangedMuralWallHeight,
+        ));
+    let panel_dimensions = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(draw_validated_number_field(
+            "Panel width (mm): ",
+            "1000",
+            texts[2],
+            is_positive(texts[2]),
+            panel_width_widget,
+            GuiEvent::ChangedMuralPanelWidth,
+        ))
+        .push(draw_validated_number_field(
+            "Overlap (mm): ",
+            "20",
+            texts[3],
+            overlap_is_valid,
+            overlap_widget,
+            GuiEvent::ChangedMuralOverlap,
+        ));
+
+    Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .push(Checkbox::new(
+            mural.enabled,
+            "Wall mural (uses the wall dimensions as pattern dimensions)",
+            GuiEvent::ToggledMural,
+        ))
+        .push(wall_dimensions)
+        .push(panel_dimensions)
+}
+
+fn draw_toss_options<'a>(
+    toss: &TossOptions,
+    texts: [&str; 4],
+    widgets: [&'a mut iced::text_input::State; 4],
+) -> Column<'a, GuiEvent> {
+    let [cell_width_widget, cell_height_widget, spacing_widget, seed_widget] = widgets;
+    let is_positive = |text: &str| parse_number_field(text, |value| value > 0.0).is_some();
+    let spacing_is_valid = parse_number_field(texts[2], |value| value >= 0.0).is_some();
+    let seed_is_valid = texts[3].parse::<u64>().is_ok();
+
+    let cell_dimensions = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(draw_validated_number_field(
+            "Cell width (mm): ",
+            "200",
+            texts[0],
+            is_positive(texts[0]),
+            cell_width_widget,
+            GuiEvent::ChangedTossCellWidth,
+        ))
+        .push(draw_validated_number_field(
+            "Cell height (mm): ",
+            "200",
+            texts[1],
+            is_positive(texts[1]),
+            cell_height_widget,
+            GuiEvent::ChangedTossCellHeight,
+        ));
+    let spacing_and_seed = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(draw_validated_number_field(
+            "Spacing (mm): ",
+            "5",
+            texts[2],
+            spacing_is_valid,
+            spacing_widget,
+            GuiEvent::ChangedTossSpacing,
+        ))
+        .push(draw_validated_number_field(
+            "Seed: ",
+            "1",
+            texts[3],
+            seed_is_valid,
+            seed_widget,
+            GuiEvent::ChangedTossSeed,
+        ));
+
+    let mut result = Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .push(
+            Row::new()
+                .spacing(20)
+                .align_items(Align::Center)
+                .push(Checkbox::new(
+                    toss.enabled,
+                    "Toss motifs at random over a repeat cell",
+                    GuiEvent::ToggledToss,
+                ))
+                .push(Checkbox::new(
+                    toss.rotate,
+                    "Rotate motifs",
+                    GuiEvent::ToggledTossRotation,
+                )),
+        );
+    if toss.enabled {
+        result = result.push(cell_dimensions).push(spacing_and_seed);
+    }
+    result
+}
+
+fn draw_variation_options<'a>(
+    variation: &VariationOptions,
+    seed_text: &str,
+    variant_filepath_texts: &[String; 3],
+    seed_widget: &'a mut iced::text_input::State,
+    variant_filepath_widgets: &'a mut [iced::text_input::State; 3],
+) -> Column<'a, GuiEvent> {
+    let mut result = Column::new()
+        .spacing(10)
+        .padding(20)
+        .align_items(Align::Center)
+        .push(Checkbox::new(
+            variation.enabled,
+            "Vary tiles at random to break up visible repetition",
+            GuiEvent::ToggledVariation,
+        ));
+    if !variation.enabled {
+        return result;
+    }
+
+    let (seed_color, seed_size) =
+        get_validity_label_size_and_color(seed_text.parse::<u64>().is_ok());
+    result = result.push(
+        Row::new()
+            .spacing(20)
+            .align_items(Align::Center)
+            .push(Checkbox::new(
+                variation.mirror,
+                "Mirror",
+                GuiEvent::ToggledVariationMirror,
+            ))
+            .push(Checkbox::new(
+                variation.rotate,
+                "Rotate",
+                GuiEvent::ToggledVariationRotate,
+            ))
+            .push(
+                Text::new("Seed: ")
+                    .size(seed_size)
+                    .color(seed_color)
+                    .width(FillPortion(1)),
+            )
+            .push(
+                TextInput::new(seed_widget, "1", seed_text, GuiEvent::ChangedVariationSeed)
+                    .padding(15)
+                    .size(seed_size)
+                    .width(FillPortion(1)),
+            ),
+    );
+    for (index, (text, widget)) in variant_filepath_texts
+        .iter()
+        .zip(variant_filepath_widgets.iter_mut())
+        .enumerate()
+    {
+        result = result.push(
+            Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(
+                    Text::new(format!("Variant {}: ", index + 1))
+                        .size(LABEL_SIZE_DEFAULT)
+                        .width(FillPortion(1)),
+                )
+                .push(
+                    TextInput::new(widget, "path/to/variant.png", text, move |value| {
+                        GuiEvent::ChangedVariantFilepath(index, value)
+                    })
+                    .padding(15)
+                    .size(LABEL_SIZE_DEFAULT)
+                    .width(FillPortion(1)),
+                ),
+        );
+    }
+    result
 }
 
 fn draw_textinput_fields<'a>(
@@ -821,6 +6431,7 @@ fn draw_textinput_fields<'a>(
     repeat_y_text: &str,
     dim_x_text: &str,
     dim_y_text: &str,
+    length_unit: LengthUnit,
     dim_x_widget: &'a mut iced::text_input::State,
     dim_y_widget: &'a mut iced::text_input::State,
     repeat_x_widget: &'a mut iced::text_input::State,
@@ -839,13 +6450,13 @@ fn draw_textinput_fields<'a>(
         GuiEvent::ChangedRepeatCountY,
     );
     let dim_x = draw_textinput_field(
-        "Image width (mm)",
+        &format!("Image width ({})", length_unit.abbreviation()),
         dim_x_text,
         dim_x_widget,
         GuiEvent::ChangedDimensionMillimeterX,
     );
     let dim_y = draw_textinput_field(
-        "Image height (mm)",
+        &format!("Image height ({})", length_unit.abbreviation()),
         dim_y_text,
         dim_y_widget,
         GuiEvent::ChangedDimensionMillimeterY,
@@ -880,14 +6491,7 @@ fn draw_textinput_fields<'a>(
 // Main
 
 fn main() {
-    let logfile_path = {
-        let logfile_dir = system::get_appdata_dir(
-            main_launcher_info::LAUNCHER_COMPANY_NAME,
-            main_launcher_info::LAUNCHER_SAVE_FOLDER_NAME,
-        )
-        .unwrap_or(get_executable_dir());
-        system::path_join(&logfile_dir, "logging.txt")
-    };
+    let logfile_path = system::path_join(&get_appdata_dir(), "logging.txt");
     if let Err(error) = ct_lib::init_logging(&logfile_path, log::LevelFilter::Info) {
         msgbox::create(
             main_launcher_info::LAUNCHER_WINDOW_TITLE,
@@ -901,5 +6505,140 @@ fn main() {
         log::error!("{}", panic_info);
     }));
 
-    RepeatyGui::run(Settings::default());
+    let arguments = match parse_commandline_arguments(&get_commandline_arguments()) {
+        Ok(arguments) => arguments,
+        Err(error_message) => {
+            log::error!("{}", error_message);
+            msgbox::create(
+                main_launcher_info::LAUNCHER_WINDOW_TITLE,
+                &error_message,
+                msgbox::IconType::Error,
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if arguments.run_without_gui {
+        let job_filepath = arguments.job_filepath.as_deref().unwrap_or_default();
+        let result = PatternJob::load_from_file(job_filepath).and_then(|mut job| {
+            // Images given on the commandline take precedence over the inputs of the job
+            if !arguments.input_paths.is_empty() {
+                job.inputs = arguments.input_paths.clone();
+            }
+            if let Some(preset) = &arguments.preset {
+                preset.apply_to(&mut job.settings);
+            }
+            arguments
+                .png_encoder
+                .apply_to(&mut job.settings.png_encoder);
+            if arguments.benchmark_png_encoder {
+                job.run_png_encoder_benchmark()
+            } else {
+                job.run()
+            }
+        });
+        if let Err(error_message) = result {
+            log::error!("{}", error_message);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    RepeatyGui::run(Settings {
+        flags: arguments,
+        ..Settings::default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement_with_layout(layout: TileLayout) -> TilePlacement {
+        TilePlacement {
+            layout,
+            ..TilePlacement::default()
+        }
+    }
+
+    #[test]
+    fn half_drop_moves_every_second_column_down() {
+        let placement = placement_with_layout(TileLayout::HalfDrop);
+        assert_eq!(placement.input_position(0, 0, 10, 8), Some((0, 0)));
+        assert_eq!(placement.input_position(9, 7, 10, 8), Some((9, 7)));
+        // The second column starts with the lower half of a tile
+        assert_eq!(placement.input_position(10, 0, 10, 8), Some((0, 4)));
+        assert_eq!(placement.input_position(12, 4, 10, 8), Some((2, 0)));
+        // The third column lines up with the first one again
+        assert_eq!(placement.input_position(23, 5, 10, 8), Some((3, 5)));
+    }
+
+    #[test]
+    fn half_brick_moves_every_second_row_right() {
+        let placement = placement_with_layout(TileLayout::HalfBrick);
+        assert_eq!(placement.input_position(3, 2, 10, 8), Some((3, 2)));
+        // The second row starts with the right half of a tile
+        assert_eq!(placement.input_position(0, 8, 10, 8), Some((5, 0)));
+        assert_eq!(placement.input_position(5, 9, 10, 8), Some((0, 1)));
+        // The third row lines up with the first one again
+        assert_eq!(placement.input_position(14, 17, 10, 8), Some((4, 1)));
+    }
+
+    #[test]
+    fn half_drop_with_gutter() {
+        let placement = TilePlacement {
+            gutter_x: 2,
+            gutter_y: 2,
+            ..placement_with_layout(TileLayout::HalfDrop)
+        };
+        // Cells are 12x10 pixels with the tile in their top left corner
+        assert_eq!(placement.input_position(10, 0, 10, 8), None);
+        assert_eq!(placement.input_position(0, 8, 10, 8), None);
+        // The second column is moved down by half a cell
+        assert_eq!(placement.input_position(12, 0, 10, 8), Some((0, 5)));
+        assert_eq!(placement.input_position(12, 3, 10, 8), None);
+        assert_eq!(placement.input_position(12, 5, 10, 8), Some((0, 0)));
+    }
+
+    #[test]
+    fn shifted_placement_keeps_the_varied_tiles() {
+        let (tile_width, tile_height) = (10, 8);
+        let variation = TileVariation::new(7, 3, true, true, false);
+        for &layout in &[
+            TileLayout::Grid,
+            TileLayout::HalfDrop,
+            TileLayout::HalfBrick,
+        ] {
+            let placement = TilePlacement {
+                gutter_x: 2,
+                gutter_y: 1,
+                ..placement_with_layout(layout)
+            }
+            .with_variation(variation)
+            .shifted(100, 60, tile_width, tile_height);
+            for &(shift_x, shift_y) in &[(0, 0), (37, 0), (0, -29), (-55, 43), (13, 9)] {
+                let shifted = placement.shifted(shift_x, shift_y, tile_width, tile_height);
+                // NOTE: Output positions are never negative
+                for y in (30..70).step_by(3) {
+                    for x in (60..110).step_by(7) {
+                        assert_eq!(
+                            shifted.input_position(x, y, tile_width, tile_height),
+                            placement.input_position(
+                                x + shift_x,
+                                y + shift_y,
+                                tile_width,
+                                tile_height
+                            ),
+                            "{:?} shifted by {}x{} at {}x{}",
+                            layout,
+                            shift_x,
+                            shift_y,
+                            x,
+                            y
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,143 @@
+use crate::pixel_buffer::{OutputColorType, PixelBuffer};
+
+/// Letters that name the motifs of a layout. `A` is always the input image.
+const MOTIF_NAMES: &str = "ABCD";
+
+pub const MAX_MOTIF_COUNT: usize = MOTIF_NAMES.len();
+
+/// Arrangement of several motifs inside one repeat. Written as rows of motif letters separated by
+/// `/`, i.e. `AB/BA` is a checkerboard of the motifs `A` and `B`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotifLayout {
+    /// Motif indices row by row
+    pub rows: Vec<Vec<usize>>,
+}
+
+impl MotifLayout {
+    pub fn parse(text: &str) -> Result<MotifLayout, String> {
+        let error =
+            |reason: String| format!("Could not parse motif layout '{}' : {}", text, reason);
+        let mut rows = Vec::new();
+        for row_text in text.split('/') {
+            let mut row = Vec::new();
+            for letter in row_text.trim().chars() {
+                let motif_index =
+                    MOTIF_NAMES
+                        .find(letter.to_ascii_uppercase())
+                        .ok_or_else(|| {
+                            error(format!(
+                                "'{}' is not a motif, use the letters A to D",
+                                letter
+                            ))
+                        })?;
+                row.push(motif_index);
+            }
+            if row.is_empty() {
+                return Err(error("A row is empty".to_string()));
+            }
+            rows.push(row);
+        }
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(error("All rows need the same length".to_string()));
+        }
+        Ok(MotifLayout { rows })
+    }
+
+    /// Number of motifs the layout refers to, i.e. 2 for `AB/BA`
+    pub fn motif_count(&self) -> usize {
+        self.rows
+            .iter()
+            .flatten()
+            .max()
+            .map(|&max_index| max_index + 1)
+            .unwrap_or(0)
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+}
+
+/// Returns the name of the motif with the given index, i.e. `B` for 1
+pub fn motif_name(motif_index: usize) -> char {
+    MOTIF_NAMES.as_bytes()[motif_index] as char
+}
+
+/// Places the motifs into one tile according to the layout. Expects motifs of the same size.
+/// Motifs with differing formats are converted to RGBA first.
+pub fn combine_motifs(motifs: &[PixelBuffer], layout: &MotifLayout) -> Result<PixelBuffer, String> {
+    let first = &motifs[0];
+    let has_same_format = motifs.iter().all(|motif| {
+        motif.color_type == first.color_type
+            && motif.bit_depth == first.bit_depth
+            && motif.palette == first.palette
+            && motif.transparency == first.transparency
+    });
+    let converted_motifs;
+    let motifs = if has_same_format {
+        motifs
+    } else {
+        if motifs
+            .iter()
+            .any(|motif| motif.bit_depth != first.bit_depth)
+        {
+            return Err("The motifs need the same bit depth".to_string());
+        }
+        converted_motifs = motifs
+            .iter()
+            .map(|motif| motif.converted(OutputColorType::Rgba))
+            .collect::<Result<Vec<_>, _>>()?;
+        &converted_motifs
+    };
+
+    let motif_width = first.width as usize;
+    let motif_height = first.height as usize;
+    let mut result = motifs[0].new_with_same_format(
+        (layout.column_count() * motif_width) as u32,
+        (layout.row_count() * motif_height) as u32,
+    );
+    let row_bytes = motif_width * result.bytes_per_pixel();
+    let result_row_bytes = layout.column_count() * row_bytes;
+    for (layout_row, motif_indices) in layout.rows.iter().enumerate() {
+        for (layout_column, &motif_index) in motif_indices.iter().enumerate() {
+            let motif = &motifs[motif_index];
+            for (y, motif_row) in motif.data.chunks_exact(row_bytes).enumerate() {
+                let begin =
+                    (layout_row * motif_height + y) * result_row_bytes + layout_column * row_bytes;
+                result.data[begin..(begin + row_bytes)].copy_from_slice(motif_row);
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_rows_of_motif_letters() {
+        let layout = MotifLayout::parse(" ab / Ba ").unwrap();
+        assert_eq!(layout.rows, vec![vec![0, 1], vec![1, 0]]);
+        assert_eq!(layout.motif_count(), 2);
+        assert_eq!(layout.column_count(), 2);
+        assert_eq!(layout.row_count(), 2);
+
+        let layout = MotifLayout::parse("ADC").unwrap();
+        assert_eq!(layout.rows, vec![vec![0, 3, 2]]);
+        assert_eq!(layout.motif_count(), 4);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_layouts() {
+        assert!(MotifLayout::parse("AE").is_err());
+        assert!(MotifLayout::parse("A B").is_err());
+        assert!(MotifLayout::parse("AB//BA").is_err());
+        assert!(MotifLayout::parse("").is_err());
+        assert!(MotifLayout::parse("AB/A").is_err());
+    }
+}
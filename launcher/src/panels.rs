@@ -0,0 +1,165 @@
+/// Splits the width of a pattern into panels of a fixed width like wallpaper drops or mural
+/// panels. Every panel has the full height of the pattern.
+#[derive(Debug, Clone)]
+pub struct PanelLayout {
+    pub panel_pixel_width: i32,
+    pub panel_width_mm: f64,
+    pub panel_height_mm: f64,
+    pub panel_count: usize,
+    /// Width of the strip that neighboring panels have in common
+    pub overlap_pixels: i32,
+    pub overlap_mm: f64,
+    /// How far every second panel moves the repeat down for a drop match
+    pub drop_pixels: i32,
+    /// `None` for panels that are not cut from rolls. Zero if a single panel is longer than the
+    /// roll.
+    pub panels_per_roll: Option<usize>,
+    /// Mural panels are printed with their exact physical width instead of the width of whole
+    /// pixels
+    pub is_mural: bool,
+}
+
+impl PanelLayout {
+    /// Panels with the width of a wallpaper roll that are placed edge to edge
+    pub fn for_wallpaper(
+        pattern_pixel_width: i32,
+        pattern_height_mm: f64,
+        pixel_per_mm: f64,
+        roll_width_mm: f64,
+        roll_length_mm: f64,
+        drop_pixels: i32,
+    ) -> PanelLayout {
+        let panel_pixel_width = ((roll_width_mm * pixel_per_mm).round() as i32).max(1);
+        PanelLayout {
+            panel_pixel_width,
+            panel_width_mm: panel_pixel_width as f64 / pixel_per_mm,
+            panel_height_mm: pattern_height_mm,
+            panel_count: panel_count(pattern_pixel_width, panel_pixel_width, 0),
+            overlap_pixels: 0,
+            overlap_mm: 0.0,
+            drop_pixels,
+            panels_per_roll: Some((roll_length_mm / pattern_height_mm).floor().max(0.0) as usize),
+            is_mural: false,
+        }
+    }
+
+    /// Mural panels of the given width where neighboring panels overlap for pasting
+    pub fn for_mural(
+        pattern_pixel_width: i32,
+        pattern_height_mm: f64,
+        pixel_per_mm: f64,
+        panel_width_mm: f64,
+        overlap_mm: f64,
+    ) -> PanelLayout {
+        let panel_pixel_width = ((panel_width_mm * pixel_per_mm).round() as i32).max(1);
+        let overlap_pixels = ((overlap_mm * pixel_per_mm).round() as i32)
+            .min(panel_pixel_width - 1)
+            .max(0);
+        PanelLayout {
+            panel_pixel_width,
+            panel_width_mm,
+            panel_height_mm: pattern_height_mm,
+            panel_count: panel_count(pattern_pixel_width, panel_pixel_width, overlap_pixels),
+            overlap_pixels,
+            overlap_mm: overlap_pixels as f64 / pixel_per_mm,
+            drop_pixels: 0,
+            panels_per_roll: None,
+            is_mural: true,
+        }
+    }
+
+    /// Number of rolls needed for all panels. Returns `None` if the panels are not cut from rolls
+    /// or if a panel does not fit onto a roll.
+    pub fn roll_count(&self) -> Option<usize> {
+        match self.panels_per_roll {
+            Some(panels_per_roll) if panels_per_roll > 0 => {
+                Some((self.panel_count + panels_per_roll - 1) / panels_per_roll)
+            }
+            _ => None,
+        }
+    }
+
+    /// Horizontal position of the panel inside the pattern
+    pub fn panel_offset_x(&self, panel_index: usize) -> i32 {
+        panel_index as i32 * (self.panel_pixel_width - self.overlap_pixels)
+    }
+
+    /// Vertical shift of the repeat on the panel relative to a straight match
+    pub fn panel_offset_y(&self, panel_index: usize) -> i32 {
+        (panel_index % 2) as i32 * self.drop_pixels
+    }
+}
+
+/// The last panel continues the repeat beyond the pattern width as panels can only be cut
+/// lengthwise
+fn panel_count(pattern_pixel_width: i32, panel_pixel_width: i32, overlap_pixels: i32) -> usize {
+    let pattern_pixel_width = pattern_pixel_width.max(1) as i64;
+    let panel_pixel_width = panel_pixel_width as i64;
+    if pattern_pixel_width <= panel_pixel_width {
+        return 1;
+    }
+    let step_pixels = (panel_pixel_width - overlap_pixels as i64).max(1);
+    let remainder = pattern_pixel_width - panel_pixel_width;
+    1 + ((remainder + step_pixels - 1) / step_pixels) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wallpaper_panels_cover_pattern_width() {
+        // A 530mm roll at one pixel per millimeter needs three panels for 1200 pixels
+        let panels = PanelLayout::for_wallpaper(1200, 2500.0, 1.0, 530.0, 10050.0, 0);
+        assert_eq!(panels.panel_pixel_width, 530);
+        assert_eq!(panels.panel_count, 3);
+        assert_eq!(panels.panel_offset_x(2), 1060);
+        assert!(panels.panel_offset_x(2) + panels.panel_pixel_width >= 1200);
+        assert_eq!(panels.panels_per_roll, Some(4));
+        assert_eq!(panels.roll_count(), Some(1));
+
+        let panels = PanelLayout::for_wallpaper(1060, 2500.0, 1.0, 530.0, 10050.0, 0);
+        assert_eq!(panels.panel_count, 2);
+    }
+
+    #[test]
+    fn wallpaper_drop_moves_every_second_panel() {
+        let panels = PanelLayout::for_wallpaper(2000, 2500.0, 1.0, 530.0, 10050.0, 64);
+        assert_eq!(panels.panel_count, 4);
+        assert_eq!(panels.panel_offset_y(0), 0);
+        assert_eq!(panels.panel_offset_y(1), 64);
+        assert_eq!(panels.panel_offset_y(2), 0);
+        assert_eq!(panels.panel_offset_y(3), 64);
+    }
+
+    #[test]
+    fn wallpaper_panel_longer_than_roll_needs_no_roll_count() {
+        let panels = PanelLayout::for_wallpaper(1000, 3000.0, 1.0, 530.0, 2000.0, 0);
+        assert_eq!(panels.panels_per_roll, Some(0));
+        assert_eq!(panels.roll_count(), None);
+    }
+
+    #[test]
+    fn mural_panels_overlap() {
+        let panels = PanelLayout::for_mural(4000, 2600.0, 1.0, 1000.0, 20.0);
+        assert_eq!(panels.overlap_pixels, 20);
+        // Every panel adds 980 new pixels after the first one
+        assert_eq!(panels.panel_count, 5);
+        for panel_index in 1..panels.panel_count {
+            let previous_end = panels.panel_offset_x(panel_index - 1) + panels.panel_pixel_width;
+            assert_eq!(previous_end - panels.panel_offset_x(panel_index), 20);
+        }
+        let last_end = panels.panel_offset_x(4) + panels.panel_pixel_width;
+        assert!(last_end >= 4000 && last_end - panels.panel_pixel_width < 4000);
+        assert_eq!(panels.roll_count(), None);
+        assert_eq!(panels.panel_offset_y(1), 0);
+    }
+
+    #[test]
+    fn mural_overlap_is_limited_to_panel_width() {
+        let panels = PanelLayout::for_mural(4000, 2600.0, 1.0, 100.0, 500.0);
+        assert_eq!(panels.overlap_pixels, 99);
+        // Every panel still adds one new pixel column
+        assert_eq!(panels.panel_count, 1 + (4000 - 100));
+    }
+}
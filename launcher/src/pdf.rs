@@ -0,0 +1,588 @@
+use crate::millimeter_in_inch;
+use crate::pixel_buffer::{ColorType, PixelBuffer};
+use crate::print_marks::PrintMarks;
+use crate::TilingGeometry;
+
+use flate2::{write::ZlibEncoder, Compression};
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+/// Pages with a side longer than this (in points) need to be scaled via the `UserUnit` entry
+const PDF_MAX_PAGE_SIDE_LENGTH: f64 = 14400.0;
+
+const CATALOG_OBJECT_ID: usize = 1;
+const PAGES_OBJECT_ID: usize = 2;
+
+fn millimeter_in_points(millimeter: f64) -> f64 {
+    millimeter_in_inch(millimeter) * 72.0
+}
+
+fn compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Splits the image into its color samples and an optional 8 or 16 bit alpha mask
+fn split_color_and_alpha(image: &PixelBuffer) -> (Vec<u8>, Option<Vec<u8>>) {
+    let bytes_per_sample = image.bit_depth as usize / 8;
+    match image.color_type {
+        ColorType::GrayAlpha | ColorType::Rgba => {
+            let bytes_per_pixel = image.bytes_per_pixel();
+            let color_bytes_per_pixel = bytes_per_pixel - bytes_per_sample;
+            let pixel_count = image.data.len() / bytes_per_pixel;
+            let mut color = Vec::with_capacity(pixel_count * color_bytes_per_pixel);
+            let mut alpha = Vec::with_capacity(pixel_count * bytes_per_sample);
+            for pixel in image.data.chunks_exact(bytes_per_pixel) {
+                color.extend_from_slice(&pixel[..color_bytes_per_pixel]);
+                alpha.extend_from_slice(&pixel[color_bytes_per_pixel..]);
+            }
+            (color, Some(alpha))
+        }
+        ColorType::Indexed => {
+            let alpha = image.transparency.as_ref().map(|transparency| {
+                image
+                    .data
+                    .iter()
+                    .map(|&index| transparency.get(index as usize).cloned().unwrap_or(0xFF))
+                    .collect()
+            });
+            (image.data.clone(), alpha)
+        }
+        ColorType::Gray | ColorType::Rgb => (image.data.clone(), None),
+    }
+}
+
+/// Physical size of a page. The bleed box is inset from the page edge by the margin and the trim
+/// box is inset from the bleed box by the bleed.
+pub struct PageSize {
+    pub width_mm: f64,
+    pub height_mm: f64,
+    pub bleed_mm: f64,
+    pub margin_mm: f64,
+}
+
+/// Physical page size in user space units. Pages that exceed the maximum page size are described
+/// in larger user units.
+struct PageGeometry {
+    user_unit: f64,
+    width: f64,
+    height: f64,
+    bleed: f64,
+    margin: f64,
+}
+
+impl PageGeometry {
+    fn new(page_size: &PageSize) -> PageGeometry {
+        let width_points = millimeter_in_points(page_size.width_mm);
+        let height_points = millimeter_in_points(page_size.height_mm);
+        let user_unit = (width_points.max(height_points) / PDF_MAX_PAGE_SIDE_LENGTH).max(1.0);
+        PageGeometry {
+            user_unit,
+            width: width_points / user_unit,
+            height: height_points / user_unit,
+            bleed: millimeter_in_points(page_size.bleed_mm) / user_unit,
+            margin: millimeter_in_points(page_size.margin_mm) / user_unit,
+        }
+    }
+
+    fn millimeter_in_units(&self, millimeter: f64) -> f64 {
+        millimeter_in_points(millimeter) / self.user_unit
+    }
+}
+
+/// Writes pdf files with one image per page. Pages are written to disk as soon as they are added
+/// so that only the current page needs to be kept in memory.
+pub struct PdfWriter {
+    filepath: String,
+    writer: BufWriter<File>,
+    position: u64,
+    /// Byte offsets of all objects in the file. Object zero is unused per definition.
+    object_offsets: Vec<u64>,
+    page_object_ids: Vec<usize>,
+    /// Every distinct ICC profile is written once and shared by all images that use it
+    icc_profile_object_ids: HashMap<Vec<u8>, usize>,
+    /// The ICC profile of the first page that has one is used as output intent for the document
+    output_intent_profile_object_id: Option<usize>,
+}
+
+impl PdfWriter {
+    pub fn create(filepath: &str) -> Result<PdfWriter, String> {
+        let file = File::create(filepath)
+            .map_err(|error| format!("Could not create pdf file '{}' : {}", filepath, error))?;
+        let mut result = PdfWriter {
+            filepath: filepath.to_string(),
+            writer: BufWriter::new(file),
+            position: 0,
+            object_offsets: vec![0; PAGES_OBJECT_ID + 1],
+            page_object_ids: Vec::new(),
+            icc_profile_object_ids: HashMap::new(),
+            output_intent_profile_object_id: None,
+        };
+        // NOTE: The comment with high bytes marks the file as binary for transfer programs
+        result.write_bytes(b"%PDF-1.6\n%\xE2\xE3\xCF\xD3\n")?;
+        Ok(result)
+    }
+
+    pub fn filepath(&self) -> &str {
+        &self.filepath
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.page_object_ids.len()
+    }
+
+    /// Adds a page with the given physical size that is completely filled by the given image. The
+    /// image is embedded with its native resolution. The optional ICC profile is expected to be
+    /// zlib compressed like in a png `iCCP` chunk.
+    pub fn add_page(
+        &mut self,
+        image: &PixelBuffer,
+        icc_profile: Option<&[u8]>,
+        page_size: &PageSize,
+    ) -> Result<(), String> {
+        let image_object_id = self.write_image(image, icc_profile)?;
+        let page = PageGeometry::new(page_size);
+        let content = format!(
+            "q {:.4} 0 0 {:.4} 0 0 cm /Im0 Do Q",
+            page.width, page.height
+        );
+        let resources = format!("<< /XObject << /Im0 {} 0 R >> >>", image_object_id);
+        self.write_page(&page, &resources, &content)
+    }
+
+    /// Adds a page with the given physical size whose bleed box is filled with a tiling pattern.
+    /// The tile is embedded only once with its native resolution regardless of the page size.
+    /// Optional print marks are drawn as vector graphics into the margin.
+    pub fn add_tiling_pattern_page(
+        &mut self,
+        tile: &PixelBuffer,
+        icc_profile: Option<&[u8]>,
+        tiling: &TilingGeometry,
+        page_size: &PageSize,
+        print_marks: Option<&PrintMarks>,
+    ) -> Result<(), String> {
+        let image_object_id = self.write_image(tile, icc_profile)?;
+        let page = PageGeometry::new(page_size);
+        let tile_width = page.millimeter_in_units(tiling.tile_width_mm);
+        let tile_height = page.millimeter_in_units(tiling.tile_height_mm);
+
+        // NOTE: The pattern space has its origin at the bottom left so the tile positions are
+        //       flipped vertically inside the cell
+        let (cell_width_mm, cell_height_mm, tile_positions_mm) = tiling.cell_tile_positions();
+        let cell_width = page.millimeter_in_units(cell_width_mm);
+        let cell_height = page.millimeter_in_units(cell_height_mm);
+        let cell_content: String = tile_positions_mm
+            .iter()
+            .map(|&(x, y)| {
+                format!(
+                    "q {:.4} 0 0 {:.4} {:.4} {:.4} cm /Im0 Do Q\n",
+                    tile_width,
+                    tile_height,
+                    page.millimeter_in_units(x),
+                    cell_height - page.millimeter_in_units(y) - tile_height
+                )
+            })
+            .collect();
+
+        // The top left corner of the page shows the given offset inside the top left tile
+        let translation_x = -page.millimeter_in_units(tiling.offset_x_mm);
+        let translation_y =
+            page.height - cell_height + page.millimeter_in_units(tiling.offset_y_mm);
+
+        let pattern_object_id = self.reserve_object_id();
+        self.write_stream_object(
+            pattern_object_id,
+            &format!(
+                "/Type /Pattern /PatternType 1 /PaintType 1 /TilingType 1 \
+                 /BBox [0 0 {:.4} {:.4}] /XStep {:.4} /YStep {:.4} \
+                 /Matrix [1 0 0 1 {:.4} {:.4}] /Resources << /XObject << /Im0 {} 0 R >> >>",
+                cell_width,
+                cell_height,
+                cell_width,
+                cell_height,
+                translation_x,
+                translation_y,
+                image_object_id
+            ),
+            cell_content.as_bytes(),
+        )?;
+
+        let mut content = format!(
+            "/Pattern cs /P0 scn {:.4} {:.4} {:.4} {:.4} re f\n",
+            page.margin,
+            page.margin,
+            page.width - 2.0 * page.margin,
+            page.height - 2.0 * page.margin
+        );
+        let resources = format!("<< /Pattern << /P0 {} 0 R >> >>", pattern_object_id);
+        if let Some(print_marks) = print_marks {
+            content += &print_marks.pdf_content(page.millimeter_in_units(1.0));
+        }
+        self.write_page(&page, &resources, &content)
+    }
+
+    fn write_page(
+        &mut self,
+        page: &PageGeometry,
+        resources: &str,
+        content: &str,
+    ) -> Result<(), String> {
+        let content_object_id = self.reserve_object_id();
+        self.write_stream_object(content_object_id, "", content.as_bytes())?;
+
+        let user_unit_entry = if page.user_unit > 1.0 {
+            format!(" /UserUnit {:.6}", page.user_unit)
+        } else {
+            String::new()
+        };
+        let page_boxes_entry = if page.bleed > 0.0 || page.margin > 0.0 {
+            let trim_inset = page.margin + page.bleed;
+            format!(
+                " /BleedBox [{:.4} {:.4} {:.4} {:.4}] /TrimBox [{:.4} {:.4} {:.4} {:.4}]",
+                page.margin,
+                page.margin,
+                page.width - page.margin,
+                page.height - page.margin,
+                trim_inset,
+                trim_inset,
+                page.width - trim_inset,
+                page.height - trim_inset
+            )
+        } else {
+            String::new()
+        };
+        let page_object_id = self.reserve_object_id();
+        self.write_object(
+            page_object_id,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {:.4} {:.4}]{}{} \
+                 /Resources {} /Contents {} 0 R >>",
+                PAGES_OBJECT_ID,
+                page.width,
+                page.height,
+                page_boxes_entry,
+                user_unit_entry,
+                resources,
+                content_object_id
+            ),
+        )?;
+        self.page_object_ids.push(page_object_id);
+
+        Ok(())
+    }
+
+    /// Writes the given image as image object with an optional soft mask and returns its id
+    fn write_image(
+        &mut self,
+        image: &PixelBuffer,
+        icc_profile: Option<&[u8]>,
+    ) -> Result<usize, String> {
+        let (color_data, alpha_data) = split_color_and_alpha(image);
+
+        let icc_profile_object_id = match icc_profile {
+            Some(icc_profile) => Some(self.write_icc_profile(icc_profile, image.color_type)?),
+            None => None,
+        };
+
+        let direct_color_space = match (icc_profile_object_id, image.color_type.is_grayscale()) {
+            (Some(object_id), _) => format!("[/ICCBased {} 0 R]", object_id),
+            (None, true) => "/DeviceGray".to_string(),
+            (None, false) => "/DeviceRGB".to_string(),
+        };
+        let color_space = if image.color_type == ColorType::Indexed {
+            let palette_hex: String = image
+                .palette
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            format!(
+                "[/Indexed {} {} <{}>]",
+                direct_color_space,
+                (image.palette.len() / 3).saturating_sub(1),
+                palette_hex
+            )
+        } else {
+            direct_color_space
+        };
+
+        let soft_mask_entry = match alpha_data {
+            Some(alpha_data) => {
+                let alpha_bit_depth = if image.color_type == ColorType::Indexed {
+                    8
+                } else {
+                    image.bit_depth
+                };
+                let object_id = self.reserve_object_id();
+                let compressed = compress(&alpha_data).map_err(|error| self.io_error(error))?;
+                self.write_stream_object(
+                    object_id,
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                         /ColorSpace /DeviceGray /BitsPerComponent {} /Filter /FlateDecode",
+                        image.width, image.height, alpha_bit_depth
+                    ),
+                    &compressed,
+                )?;
+                format!(" /SMask {} 0 R", object_id)
+            }
+            None => String::new(),
+        };
+
+        // NOTE: Grayscale and RGB images can declare a single fully transparent color
+        let color_key_mask_entry = match (&image.transparency, image.color_type) {
+            (Some(transparency), ColorType::Gray) | (Some(transparency), ColorType::Rgb) => {
+                let key_values: Vec<String> = transparency
+                    .chunks_exact(2)
+                    .take(image.color_type.sample_count())
+                    .map(|bytes| {
+                        let value = u16::from_be_bytes([bytes[0], bytes[1]]);
+                        format!("{} {}", value, value)
+                    })
+                    .collect();
+                format!(" /Mask [{}]", key_values.join(" "))
+            }
+            _ => String::new(),
+        };
+
+        let image_object_id = self.reserve_object_id();
+        let compressed = {
+            let _timer = ct_lib::TimerScoped::new_scoped("Compressing pdf image", true);
+            compress(&color_data).map_err(|error| self.io_error(error))?
+        };
+        drop(color_data);
+        self.write_stream_object(
+            image_object_id,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} \
+                 /BitsPerComponent {} /Filter /FlateDecode{}{}",
+                image.width,
+                image.height,
+                color_space,
+                image.bit_depth,
+                soft_mask_entry,
+                color_key_mask_entry
+            ),
+            &compressed,
+        )?;
+
+        Ok(image_object_id)
+    }
+
+    /// Writes the given ICC profile if it was not written before and returns its object id
+    fn write_icc_profile(
+        &mut self,
+        icc_profile: &[u8],
+        color_type: ColorType,
+    ) -> Result<usize, String> {
+        if let Some(&object_id) = self.icc_profile_object_ids.get(icc_profile) {
+            return Ok(object_id);
+        }
+
+        let component_count = if color_type.is_grayscale() { 1 } else { 3 };
+        let object_id = self.reserve_object_id();
+        self.write_stream_object(
+            object_id,
+            &format!("/N {} /Filter /FlateDecode", component_count),
+            icc_profile,
+        )?;
+        self.icc_profile_object_ids
+            .insert(icc_profile.to_vec(), object_id);
+        if self.output_intent_profile_object_id.is_none() {
+            self.output_intent_profile_object_id = Some(object_id);
+        }
+        Ok(object_id)
+    }
+
+    /// Writes the document structure and cross reference table and closes the file
+    pub fn finish(mut self) -> Result<(), String> {
+        let kids: Vec<String> = self
+            .page_object_ids
+            .iter()
+            .map(|object_id| format!("{} 0 R", object_id))
+            .collect();
+        self.write_object(
+            PAGES_OBJECT_ID,
+            &format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.page_object_ids.len()
+            ),
+        )?;
+
+        // NOTE: We do not claim PDF/X conformance so the output intent uses the `GTS_PDFA1`
+        //       subtype which only describes the intended output color space
+        let output_intents_entry = match self.output_intent_profile_object_id {
+            Some(object_id) => format!(
+                " /OutputIntents [<< /Type /OutputIntent /S /GTS_PDFA1 \
+                 /OutputConditionIdentifier (Custom) /DestOutputProfile {} 0 R >>]",
+                object_id
+            ),
+            None => String::new(),
+        };
+        self.write_object(
+            CATALOG_OBJECT_ID,
+            &format!(
+                "<< /Type /Catalog /Pages {} 0 R{} >>",
+                PAGES_OBJECT_ID, output_intents_entry
+            ),
+        )?;
+
+        // NOTE: Every cross reference entry needs to be exactly 20 bytes long
+        let xref_position = self.position;
+        let mut xref = format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.object_offsets.len()
+        );
+        for offset in self.object_offsets.iter().skip(1) {
+            xref += &format!("{:010} 00000 n \n", offset);
+        }
+        xref += &format!(
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.object_offsets.len(),
+            CATALOG_OBJECT_ID,
+            xref_position
+        );
+        self.write_bytes(xref.as_bytes())?;
+
+        self.writer.flush().map_err(|error| self.io_error(error))
+    }
+
+    fn io_error(&self, error: std::io::Error) -> String {
+        format!("Could not write pdf file '{}' : {}", self.filepath, error)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        if let Err(error) = self.writer.write_all(bytes) {
+            return Err(self.io_error(error));
+        }
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn reserve_object_id(&mut self) -> usize {
+        self.object_offsets.push(0);
+        self.object_offsets.len() - 1
+    }
+
+    fn write_object(&mut self, object_id: usize, content: &str) -> Result<(), String> {
+        self.object_offsets[object_id] = self.position;
+        self.write_bytes(format!("{} 0 obj\n{}\nendobj\n", object_id, content).as_bytes())
+    }
+
+    fn write_stream_object(
+        &mut self,
+        object_id: usize,
+        dictionary_entries: &str,
+        data: &[u8],
+    ) -> Result<(), String> {
+        self.object_offsets[object_id] = self.position;
+        self.write_bytes(
+            format!(
+                "{} 0 obj\n<< {} /Length {} >>\nstream\n",
+                object_id,
+                dictionary_entries,
+                data.len()
+            )
+            .as_bytes(),
+        )?;
+        self.write_bytes(data)?;
+        self.write_bytes(b"\nendstream\nendobj\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_test_pdf(name: &str, page_count: usize, icc_profile: Option<&[u8]>) -> Vec<u8> {
+        let filepath = std::env::temp_dir()
+            .join(format!("repeaty_test_{}.pdf", name))
+            .to_string_lossy()
+            .to_string();
+        let mut writer = PdfWriter::create(&filepath).unwrap();
+        let image = PixelBuffer::new(4, 3, ColorType::Rgb, 8);
+        let page_size = PageSize {
+            width_mm: 100.0,
+            height_mm: 75.0,
+            bleed_mm: 0.0,
+            margin_mm: 0.0,
+        };
+        for _ in 0..page_count {
+            writer.add_page(&image, icc_profile, &page_size).unwrap();
+        }
+        writer.finish().unwrap();
+        let result = std::fs::read(&filepath).unwrap();
+        std::fs::remove_file(&filepath).ok();
+        result
+    }
+
+    fn count_occurrences(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|window| *window == needle)
+            .count()
+    }
+
+    #[test]
+    fn header_and_cross_reference_offsets() {
+        let pdf = write_test_pdf("xref", 2, None);
+        assert!(pdf.starts_with(b"%PDF-1.6\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        // NOTE: Everything after the last stream is plain ASCII
+        let startxref = pdf
+            .windows(b"startxref\n".len())
+            .rposition(|window| window == b"startxref\n")
+            .unwrap();
+        let trailer = std::str::from_utf8(&pdf[startxref..]).unwrap();
+        let xref_position: usize = trailer.lines().nth(1).unwrap().parse().unwrap();
+        let xref = std::str::from_utf8(&pdf[xref_position..]).unwrap();
+        assert!(xref.starts_with("xref\n"));
+
+        let mut lines = xref.lines().skip(1);
+        let object_count: usize = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(lines.next().unwrap(), "0000000000 65535 f ");
+        for object_id in 1..object_count {
+            let entry = lines.next().unwrap();
+            assert_eq!(entry.len() + 1, 20);
+            let offset: usize = entry[..10].parse().unwrap();
+            let expected = format!("{} 0 obj\n", object_id);
+            assert!(pdf[offset..].starts_with(expected.as_bytes()));
+        }
+        assert!(lines.next().unwrap().starts_with("trailer"));
+    }
+
+    #[test]
+    fn page_has_physical_size_in_points() {
+        let pdf = write_test_pdf("mediabox", 1, None);
+        // 100mm x 75mm
+        assert_eq!(
+            count_occurrences(&pdf, b"/MediaBox [0 0 283.4646 212.5984]"),
+            1
+        );
+        assert_eq!(count_occurrences(&pdf, b"/UserUnit"), 0);
+        assert_eq!(count_occurrences(&pdf, b"/OutputIntents"), 0);
+    }
+
+    #[test]
+    fn icc_profile_is_written_once() {
+        let icc_profile = compress(b"not a real icc profile").unwrap();
+        let pdf = write_test_pdf("icc", 3, Some(&icc_profile));
+        assert_eq!(count_occurrences(&pdf, b"/N 3 /Filter /FlateDecode"), 1);
+        assert_eq!(count_occurrences(&pdf, b"/Subtype /Image"), 3);
+        assert_eq!(count_occurrences(&pdf, b"/S /GTS_PDFA1"), 1);
+        assert_eq!(count_occurrences(&pdf, b"/GTS_PDFX"), 0);
+    }
+}
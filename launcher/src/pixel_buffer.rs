@@ -0,0 +1,799 @@
+use ct_lib::serde_derive::{Deserialize, Serialize};
+
+use std::{collections::HashMap, fs::File};
+
+/// Layout of the samples of a single pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    /// One 8 bit palette index per pixel
+    Indexed,
+}
+
+impl ColorType {
+    pub fn sample_count(self) -> usize {
+        match self {
+            ColorType::Gray => 1,
+            ColorType::GrayAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4,
+            ColorType::Indexed => 1,
+        }
+    }
+
+    pub fn is_grayscale(self) -> bool {
+        match self {
+            ColorType::Gray | ColorType::GrayAlpha => true,
+            ColorType::Rgb | ColorType::Rgba | ColorType::Indexed => false,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorType::Gray => "Gray",
+            ColorType::GrayAlpha => "Gray+Alpha",
+            ColorType::Rgb => "RGB",
+            ColorType::Rgba => "RGBA",
+            ColorType::Indexed => "Indexed",
+        }
+    }
+
+    pub fn to_mtpng(self) -> mtpng::ColorType {
+        match self {
+            ColorType::Gray => mtpng::ColorType::Greyscale,
+            ColorType::GrayAlpha => mtpng::ColorType::GreyscaleAlpha,
+            ColorType::Rgb => mtpng::ColorType::Truecolor,
+            ColorType::Rgba => mtpng::ColorType::TruecolorAlpha,
+            ColorType::Indexed => mtpng::ColorType::IndexedColor,
+        }
+    }
+}
+
+/// The color type of the output image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputColorType {
+    /// Same color type as the input image
+    Original,
+    /// The smallest color type that represents the input image without loss
+    Smallest,
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+    Indexed,
+}
+impl Default for OutputColorType {
+    fn default() -> Self {
+        OutputColorType::Original
+    }
+}
+
+/// An image that keeps the color type and bit depth of its source. Samples are stored in the same
+/// byte order as in a png file which means that 16 bit samples are big endian.
+#[derive(Debug, Clone)]
+pub struct PixelBuffer {
+    pub width: i32,
+    pub height: i32,
+    pub color_type: ColorType,
+    /// Either 8 or 16. Indexed images always use 8 bit indices.
+    pub bit_depth: u8,
+    /// Bit depth of the png file that 1, 2 or 4 bit samples were unpacked from. Same as
+    /// `bit_depth` otherwise.
+    pub source_bit_depth: u8,
+    pub data: Vec<u8>,
+    /// RGB triples of the `PLTE` chunk for indexed images
+    pub palette: Vec<u8>,
+    /// Content of the `tRNS` chunk for grayscale, RGB and indexed images
+    pub transparency: Option<Vec<u8>>,
+}
+
+impl PixelBuffer {
+    pub fn new(width: u32, height: u32, color_type: ColorType, bit_depth: u8) -> PixelBuffer {
+        assert!(bit_depth == 8 || bit_depth == 16);
+        assert!(color_type != ColorType::Indexed || bit_depth == 8);
+        let bytes_per_pixel = color_type.sample_count() * (bit_depth as usize / 8);
+        PixelBuffer {
+            width: width as i32,
+            height: height as i32,
+            color_type,
+            bit_depth,
+            source_bit_depth: bit_depth,
+            data: vec![0; width as usize * height as usize * bytes_per_pixel],
+            palette: Vec::new(),
+            transparency: None,
+        }
+    }
+
+    /// Creates an empty image with the same color type, bit depth, palette and transparency
+    pub fn new_with_same_format(&self, width: u32, height: u32) -> PixelBuffer {
+        let mut result = PixelBuffer::new(width, height, self.color_type, self.bit_depth);
+        result.source_bit_depth = self.source_bit_depth;
+        result.palette = self.palette.clone();
+        result.transparency = self.transparency.clone();
+        result
+    }
+
+    pub fn from_png_file(image_filepath: &str) -> Result<PixelBuffer, String> {
+        let file = File::open(image_filepath)
+            .map_err(|error| format!("Could not open file '{}' : {}", image_filepath, error))?;
+        let decoding_error_message = format!("Could not decode png file '{}'", image_filepath);
+
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder
+            .read_info()
+            .map_err(|error| format!("{} : {}", decoding_error_message, error))?;
+        let mut decoded = vec![0; info.buffer_size()];
+        reader
+            .next_frame(&mut decoded)
+            .map_err(|error| format!("{} : {}", decoding_error_message, error))?;
+
+        let color_type = match info.color_type {
+            png::ColorType::Grayscale => ColorType::Gray,
+            png::ColorType::GrayscaleAlpha => ColorType::GrayAlpha,
+            png::ColorType::RGB => ColorType::Rgb,
+            png::ColorType::RGBA => ColorType::Rgba,
+            png::ColorType::Indexed => ColorType::Indexed,
+        };
+        let source_bit_depth = info.bit_depth as u8;
+        let palette = reader.info().palette.clone().unwrap_or_default();
+        let mut transparency = reader.info().trns.clone();
+
+        // We unpack samples with less than 8 bit so that every pixel starts at a byte boundary
+        let (bit_depth, data) = if source_bit_depth < 8 {
+            let scale_to_8_bit = color_type == ColorType::Gray;
+            let data = unpack_samples_to_8_bit(
+                &decoded,
+                info.width as usize,
+                info.height as usize,
+                info.line_size,
+                source_bit_depth,
+                scale_to_8_bit,
+            );
+            if scale_to_8_bit {
+                // The transparent gray value needs to be scaled as well
+                if let Some(gray_key) = &mut transparency {
+                    if gray_key.len() == 2 {
+                        let max_value = (1u16 << source_bit_depth) - 1;
+                        let value = u16::from_be_bytes([gray_key[0], gray_key[1]]);
+                        let scaled = (value.min(max_value) as u32 * 255 / max_value as u32) as u16;
+                        gray_key.copy_from_slice(&scaled.to_be_bytes());
+                    }
+                }
+            }
+            (8, data)
+        } else {
+            (source_bit_depth, decoded)
+        };
+
+        Ok(PixelBuffer {
+            width: info.width as i32,
+            height: info.height as i32,
+            color_type,
+            bit_depth,
+            source_bit_depth,
+            data,
+            palette,
+            transparency,
+        })
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.color_type.sample_count() * (self.bit_depth as usize / 8)
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> &[u8] {
+        debug_assert!(0 <= x && x < self.width && 0 <= y && y < self.height);
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let index = (y as usize * self.width as usize + x as usize) * bytes_per_pixel;
+        &self.data[index..(index + bytes_per_pixel)]
+    }
+
+    /// Returns the bytes of an opaque gray pixel in the format of this image. Indexed images are not
+    /// supported because their palette does not necessarily contain the gray value.
+    pub fn opaque_gray_pixel(&self, value: u8) -> Vec<u8> {
+        assert!(self.color_type != ColorType::Indexed);
+        let bytes_per_sample = self.bit_depth as usize / 8;
+        let color_sample_count = if self.color_type.is_grayscale() { 1 } else { 3 };
+        let mut result = vec![value; color_sample_count * bytes_per_sample];
+        if self.color_type == ColorType::GrayAlpha || self.color_type == ColorType::Rgba {
+            result.extend(std::iter::repeat(255).take(bytes_per_sample));
+        }
+        result
+    }
+
+    /// Returns the bytes of a pixel with the given color in the format of this image or of a fully
+    /// transparent pixel if no color is given. Grayscale images get the luma of the color.
+    /// Indexed images are not supported for the same reason as in `opaque_gray_pixel`.
+    pub fn background_pixel(&self, color: Option<[u8; 3]>) -> Vec<u8> {
+        assert!(self.color_type != ColorType::Indexed);
+        let bytes_per_sample = self.bit_depth as usize / 8;
+        let color_samples = match color {
+            Some([r, g, b]) if self.color_type.is_grayscale() => {
+                // NOTE: We use the Rec. 709 luma coefficients like `rgba_to_direct_color`
+                let gray = 0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64;
+                vec![gray.round() as u8]
+            }
+            Some(rgb) => rgb.to_vec(),
+            None if self.color_type.is_grayscale() => vec![0],
+            None => vec![0, 0, 0],
+        };
+        let mut result: Vec<u8> = color_samples
+            .iter()
+            .flat_map(|&sample| std::iter::repeat(sample).take(bytes_per_sample))
+            .collect();
+        if self.color_type == ColorType::GrayAlpha || self.color_type == ColorType::Rgba {
+            let alpha = if color.is_some() { 255 } else { 0 };
+            result.extend(std::iter::repeat(alpha).take(bytes_per_sample));
+        }
+        result
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Example: "RGB 16bit"
+    pub fn format_description(&self) -> String {
+        format!("{} {}bit", self.color_type.name(), self.bit_depth)
+    }
+
+    /// Returns the image converted to the given output color type. The bit depth is kept except for
+    /// indexed output which requires 8 bit.
+    pub fn converted(&self, output_color_type: OutputColorType) -> Result<PixelBuffer, String> {
+        let target_color_type = match output_color_type {
+            OutputColorType::Original => return Ok(self.clone()),
+            OutputColorType::Smallest => self.smallest_lossless_color_type(),
+            OutputColorType::Gray => ColorType::Gray,
+            OutputColorType::GrayAlpha => ColorType::GrayAlpha,
+            OutputColorType::Rgb => ColorType::Rgb,
+            OutputColorType::Rgba => ColorType::Rgba,
+            OutputColorType::Indexed => ColorType::Indexed,
+        };
+        if target_color_type == self.color_type {
+            return Ok(self.clone());
+        }
+
+        let rgba = self.to_rgba();
+        if (target_color_type == ColorType::Gray || target_color_type == ColorType::Rgb)
+            && !rgba.is_opaque()
+        {
+            return Err(format!(
+                "The image has transparent parts that can not be kept in {} output, please \
+                 choose an output color type with transparency or flatten the image",
+                target_color_type.name()
+            ));
+        }
+        match target_color_type {
+            ColorType::Rgba => Ok(rgba),
+            ColorType::Indexed => rgba.rgba_to_indexed(),
+            _ => Ok(rgba.rgba_to_direct_color(target_color_type)),
+        }
+    }
+
+    /// Expects an RGBA image
+    fn is_opaque(&self) -> bool {
+        debug_assert!(self.color_type == ColorType::Rgba);
+        let max_sample = self.max_sample();
+        self.data
+            .chunks_exact(self.bytes_per_pixel())
+            .all(|pixel| self.sample(pixel, 3) == max_sample)
+    }
+
+    /// Packs the samples back into the 1, 2 or 4 bit of the source png file. Returns `None` if the
+    /// image was not unpacked or if some samples can not be represented with the source bit depth
+    /// anymore.
+    pub fn packed_to_source_bit_depth(&self) -> Option<PackedSamples> {
+        let bit_depth = self.source_bit_depth;
+        if bit_depth >= 8 || self.bit_depth != 8 || self.width == 0 {
+            return None;
+        }
+        let max_value = (1u8 << bit_depth) - 1;
+        let scale = match self.color_type {
+            ColorType::Gray => 255 / max_value,
+            ColorType::Indexed => 1,
+            _ => return None,
+        };
+        let is_representable = |value: u8| value % scale == 0 && value / scale <= max_value;
+        if !self.data.iter().all(|&value| is_representable(value)) {
+            return None;
+        }
+
+        // The transparent gray value was scaled to 8 bit when unpacking
+        let transparency = match (&self.transparency, self.color_type) {
+            (Some(gray_key), ColorType::Gray) if gray_key.len() == 2 => {
+                let value = u16::from_be_bytes([gray_key[0], gray_key[1]]);
+                if value > 0xFF || !is_representable(value as u8) {
+                    return None;
+                }
+                Some((value / scale as u16).to_be_bytes().to_vec())
+            }
+            (transparency, _) => transparency.clone(),
+        };
+
+        let samples_per_byte = 8 / bit_depth as usize;
+        let width = self.width as usize;
+        let line_size = (width + samples_per_byte - 1) / samples_per_byte;
+        let mut data = vec![0; line_size * self.height as usize];
+        for (row, packed_row) in self
+            .data
+            .chunks_exact(width)
+            .zip(data.chunks_exact_mut(line_size))
+        {
+            for (x, &value) in row.iter().enumerate() {
+                let shift = 8 - bit_depth as usize * (x % samples_per_byte + 1);
+                packed_row[x / samples_per_byte] |= (value / scale) << shift;
+            }
+        }
+
+        Some(PackedSamples {
+            bit_depth,
+            data,
+            transparency,
+        })
+    }
+
+    /// Returns the sample value for an opaque pixel
+    fn max_sample(&self) -> u16 {
+        if self.bit_depth == 16 {
+            0xFFFF
+        } else {
+            0xFF
+        }
+    }
+
+    fn sample(&self, pixel: &[u8], index: usize) -> u16 {
+        if self.bit_depth == 16 {
+            u16::from_be_bytes([pixel[2 * index], pixel[2 * index + 1]])
+        } else {
+            pixel[index] as u16
+        }
+    }
+
+    fn push_sample(&self, data: &mut Vec<u8>, value: u16) {
+        if self.bit_depth == 16 {
+            data.extend_from_slice(&value.to_be_bytes());
+        } else {
+            data.push(value as u8);
+        }
+    }
+
+    /// Returns the given sample of the pixel normalized to 0..1
+    pub fn normalized_sample(&self, pixel: &[u8], index: usize) -> f64 {
+        self.sample(pixel, index) as f64 / self.max_sample() as f64
+    }
+
+    /// Appends a sample that is given normalized to 0..1
+    pub fn push_normalized_sample(&self, data: &mut Vec<u8>, value: f64) {
+        let value = value.max(0.0).min(1.0) * self.max_sample() as f64;
+        self.push_sample(data, value.round() as u16);
+    }
+
+    /// Overwrites a sample of the pixel with a value that is given normalized to 0..1
+    pub fn set_normalized_sample(&self, pixel: &mut [u8], index: usize, value: f64) {
+        let value = (value.max(0.0).min(1.0) * self.max_sample() as f64).round() as u16;
+        if self.bit_depth == 16 {
+            pixel[(2 * index)..(2 * index + 2)].copy_from_slice(&value.to_be_bytes());
+        } else {
+            pixel[index] = value as u8;
+        }
+    }
+
+    /// Converts the image into RGBA with the same bit depth. Indexed images are converted to
+    /// 8 bit RGBA.
+    pub fn to_rgba(&self) -> PixelBuffer {
+        if self.color_type == ColorType::Rgba {
+            return self.clone();
+        }
+
+        let max_sample = self.max_sample();
+        let transparent_key: Option<Vec<u16>> = match (&self.transparency, self.color_type) {
+            (Some(trns), ColorType::Gray) if trns.len() >= 2 => {
+                Some(vec![u16::from_be_bytes([trns[0], trns[1]])])
+            }
+            (Some(trns), ColorType::Rgb) if trns.len() >= 6 => Some(
+                trns.chunks_exact(2)
+                    .take(3)
+                    .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let mut result = PixelBuffer::new(
+            self.width as u32,
+            self.height as u32,
+            ColorType::Rgba,
+            self.bit_depth,
+        );
+        let mut data = Vec::with_capacity(result.data.len());
+        for pixel in self.data.chunks_exact(self.bytes_per_pixel()) {
+            let (r, g, b, a) = match self.color_type {
+                ColorType::Gray => {
+                    let gray = self.sample(pixel, 0);
+                    let is_transparent = transparent_key
+                        .as_ref()
+                        .map(|key| key[0] == gray)
+                        .unwrap_or(false);
+                    (
+                        gray,
+                        gray,
+                        gray,
+                        if is_transparent { 0 } else { max_sample },
+                    )
+                }
+                ColorType::GrayAlpha => {
+                    let gray = self.sample(pixel, 0);
+                    (gray, gray, gray, self.sample(pixel, 1))
+                }
+                ColorType::Rgb => {
+                    let (r, g, b) = (
+                        self.sample(pixel, 0),
+                        self.sample(pixel, 1),
+                        self.sample(pixel, 2),
+                    );
+                    let is_transparent = transparent_key
+                        .as_ref()
+                        .map(|key| key[0] == r && key[1] == g && key[2] == b)
+                        .unwrap_or(false);
+                    (r, g, b, if is_transparent { 0 } else { max_sample })
+                }
+                ColorType::Indexed => {
+                    let index = pixel[0] as usize;
+                    let color = self
+                        .palette
+                        .get((3 * index)..(3 * index + 3))
+                        .unwrap_or(&[0, 0, 0]);
+                    let alpha = self
+                        .transparency
+                        .as_ref()
+                        .and_then(|trns| trns.get(index).cloned())
+                        .unwrap_or(0xFF);
+                    (
+                        color[0] as u16,
+                        color[1] as u16,
+                        color[2] as u16,
+                        alpha as u16,
+                    )
+                }
+                ColorType::Rgba => unreachable!(),
+            };
+            result.push_sample(&mut data, r);
+            result.push_sample(&mut data, g);
+            result.push_sample(&mut data, b);
+            result.push_sample(&mut data, a);
+        }
+        result.data = data;
+        result
+    }
+
+    /// Expects an RGBA image
+    fn rgba_to_direct_color(&self, target_color_type: ColorType) -> PixelBuffer {
+        debug_assert!(self.color_type == ColorType::Rgba);
+
+        let mut result = PixelBuffer::new(
+            self.width as u32,
+            self.height as u32,
+            target_color_type,
+            self.bit_depth,
+        );
+        let mut data = Vec::with_capacity(result.data.len());
+        for pixel in self.data.chunks_exact(self.bytes_per_pixel()) {
+            let (r, g, b, a) = (
+                self.sample(pixel, 0),
+                self.sample(pixel, 1),
+                self.sample(pixel, 2),
+                self.sample(pixel, 3),
+            );
+            // NOTE: We use the Rec. 709 luma coefficients
+            let gray =
+                || (0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64).round() as u16;
+            match target_color_type {
+                ColorType::Gray => result.push_sample(&mut data, gray()),
+                ColorType::GrayAlpha => {
+                    result.push_sample(&mut data, gray());
+                    result.push_sample(&mut data, a);
+                }
+                ColorType::Rgb => {
+                    result.push_sample(&mut data, r);
+                    result.push_sample(&mut data, g);
+                    result.push_sample(&mut data, b);
+                }
+                ColorType::Rgba | ColorType::Indexed => unreachable!(),
+            }
+        }
+        result.data = data;
+        result
+    }
+
+    /// Expects an RGBA image with at most 256 different colors
+    fn rgba_to_indexed(&self) -> Result<PixelBuffer, String> {
+        debug_assert!(self.color_type == ColorType::Rgba);
+        if self.bit_depth != 8 {
+            return Err("Indexed output requires an image with 8 bit per channel".to_string());
+        }
+
+        let mut color_indices: HashMap<[u8; 4], u8> = HashMap::new();
+        let mut palette_colors: Vec<[u8; 4]> = Vec::new();
+        let mut result =
+            PixelBuffer::new(self.width as u32, self.height as u32, ColorType::Indexed, 8);
+        for (pixel, index) in self.data.chunks_exact(4).zip(result.data.iter_mut()) {
+            let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            *index = match color_indices.get(&color) {
+                Some(&index) => index,
+                None => {
+                    if palette_colors.len() == 256 {
+                        return Err(
+                            "Indexed output requires an image with at most 256 colors".to_string()
+                        );
+                    }
+                    let index = palette_colors.len() as u8;
+                    palette_colors.push(color);
+                    color_indices.insert(color, index);
+                    index
+                }
+            };
+        }
+
+        result.palette = palette_colors
+            .iter()
+            .flat_map(|color| color[0..3].to_vec())
+            .collect();
+
+        // NOTE: Trailing opaque entries can be omitted from the transparency chunk
+        let mut alphas: Vec<u8> = palette_colors.iter().map(|color| color[3]).collect();
+        while alphas.last() == Some(&0xFF) {
+            alphas.pop();
+        }
+        result.transparency = if alphas.is_empty() {
+            None
+        } else {
+            Some(alphas)
+        };
+
+        Ok(result)
+    }
+
+    fn smallest_lossless_color_type(&self) -> ColorType {
+        if self.color_type == ColorType::Indexed {
+            return ColorType::Indexed;
+        }
+
+        let rgba = self.to_rgba();
+        let max_sample = rgba.max_sample();
+        let mut is_opaque = true;
+        let mut is_gray = true;
+        let mut unique_colors = HashMap::new();
+        for pixel in rgba.data.chunks_exact(rgba.bytes_per_pixel()) {
+            let (r, g, b, a) = (
+                rgba.sample(pixel, 0),
+                rgba.sample(pixel, 1),
+                rgba.sample(pixel, 2),
+                rgba.sample(pixel, 3),
+            );
+            is_opaque &= a == max_sample;
+            is_gray &= r == g && g == b;
+            if unique_colors.len() <= 256 {
+                unique_colors.insert([r, g, b, a], ());
+            }
+        }
+
+        match (is_gray, is_opaque) {
+            (true, true) => ColorType::Gray,
+            (true, false) => ColorType::GrayAlpha,
+            _ if rgba.bit_depth == 8 && unique_colors.len() <= 256 => ColorType::Indexed,
+            (false, true) => ColorType::Rgb,
+            (false, false) => ColorType::Rgba,
+        }
+    }
+}
+
+/// Rows of 1, 2 or 4 bit samples as they are stored in a png file
+pub struct PackedSamples {
+    pub bit_depth: u8,
+    /// Every row starts at a byte boundary
+    pub data: Vec<u8>,
+    /// Content of the `tRNS` chunk with a gray value that matches the bit depth
+    pub transparency: Option<Vec<u8>>,
+}
+
+/// Converts rows of 1, 2 or 4 bit samples into 8 bit samples. Grayscale values are scaled to the
+/// full 8 bit range while palette indices keep their values.
+fn unpack_samples_to_8_bit(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    line_size: usize,
+    bit_depth: u8,
+    scale_to_8_bit: bool,
+) -> Vec<u8> {
+    let samples_per_byte = 8 / bit_depth as usize;
+    let mask = (1u16 << bit_depth) as u8 - 1;
+    let scale = if scale_to_8_bit { 255 / mask } else { 1 };
+
+    let mut result = Vec::with_capacity(width * height);
+    for row in data.chunks(line_size).take(height) {
+        for x in 0..width {
+            let byte = row[x / samples_per_byte];
+            let shift = 8 - bit_depth as usize * (x % samples_per_byte + 1);
+            let value = (byte >> shift) & mask;
+            result.push(value * scale);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        create_pattern_bitmap, encode_png_to_writer, PngEncoderOptions, PngMetadataChunks,
+        TilePlacement,
+    };
+
+    /// Writes the samples with the format of `header` into a png file using the png crate and
+    /// loads it back. Samples with less than 8 bit are given packed as in the png file.
+    fn load_png_written_with(name: &str, header: &PixelBuffer, data: &[u8]) -> PixelBuffer {
+        let filepath = std::env::temp_dir()
+            .join(format!("repeaty_test_{}.png", name))
+            .to_string_lossy()
+            .to_string();
+        {
+            let file = File::create(&filepath).unwrap();
+            let mut encoder = png::Encoder::new(file, header.width as u32, header.height as u32);
+            encoder.set_color(match header.color_type {
+                ColorType::Gray => png::ColorType::Grayscale,
+                ColorType::GrayAlpha => png::ColorType::GrayscaleAlpha,
+                ColorType::Rgb => png::ColorType::RGB,
+                ColorType::Rgba => png::ColorType::RGBA,
+                ColorType::Indexed => png::ColorType::Indexed,
+            });
+            encoder.set_depth(png::BitDepth::from_u8(header.source_bit_depth).unwrap());
+            if !header.palette.is_empty() {
+                encoder.set_palette(header.palette.clone());
+            }
+            if let Some(transparency) = &header.transparency {
+                encoder.set_trns(transparency.clone());
+            }
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(data).unwrap();
+        }
+        let result = PixelBuffer::from_png_file(&filepath).unwrap();
+        std::fs::remove_file(&filepath).ok();
+        result
+    }
+
+    /// Encodes the image with our png encoder and decodes it with the png crate
+    fn encode_and_decode(image: &PixelBuffer) -> (png::Info, Vec<u8>) {
+        let mut encoded = Vec::new();
+        encode_png_to_writer(
+            image,
+            &mut encoded,
+            &PngMetadataChunks::new(),
+            &PngEncoderOptions::default(),
+        )
+        .unwrap();
+        let mut decoder = png::Decoder::new(encoded.as_slice());
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (output_info, mut reader) = decoder.read_info().unwrap();
+        let mut decoded = vec![0; output_info.buffer_size()];
+        reader.next_frame(&mut decoded).unwrap();
+        (reader.info().clone(), decoded)
+    }
+
+    #[test]
+    fn sixteen_bit_samples_survive_loading_tiling_and_encoding() {
+        for &color_type in &[ColorType::Gray, ColorType::Rgba] {
+            let header = PixelBuffer::new(3, 2, color_type, 16);
+            // NOTE: High and low bytes differ everywhere so that swapped bytes are noticed
+            let data: Vec<u8> = (0..header.data.len())
+                .map(|index| (index * 37 + 11) as u8)
+                .collect();
+            let tile =
+                load_png_written_with(&format!("16bit_{}", color_type.name()), &header, &data);
+            assert_eq!(tile.bit_depth, 16);
+            assert_eq!(tile.data, data);
+
+            let pattern = create_pattern_bitmap(&tile, 7, 5, &TilePlacement::default(), None, None);
+            let (info, decoded) = encode_and_decode(&pattern);
+            assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+            assert_eq!((info.width, info.height), (7, 5));
+            let bytes_per_pixel = tile.bytes_per_pixel();
+            for (index, pixel) in decoded.chunks_exact(bytes_per_pixel).enumerate() {
+                let (x, y) = (index as i32 % 7, index as i32 / 7);
+                assert_eq!(
+                    pixel,
+                    tile.pixel(x % 3, y % 2),
+                    "{} at {}x{}",
+                    color_type.name(),
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gray_stays_gray() {
+        let mut image = PixelBuffer::new(3, 1, ColorType::Gray, 8);
+        image.data = vec![0, 128, 255];
+        for &output_color_type in &[
+            OutputColorType::Original,
+            OutputColorType::Smallest,
+            OutputColorType::Gray,
+        ] {
+            let converted = image.converted(output_color_type).unwrap();
+            assert_eq!(converted.color_type, ColorType::Gray);
+            assert_eq!(converted.data, image.data);
+        }
+    }
+
+    #[test]
+    fn opaque_rgba_becomes_rgb() {
+        let mut image = PixelBuffer::new(2, 1, ColorType::Rgba, 16);
+        image.data = vec![1, 2, 3, 4, 5, 6, 255, 255, 7, 8, 9, 10, 11, 12, 255, 255];
+        // NOTE: Palettes only hold 8 bit colors so the smallest type for 16 bit is RGB
+        assert_eq!(image.smallest_lossless_color_type(), ColorType::Rgb);
+        let converted = image.converted(OutputColorType::Smallest).unwrap();
+        assert_eq!(converted.color_type, ColorType::Rgb);
+        assert_eq!(converted.bit_depth, 16);
+        assert_eq!(converted.data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn few_colors_become_indexed_with_transparency() {
+        let mut image = PixelBuffer::new(4, 1, ColorType::Rgba, 8);
+        image.data = vec![
+            10, 20, 30, 255, //
+            40, 50, 60, 128, //
+            10, 20, 30, 255, //
+            0, 0, 0, 0,
+        ];
+        assert_eq!(image.smallest_lossless_color_type(), ColorType::Indexed);
+        let indexed = image.converted(OutputColorType::Smallest).unwrap();
+        assert_eq!(indexed.color_type, ColorType::Indexed);
+        assert_eq!(indexed.data, vec![0, 1, 0, 2]);
+        assert_eq!(indexed.palette, vec![10, 20, 30, 40, 50, 60, 0, 0, 0]);
+        assert_eq!(indexed.transparency, Some(vec![255, 128, 0]));
+        assert_eq!(indexed.to_rgba().data, image.data);
+
+        let (info, decoded) = encode_and_decode(&indexed);
+        assert_eq!(info.color_type, png::ColorType::Indexed);
+        assert_eq!(info.palette, Some(indexed.palette.clone()));
+        assert_eq!(info.trns, indexed.transparency);
+        assert_eq!(decoded, indexed.data);
+    }
+
+    #[test]
+    fn two_bit_indexed_round_trip() {
+        let mut header = PixelBuffer::new(5, 2, ColorType::Indexed, 8);
+        header.source_bit_depth = 2;
+        header.palette = vec![0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255];
+        header.transparency = Some(vec![0]);
+        // Five 2 bit indices per row padded to whole bytes
+        let packed = vec![0b0001_1011, 0b1100_0000, 0b1110_0100, 0b0100_0000];
+        let image = load_png_written_with("indexed_2bit", &header, &packed);
+        assert_eq!((image.bit_depth, image.source_bit_depth), (8, 2));
+        assert_eq!(image.data, vec![0, 1, 2, 3, 3, 3, 2, 1, 0, 1]);
+
+        let converted = image.converted(OutputColorType::Original).unwrap();
+        let (info, decoded) = encode_and_decode(&converted);
+        assert_eq!(info.bit_depth, png::BitDepth::Two);
+        assert_eq!(info.color_type, png::ColorType::Indexed);
+        assert_eq!(info.palette, Some(header.palette.clone()));
+        assert_eq!(info.trns, header.transparency);
+        assert_eq!(decoded, packed);
+    }
+
+    #[test]
+    fn forced_rgb_rejects_translucent_image() {
+        let mut image = PixelBuffer::new(1, 1, ColorType::Rgba, 8);
+        image.data = vec![10, 20, 30, 200];
+        assert!(image.converted(OutputColorType::Rgb).is_err());
+        assert!(image.converted(OutputColorType::Gray).is_err());
+        assert!(image.converted(OutputColorType::Rgba).is_ok());
+    }
+}
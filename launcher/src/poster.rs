@@ -0,0 +1,246 @@
+use crate::print_marks::PrintMarks;
+
+/// Unprinted border around the content of every sheet that holds the assembly marks and the page
+/// label. Office printers cannot print up to the paper edge anyway.
+pub const SHEET_BORDER_MM: f64 = 10.0;
+
+/// The part of the pattern that is printed on one sheet of a poster. The region is given in
+/// pixels of the complete pattern.
+#[derive(Debug, Clone, Copy)]
+pub struct PosterSheet {
+    pub row: usize,
+    pub column: usize,
+    pub region_x: i32,
+    pub region_y: i32,
+    pub region_width: i32,
+    pub region_height: i32,
+}
+
+/// Splits a pattern into printer paper sized sheets. Neighboring sheets share an overlap so that
+/// they can be glued or taped together.
+#[derive(Debug, Clone)]
+pub struct PosterLayout {
+    /// The paper size in the orientation that needs fewer sheets
+    pub paper_width_mm: f64,
+    pub paper_height_mm: f64,
+    pub sheet_pixel_width: i32,
+    pub sheet_pixel_height: i32,
+    /// Border per side rounded to whole pixels
+    pub border_pixels: i32,
+    pub overlap_pixels: i32,
+    pub column_count: usize,
+    pub row_count: usize,
+    /// Row by row starting at the top left
+    pub sheets: Vec<PosterSheet>,
+    pixel_per_mm: f64,
+}
+
+impl PosterLayout {
+    /// The paper dimensions are given in portrait orientation. Landscape orientation is used
+    /// instead if it needs fewer sheets.
+    pub fn new(
+        pattern_pixel_width: i32,
+        pattern_pixel_height: i32,
+        pixel_per_mm: f64,
+        paper_width_mm: f64,
+        paper_height_mm: f64,
+        overlap_mm: f64,
+    ) -> PosterLayout {
+        let border_pixels = (SHEET_BORDER_MM * pixel_per_mm).round() as i32;
+        let overlap_pixels = (overlap_mm * pixel_per_mm).round() as i32;
+
+        // Returns the sheet size in pixels and the number of sheets needed along one axis
+        let sheets_per_axis = |paper_mm: f64, pattern_pixels: i32| -> (i32, usize) {
+            let sheet_pixels = (paper_mm * pixel_per_mm).round() as i32;
+            let content_pixels = (sheet_pixels - 2 * border_pixels).max(1);
+            let step_pixels = (content_pixels - overlap_pixels).max(1);
+            let count = if pattern_pixels <= content_pixels {
+                1
+            } else {
+                let remainder = (pattern_pixels - content_pixels) as i64;
+                1 + ((remainder + step_pixels as i64 - 1) / step_pixels as i64) as usize
+            };
+            (sheet_pixels, count)
+        };
+
+        let portrait = (
+            sheets_per_axis(paper_width_mm, pattern_pixel_width),
+            sheets_per_axis(paper_height_mm, pattern_pixel_height),
+        );
+        let landscape = (
+            sheets_per_axis(paper_height_mm, pattern_pixel_width),
+            sheets_per_axis(paper_width_mm, pattern_pixel_height),
+        );
+        let is_landscape = (landscape.0).1 * (landscape.1).1 < (portrait.0).1 * (portrait.1).1;
+        let (
+            (sheet_pixel_width, column_count),
+            (sheet_pixel_height, row_count),
+            paper_width_mm,
+            paper_height_mm,
+        ) = if is_landscape {
+            (landscape.0, landscape.1, paper_height_mm, paper_width_mm)
+        } else {
+            (portrait.0, portrait.1, paper_width_mm, paper_height_mm)
+        };
+
+        let content_width = (sheet_pixel_width - 2 * border_pixels).max(1);
+        let content_height = (sheet_pixel_height - 2 * border_pixels).max(1);
+        let step_x = (content_width - overlap_pixels).max(1);
+        let step_y = (content_height - overlap_pixels).max(1);
+        let mut sheets = Vec::with_capacity(column_count * row_count);
+        for row in 0..row_count {
+            for column in 0..column_count {
+                let region_x = column as i32 * step_x;
+                let region_y = row as i32 * step_y;
+                sheets.push(PosterSheet {
+                    row,
+                    column,
+                    region_x,
+                    region_y,
+                    region_width: content_width.min(pattern_pixel_width - region_x),
+                    region_height: content_height.min(pattern_pixel_height - region_y),
+                });
+            }
+        }
+
+        PosterLayout {
+            paper_width_mm,
+            paper_height_mm,
+            sheet_pixel_width,
+            sheet_pixel_height,
+            border_pixels,
+            overlap_pixels,
+            column_count,
+            row_count,
+            sheets,
+            pixel_per_mm,
+        }
+    }
+
+    pub fn is_landscape(&self) -> bool {
+        self.paper_width_mm > self.paper_height_mm
+    }
+
+    /// Returns false if the overlap leaves no room for new content on a sheet
+    pub fn has_room_for_overlap(&self) -> bool {
+        let content_pixels = (self.sheet_pixel_width - 2 * self.border_pixels)
+            .min(self.sheet_pixel_height - 2 * self.border_pixels);
+        self.overlap_pixels < content_pixels
+    }
+
+    /// Crop marks at the content corners, ticks where the neighboring sheets end and the given
+    /// label in the top border
+    pub fn sheet_marks(&self, sheet: &PosterSheet, label: String) -> PrintMarks {
+        let mm = |pixels: i32| pixels as f64 / self.pixel_per_mm;
+        let overlap_mm = mm(self.overlap_pixels);
+        let overlap_if = |has_neighbor: bool| if has_neighbor { overlap_mm } else { 0.0 };
+        PrintMarks::for_poster_sheet(
+            self.paper_width_mm,
+            self.paper_height_mm,
+            [
+                mm(self.border_pixels),
+                mm(self.border_pixels),
+                mm(self.border_pixels + sheet.region_width),
+                mm(self.border_pixels + sheet.region_height),
+            ],
+            [
+                overlap_if(sheet.column > 0),
+                overlap_if(sheet.row > 0),
+                overlap_if(sheet.column + 1 < self.column_count),
+                overlap_if(sheet.row + 1 < self.row_count),
+            ],
+            label,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A4_WIDTH_MM: f64 = 210.0;
+    const A4_HEIGHT_MM: f64 = 297.0;
+
+    /// Checks that the sheets cover the pattern without gaps and that neighbors overlap by exactly
+    /// the overlap of the layout
+    fn assert_sheets_cover_pattern(layout: &PosterLayout, pattern_width: i32, pattern_height: i32) {
+        assert_eq!(layout.sheets.len(), layout.column_count * layout.row_count);
+        for sheet in &layout.sheets {
+            assert!(sheet.region_width > 0 && sheet.region_height > 0);
+            assert!(sheet.region_x + sheet.region_width <= pattern_width);
+            assert!(sheet.region_y + sheet.region_height <= pattern_height);
+            let index = sheet.row * layout.column_count + sheet.column;
+            if sheet.column + 1 < layout.column_count {
+                let right = &layout.sheets[index + 1];
+                assert_eq!(
+                    sheet.region_x + sheet.region_width - right.region_x,
+                    layout.overlap_pixels
+                );
+            } else {
+                assert_eq!(sheet.region_x + sheet.region_width, pattern_width);
+            }
+            if sheet.row + 1 < layout.row_count {
+                let below = &layout.sheets[index + layout.column_count];
+                assert_eq!(
+                    sheet.region_y + sheet.region_height - below.region_y,
+                    layout.overlap_pixels
+                );
+            } else {
+                assert_eq!(sheet.region_y + sheet.region_height, pattern_height);
+            }
+        }
+    }
+
+    #[test]
+    fn small_pattern_fits_on_one_sheet() {
+        let layout = PosterLayout::new(100, 150, 1.0, A4_WIDTH_MM, A4_HEIGHT_MM, 10.0);
+        assert_eq!((layout.column_count, layout.row_count), (1, 1));
+        assert!(!layout.is_landscape());
+        assert_eq!(layout.border_pixels, 10);
+        assert_sheets_cover_pattern(&layout, 100, 150);
+    }
+
+    #[test]
+    fn wide_pattern_uses_landscape_sheets() {
+        // Portrait needs 3x2 sheets with a content size of 190x277 while landscape needs 2x2
+        // sheets with a content size of 277x190
+        let layout = PosterLayout::new(500, 300, 1.0, A4_WIDTH_MM, A4_HEIGHT_MM, 10.0);
+        assert!(layout.is_landscape());
+        assert_eq!((layout.column_count, layout.row_count), (2, 2));
+        assert_eq!(
+            (layout.sheet_pixel_width, layout.sheet_pixel_height),
+            (297, 210)
+        );
+        assert_eq!(layout.sheets[1].region_x, 267);
+        assert_eq!(layout.sheets[1].region_width, 233);
+        assert_eq!(layout.sheets[2].region_y, 180);
+        assert_sheets_cover_pattern(&layout, 500, 300);
+    }
+
+    #[test]
+    fn neighboring_sheets_overlap() {
+        for &(pattern_width, pattern_height, pixel_per_mm, overlap_mm) in &[
+            (1000, 1000, 1.0, 0.0),
+            (1000, 2500, 1.0, 15.0),
+            (4000, 3000, 300.0 / 25.4, 10.0),
+            (278, 191, 1.0, 5.0),
+        ] {
+            let layout = PosterLayout::new(
+                pattern_width,
+                pattern_height,
+                pixel_per_mm,
+                A4_WIDTH_MM,
+                A4_HEIGHT_MM,
+                overlap_mm,
+            );
+            assert!(layout.has_room_for_overlap());
+            assert_sheets_cover_pattern(&layout, pattern_width, pattern_height);
+        }
+    }
+
+    #[test]
+    fn overlap_larger_than_sheet_content_leaves_no_room() {
+        let layout = PosterLayout::new(1000, 1000, 1.0, A4_WIDTH_MM, A4_HEIGHT_MM, 190.0);
+        assert!(!layout.has_room_for_overlap());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::appdata;
+use crate::{
+    inch_in_millimeter, AspectLock, OutputFormat, PatternSettings, SizeMode, SnapPolicy, TileLayout,
+};
+
+use ct_lib::log;
+use ct_lib::serde_derive::{Deserialize, Serialize};
+
+const USER_PRESETS_FILENAME: &str = "presets.json";
+
+/// Target values of a common print product that are filled into the pattern settings at once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    pub dim_mm_x: f64,
+    pub dim_mm_y: f64,
+    /// Overrides the DPI of the input images if set
+    pub ppi: Option<f64>,
+    pub tile_layout: TileLayout,
+    pub snap_policy: SnapPolicy,
+    pub bleed_mm: f64,
+    pub output_format: OutputFormat,
+}
+impl Default for Preset {
+    fn default() -> Self {
+        Preset {
+            name: String::new(),
+            dim_mm_x: 1000.0,
+            dim_mm_y: 1000.0,
+            ppi: None,
+            tile_layout: TileLayout::Grid,
+            snap_policy: SnapPolicy::Off,
+            bleed_mm: 0.0,
+            output_format: OutputFormat::Png,
+        }
+    }
+}
+
+impl Preset {
+    /// Captures the values of the given settings that a preset covers
+    pub fn from_settings(name: &str, settings: &PatternSettings) -> Preset {
+        Preset {
+            name: name.to_string(),
+            dim_mm_x: settings.dim_mm_x,
+            dim_mm_y: settings.dim_mm_y,
+            ppi: settings.ppi_override,
+            tile_layout: settings.tile_layout,
+            snap_policy: settings.snap_policy,
+            bleed_mm: settings.bleed_mm,
+            output_format: settings.output_format,
+        }
+    }
+
+    /// Switches the settings to the physical dimensions of the preset. The aspect lock is turned
+    /// off as it would otherwise overwrite one of the dimensions.
+    pub fn apply_to(&self, settings: &mut PatternSettings) {
+        settings.size_mode = SizeMode::Dimension;
+        settings.dim_mm_x = self.dim_mm_x;
+        settings.dim_mm_y = self.dim_mm_y;
+        settings.aspect_lock = AspectLock::Off;
+        settings.ppi_override = self.ppi;
+        settings.tile_layout = self.tile_layout;
+        settings.snap_policy = self.snap_policy;
+        settings.bleed_mm = self.bleed_mm;
+        settings.output_format = self.output_format;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+            && self.dim_mm_x > 0.0
+            && self.dim_mm_y > 0.0
+            && self.ppi.map(|ppi| ppi > 0.0).unwrap_or(true)
+            && self.bleed_mm >= 0.0
+    }
+}
+
+fn builtin_presets() -> Vec<Preset> {
+    let spoonflower_yard = |width_inch: f64| Preset {
+        name: format!("Spoonflower {}\" x 1 yard", width_inch),
+        dim_mm_x: inch_in_millimeter(width_inch),
+        dim_mm_y: inch_in_millimeter(36.0),
+        ppi: Some(150.0),
+        ..Preset::default()
+    };
+    vec![
+        Preset {
+            name: "Fabric 140cm x 1m".to_string(),
+            dim_mm_x: 1400.0,
+            dim_mm_y: 1000.0,
+            ppi: Some(150.0),
+            ..Preset::default()
+        },
+        Preset {
+            name: "Wallpaper roll 53cm x 10.05m".to_string(),
+            dim_mm_x: 530.0,
+            dim_mm_y: 10050.0,
+            ppi: Some(150.0),
+            ..Preset::default()
+        },
+        spoonflower_yard(42.0),
+        spoonflower_yard(54.0),
+        spoonflower_yard(56.0),
+        Preset {
+            name: "A4 print with 3mm bleed".to_string(),
+            dim_mm_x: 210.0,
+            dim_mm_y: 297.0,
+            ppi: Some(300.0),
+            bleed_mm: 3.0,
+            output_format: OutputFormat::Pdf,
+            ..Preset::default()
+        },
+    ]
+}
+
+/// The presets the user saved
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct UserPresets {
+    presets: Vec<Preset>,
+}
+
+impl UserPresets {
+    /// Returns no presets if there are no previously saved presets or if they are unreadable
+    fn load() -> UserPresets {
+        let mut result: UserPresets =
+            appdata::load_json_or_default(USER_PRESETS_FILENAME, "presets");
+        result.presets.retain(|preset| {
+            if !preset.is_valid() {
+                log::warn!(
+                    "Ignoring invalid preset '{}' in '{}'",
+                    preset.name,
+                    appdata::appdata_filepath(USER_PRESETS_FILENAME)
+                );
+            }
+            preset.is_valid()
+        });
+        result
+    }
+
+    fn save(&self) -> Result<(), String> {
+        appdata::save_json(USER_PRESETS_FILENAME, "presets", self)
+    }
+}
+
+/// Returns the built-in presets followed by the given user presets. A user preset replaces a
+/// built-in preset with the same case insensitive name.
+fn merged_presets(builtin_presets: Vec<Preset>, user_presets: Vec<Preset>) -> Vec<Preset> {
+    let mut result: Vec<Preset> = builtin_presets
+        .into_iter()
+        .filter(|builtin| {
+            !user_presets
+                .iter()
+                .any(|user| user.name.eq_ignore_ascii_case(&builtin.name))
+        })
+        .collect();
+    result.extend(user_presets);
+    result
+}
+
+/// Returns the built-in presets followed by the presets the user saved. User presets take
+/// precedence over built-in presets with the same name.
+pub fn all_presets() -> Vec<Preset> {
+    merged_presets(builtin_presets(), UserPresets::load().presets)
+}
+
+/// Looks up a preset by its case insensitive name
+pub fn find_preset(name: &str) -> Option<Preset> {
+    all_presets()
+        .into_iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+/// Stores the given preset as user preset. A user preset with the same name is replaced.
+pub fn save_user_preset(preset: Preset) -> Result<(), String> {
+    if !preset.is_valid() {
+        return Err(format!(
+            "Could not save preset '{}' : It needs a name and valid dimensions",
+            preset.name
+        ));
+    }
+    let mut user_presets = UserPresets::load();
+    user_presets
+        .presets
+        .retain(|existing| !existing.name.eq_ignore_ascii_case(&preset.name));
+    user_presets.presets.push(preset);
+    user_presets.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset_with_name(name: &str, dim_mm_x: f64) -> Preset {
+        Preset {
+            name: name.to_string(),
+            dim_mm_x,
+            ..Preset::default()
+        }
+    }
+
+    #[test]
+    fn user_presets_replace_builtin_presets_with_same_name() {
+        let builtin = vec![
+            preset_with_name("Fabric", 1400.0),
+            preset_with_name("Wallpaper", 530.0),
+        ];
+        let user = vec![
+            preset_with_name("fabric", 1500.0),
+            preset_with_name("Poster", 600.0),
+        ];
+        let presets = merged_presets(builtin, user);
+        let names: Vec<&str> = presets.iter().map(|preset| preset.name.as_str()).collect();
+        assert_eq!(names, vec!["Wallpaper", "fabric", "Poster"]);
+        assert_eq!(presets[1].dim_mm_x, 1500.0);
+    }
+
+    #[test]
+    fn builtin_presets_are_valid_and_unique() {
+        let presets = builtin_presets();
+        for (index, preset) in presets.iter().enumerate() {
+            assert!(preset.is_valid(), "preset '{}'", preset.name);
+            assert!(!presets[index + 1..]
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&preset.name)));
+        }
+    }
+}
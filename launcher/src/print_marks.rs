@@ -0,0 +1,788 @@
+use crate::svg::escape_xml_attribute;
+
+/// Space between the bleed edge and the canvas edge that holds the print marks
+pub const PRINT_MARKS_MARGIN_MM: f64 = 12.0;
+
+const MARK_LINE_WIDTH_MM: f64 = 0.1;
+/// Crop marks start this far outside of the bleed so that they cannot show up after trimming
+const CROP_MARK_GAP_MM: f64 = 2.0;
+const CROP_MARK_LENGTH_MM: f64 = 5.0;
+const REGISTRATION_MARK_RADIUS_MM: f64 = 2.0;
+/// Distance between the bleed edge and the center of a registration mark
+const REGISTRATION_MARK_DISTANCE_MM: f64 = 4.5;
+/// Height of the capital letters of the slug line
+const SLUG_TEXT_HEIGHT_MM: f64 = 2.5;
+/// Distance between the bleed edge and the top of the slug line
+const SLUG_TEXT_DISTANCE_MM: f64 = 8.0;
+/// Height of the capital letters of Helvetica relative to its font size
+const HELVETICA_CAP_HEIGHT: f64 = 0.718;
+
+const GLYPH_WIDTH: i32 = 5;
+const GLYPH_HEIGHT: i32 = 7;
+/// Glyph width plus one column of spacing
+const GLYPH_ADVANCE: i32 = 6;
+
+/// A horizontal or vertical line
+#[derive(Debug, Clone, Copy)]
+struct MarkLine {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MarkCircle {
+    center_x: f64,
+    center_y: f64,
+    radius: f64,
+}
+
+/// Crop marks, registration marks and a slug line in a margin around the bleed of a pattern. All
+/// lengths are millimeter with the origin at the top left corner of the canvas.
+#[derive(Debug, Clone)]
+pub struct PrintMarks {
+    canvas_width_mm: f64,
+    canvas_height_mm: f64,
+    /// The area that shows the pattern. Everything around it is paper with the marks on top.
+    content_left_mm: f64,
+    content_top_mm: f64,
+    content_right_mm: f64,
+    content_bottom_mm: f64,
+    lines: Vec<MarkLine>,
+    circles: Vec<MarkCircle>,
+    slug_text: String,
+    slug_x_mm: f64,
+    slug_y_mm: f64,
+}
+
+impl PrintMarks {
+    /// The margin is expected to be `PRINT_MARKS_MARGIN_MM` rounded to whole pixels so that the
+    /// raster and vector versions of the marks match.
+    pub fn new(
+        trim_width_mm: f64,
+        trim_height_mm: f64,
+        bleed_mm: f64,
+        margin_mm: f64,
+        slug_text: String,
+    ) -> PrintMarks {
+        let canvas_width_mm = trim_width_mm + 2.0 * (bleed_mm + margin_mm);
+        let canvas_height_mm = trim_height_mm + 2.0 * (bleed_mm + margin_mm);
+
+        let trim_left = margin_mm + bleed_mm;
+        let trim_top = margin_mm + bleed_mm;
+        let trim_right = trim_left + trim_width_mm;
+        let trim_bottom = trim_top + trim_height_mm;
+        let bleed_left = margin_mm;
+        let bleed_top = margin_mm;
+        let bleed_right = canvas_width_mm - margin_mm;
+        let bleed_bottom = canvas_height_mm - margin_mm;
+
+        // Crop marks continue the trim edges into the margin at every corner
+        let mark_start = CROP_MARK_GAP_MM;
+        let mark_end = CROP_MARK_GAP_MM + CROP_MARK_LENGTH_MM;
+        let mut lines = Vec::new();
+        for &trim_x in &[trim_left, trim_right] {
+            lines.push(MarkLine {
+                x0: trim_x,
+                y0: bleed_top - mark_start,
+                x1: trim_x,
+                y1: bleed_top - mark_end,
+            });
+            lines.push(MarkLine {
+                x0: trim_x,
+                y0: bleed_bottom + mark_start,
+                x1: trim_x,
+                y1: bleed_bottom + mark_end,
+            });
+        }
+        for &trim_y in &[trim_top, trim_bottom] {
+            lines.push(MarkLine {
+                x0: bleed_left - mark_start,
+                y0: trim_y,
+                x1: bleed_left - mark_end,
+                y1: trim_y,
+            });
+            lines.push(MarkLine {
+                x0: bleed_right + mark_start,
+                y0: trim_y,
+                x1: bleed_right + mark_end,
+                y1: trim_y,
+            });
+        }
+
+        // Registration marks are circles with a crosshair in the middle of every side
+        let center_x = canvas_width_mm / 2.0;
+        let center_y = canvas_height_mm / 2.0;
+        let registration_mark_centers = [
+            (center_x, bleed_top - REGISTRATION_MARK_DISTANCE_MM),
+            (center_x, bleed_bottom + REGISTRATION_MARK_DISTANCE_MM),
+            (bleed_left - REGISTRATION_MARK_DISTANCE_MM, center_y),
+            (bleed_right + REGISTRATION_MARK_DISTANCE_MM, center_y),
+        ];
+        let crosshair_half_length = 1.5 * REGISTRATION_MARK_RADIUS_MM;
+        let mut circles = Vec::new();
+        for &(x, y) in &registration_mark_centers {
+            circles.push(MarkCircle {
+                center_x: x,
+                center_y: y,
+                radius: REGISTRATION_MARK_RADIUS_MM,
+            });
+            lines.push(MarkLine {
+                x0: x - crosshair_half_length,
+                y0: y,
+                x1: x + crosshair_half_length,
+                y1: y,
+            });
+            lines.push(MarkLine {
+                x0: x,
+                y0: y - crosshair_half_length,
+                x1: x,
+                y1: y + crosshair_half_length,
+            });
+        }
+
+        PrintMarks {
+            canvas_width_mm,
+            canvas_height_mm,
+            content_left_mm: bleed_left,
+            content_top_mm: bleed_top,
+            content_right_mm: bleed_right,
+            content_bottom_mm: bleed_bottom,
+            lines,
+            circles,
+            slug_text,
+            slug_x_mm: trim_left,
+            slug_y_mm: bleed_bottom + SLUG_TEXT_DISTANCE_MM,
+        }
+    }
+
+    /// Marks for one sheet of a poster. The content is the part of the pattern on this sheet. Its
+    /// corners get crop marks and the edges that overlap with a neighboring sheet get ticks where
+    /// the overlap starts. An overlap of zero means that there is no neighbor on that side.
+    pub fn for_poster_sheet(
+        paper_width_mm: f64,
+        paper_height_mm: f64,
+        content: [f64; 4],
+        overlaps: [f64; 4],
+        label: String,
+    ) -> PrintMarks {
+        let [left, top, right, bottom] = content;
+        let [overlap_left, overlap_top, overlap_right, overlap_bottom] = overlaps;
+        let mark_start = 1.0;
+        let mark_end = 1.0 + CROP_MARK_LENGTH_MM;
+
+        let mut lines = Vec::new();
+        let mut vertical_ticks = vec![left, right];
+        if overlap_left > 0.0 {
+            vertical_ticks.push(left + overlap_left);
+        }
+        if overlap_right > 0.0 {
+            vertical_ticks.push(right - overlap_right);
+        }
+        for &x in &vertical_ticks {
+            lines.push(MarkLine {
+                x0: x,
+                y0: top - mark_start,
+                x1: x,
+                y1: top - mark_end,
+            });
+            lines.push(MarkLine {
+                x0: x,
+                y0: bottom + mark_start,
+                x1: x,
+                y1: bottom + mark_end,
+            });
+        }
+        let mut horizontal_ticks = vec![top, bottom];
+        if overlap_top > 0.0 {
+            horizontal_ticks.push(top + overlap_top);
+        }
+        if overlap_bottom > 0.0 {
+            horizontal_ticks.push(bottom - overlap_bottom);
+        }
+        for &y in &horizontal_ticks {
+            lines.push(MarkLine {
+                x0: left - mark_start,
+                y0: y,
+                x1: left - mark_end,
+                y1: y,
+            });
+            lines.push(MarkLine {
+                x0: right + mark_start,
+                y0: y,
+                x1: right + mark_end,
+                y1: y,
+            });
+        }
+
+        PrintMarks {
+            canvas_width_mm: paper_width_mm,
+            canvas_height_mm: paper_height_mm,
+            content_left_mm: left,
+            content_top_mm: top,
+            content_right_mm: right,
+            content_bottom_mm: bottom,
+            lines,
+            circles: Vec::new(),
+            slug_text: label,
+            slug_x_mm: left + mark_end,
+            slug_y_mm: (top - SLUG_TEXT_HEIGHT_MM) / 2.0,
+        }
+    }
+
+    /// Distance between the canvas edge and the pattern for marks around a centered pattern
+    pub fn margin_mm(&self) -> f64 {
+        self.content_left_mm
+    }
+
+    /// Returns pdf content stream operators that draw the marks. The page is expected to have the
+    /// size of the canvas with the origin at its bottom left corner.
+    /// NOTE: The slug line is drawn with filled rectangles from the same glyphs that raster outputs
+    ///       use so that the pdf does not depend on a font that it does not embed
+    pub fn pdf_content(&self, units_per_mm: f64) -> String {
+        let x = |mm: f64| mm * units_per_mm;
+        let y = |mm: f64| (self.canvas_height_mm - mm) * units_per_mm;
+
+        let mut result = format!("q 0 G 0 g {:.4} w\n", MARK_LINE_WIDTH_MM * units_per_mm);
+        for line in &self.lines {
+            result += &format!(
+                "{:.4} {:.4} m {:.4} {:.4} l S\n",
+                x(line.x0),
+                y(line.y0),
+                x(line.x1),
+                y(line.y1)
+            );
+        }
+
+        // NOTE: Circles are approximated by four bezier curves
+        const KAPPA: f64 = 0.552_284_8;
+        for circle in &self.circles {
+            let (cx, cy) = (x(circle.center_x), y(circle.center_y));
+            let r = circle.radius * units_per_mm;
+            let k = KAPPA * r;
+            result += &format!(
+                "{:.4} {:.4} m \
+                 {:.4} {:.4} {:.4} {:.4} {:.4} {:.4} c \
+                 {:.4} {:.4} {:.4} {:.4} {:.4} {:.4} c \
+                 {:.4} {:.4} {:.4} {:.4} {:.4} {:.4} c \
+                 {:.4} {:.4} {:.4} {:.4} {:.4} {:.4} c S\n",
+                cx + r,
+                cy,
+                cx + r,
+                cy + k,
+                cx + k,
+                cy + r,
+                cx,
+                cy + r,
+                cx - k,
+                cy + r,
+                cx - r,
+                cy + k,
+                cx - r,
+                cy,
+                cx - r,
+                cy - k,
+                cx - k,
+                cy - r,
+                cx,
+                cy - r,
+                cx + k,
+                cy - r,
+                cx + r,
+                cy - k,
+                cx + r,
+                cy
+            );
+        }
+
+        let glyph_pixel_mm = SLUG_TEXT_HEIGHT_MM / GLYPH_HEIGHT as f64;
+        let runs = glyph_pixel_runs(&self.slug_text);
+        for run in &runs {
+            let left_mm = self.slug_x_mm + run.x as f64 * glyph_pixel_mm;
+            let bottom_mm = self.slug_y_mm + (run.y + 1) as f64 * glyph_pixel_mm;
+            result += &format!(
+                "{:.4} {:.4} {:.4} {:.4} re\n",
+                x(left_mm),
+                y(bottom_mm),
+                run.length as f64 * glyph_pixel_mm * units_per_mm,
+                glyph_pixel_mm * units_per_mm
+            );
+        }
+        if !runs.is_empty() {
+            result += "f\n";
+        }
+        result += "Q\n";
+        result
+    }
+
+    /// Returns svg elements that draw the marks inside a viewBox with millimeter units
+    pub fn svg_elements(&self) -> String {
+        let mut result = format!(
+            "  <g stroke=\"black\" stroke-width=\"{:.6}\" fill=\"none\">\n",
+            MARK_LINE_WIDTH_MM
+        );
+        for line in &self.lines {
+            result += &format!(
+                "    <line x1=\"{:.6}\" y1=\"{:.6}\" x2=\"{:.6}\" y2=\"{:.6}\"/>\n",
+                line.x0, line.y0, line.x1, line.y1
+            );
+        }
+        for circle in &self.circles {
+            result += &format!(
+                "    <circle cx=\"{:.6}\" cy=\"{:.6}\" r=\"{:.6}\"/>\n",
+                circle.center_x, circle.center_y, circle.radius
+            );
+        }
+        result += "  </g>\n";
+        result += &format!(
+            "  <text x=\"{:.6}\" y=\"{:.6}\" font-family=\"Helvetica, Arial, sans-serif\" \
+             font-size=\"{:.6}\" fill=\"black\">{}</text>\n",
+            self.slug_x_mm,
+            self.slug_y_mm + SLUG_TEXT_HEIGHT_MM,
+            SLUG_TEXT_HEIGHT_MM / HELVETICA_CAP_HEIGHT,
+            escape_xml_attribute(&self.slug_text)
+        );
+        result
+    }
+
+    /// Converts the marks into pixels of a canvas with the given resolution
+    pub fn rasterized(&self, pixel_per_mm: f64, canvas_width: i32) -> RasterPrintMarks {
+        let line_width = (MARK_LINE_WIDTH_MM * pixel_per_mm).round().max(1.0);
+        let pixels = |mm: f64| (mm * pixel_per_mm).round() as i32;
+        // Lines have no extent along one axis so we give them the line width there
+        let pixel_range = |start_mm: f64, end_mm: f64| {
+            if start_mm == end_mm {
+                let start = (start_mm * pixel_per_mm - line_width / 2.0).round() as i32;
+                (start, start + line_width as i32)
+            } else {
+                (pixels(start_mm.min(end_mm)), pixels(start_mm.max(end_mm)))
+            }
+        };
+
+        let rects = self
+            .lines
+            .iter()
+            .map(|line| {
+                let (x_min, x_max) = pixel_range(line.x0, line.x1);
+                let (y_min, y_max) = pixel_range(line.y0, line.y1);
+                PixelRect {
+                    x_min,
+                    y_min,
+                    x_max,
+                    y_max,
+                }
+            })
+            .collect();
+        let circles = self
+            .circles
+            .iter()
+            .map(|circle| MarkCircle {
+                center_x: circle.center_x * pixel_per_mm,
+                center_y: circle.center_y * pixel_per_mm,
+                radius: circle.radius * pixel_per_mm,
+            })
+            .collect();
+
+        let text_scale =
+            ((SLUG_TEXT_HEIGHT_MM * pixel_per_mm / GLYPH_HEIGHT as f64).round() as i32).max(1);
+        RasterPrintMarks {
+            canvas_width,
+            content: PixelRect {
+                x_min: pixels(self.content_left_mm),
+                y_min: pixels(self.content_top_mm),
+                x_max: pixels(self.content_right_mm),
+                y_max: pixels(self.content_bottom_mm),
+            },
+            rects,
+            circles,
+            half_line_width: line_width / 2.0,
+            text: self.slug_text.chars().collect(),
+            text_x: pixels(self.slug_x_mm),
+            text_y: pixels(self.slug_y_mm),
+            text_scale,
+        }
+    }
+}
+
+/// Horizontal run of set glyph pixels. Coordinates are in glyph pixels with the origin at the top
+/// left corner of the text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GlyphPixelRun {
+    x: i32,
+    y: i32,
+    length: i32,
+}
+
+/// Returns the set pixels of the given text as runs so that vector outputs need one rectangle per
+/// run instead of one per pixel
+fn glyph_pixel_runs(text: &str) -> Vec<GlyphPixelRun> {
+    let mut result = Vec::new();
+    for (character_index, character) in text.chars().enumerate() {
+        let glyph_x = character_index as i32 * GLYPH_ADVANCE;
+        for (row, row_bits) in glyph(character).iter().enumerate() {
+            let mut run_start = None;
+            for column in 0..=GLYPH_WIDTH {
+                let is_set =
+                    column < GLYPH_WIDTH && (row_bits >> (GLYPH_WIDTH - 1 - column)) & 1 == 1;
+                match (is_set, run_start) {
+                    (true, None) => run_start = Some(column),
+                    (false, Some(start)) => {
+                        result.push(GlyphPixelRun {
+                            x: glyph_x + start,
+                            y: row as i32,
+                            length: column - start,
+                        });
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Half-open pixel rectangle
+#[derive(Debug, Clone, Copy)]
+struct PixelRect {
+    x_min: i32,
+    y_min: i32,
+    x_max: i32,
+    y_max: i32,
+}
+
+impl PixelRect {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        self.x_min <= x && x < self.x_max && self.y_min <= y && y < self.y_max
+    }
+}
+
+/// The print marks in pixels of a raster canvas
+pub struct RasterPrintMarks {
+    canvas_width: i32,
+    /// Everything outside of this is paper
+    content: PixelRect,
+    rects: Vec<PixelRect>,
+    /// In pixels
+    circles: Vec<MarkCircle>,
+    half_line_width: f64,
+    text: Vec<char>,
+    text_x: i32,
+    text_y: i32,
+    /// Size of a glyph pixel in canvas pixels
+    text_scale: i32,
+}
+
+impl RasterPrintMarks {
+    /// Overwrites everything outside of the content in the given part of the canvas with paper and
+    /// the marks with ink.
+    /// `start_index` is the index of the first pixel of the buffer inside the canvas.
+    pub fn draw(
+        &self,
+        canvas_buffer: &mut [u8],
+        start_index: usize,
+        paper_pixel: &[u8],
+        ink_pixel: &[u8],
+    ) {
+        for (index, pixel) in canvas_buffer
+            .chunks_exact_mut(paper_pixel.len())
+            .enumerate()
+        {
+            let x = ((index + start_index) % self.canvas_width as usize) as i32;
+            let y = ((index + start_index) / self.canvas_width as usize) as i32;
+            if !self.content.contains(x, y) {
+                if self.is_mark(x, y) {
+                    pixel.copy_from_slice(ink_pixel);
+                } else {
+                    pixel.copy_from_slice(paper_pixel);
+                }
+            }
+        }
+    }
+
+    fn is_mark(&self, x: i32, y: i32) -> bool {
+        if self.rects.iter().any(|rect| rect.contains(x, y)) {
+            return true;
+        }
+        let (pixel_center_x, pixel_center_y) = (x as f64 + 0.5, y as f64 + 0.5);
+        if self.circles.iter().any(|circle| {
+            let distance =
+                (pixel_center_x - circle.center_x).hypot(pixel_center_y - circle.center_y);
+            (distance - circle.radius).abs() <= self.half_line_width
+        }) {
+            return true;
+        }
+        self.is_text(x, y)
+    }
+
+    fn is_text(&self, x: i32, y: i32) -> bool {
+        let local_x = x - self.text_x;
+        let local_y = y - self.text_y;
+        if local_x < 0 || local_y < 0 || local_y >= GLYPH_HEIGHT * self.text_scale {
+            return false;
+        }
+        let character_index = (local_x / (GLYPH_ADVANCE * self.text_scale)) as usize;
+        let column = (local_x % (GLYPH_ADVANCE * self.text_scale)) / self.text_scale;
+        let row = local_y / self.text_scale;
+        match self.text.get(character_index) {
+            Some(&character) if column < GLYPH_WIDTH => {
+                let row_bits = glyph(character)[row as usize];
+                (row_bits >> (GLYPH_WIDTH - 1 - column)) & 1 == 1
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns the rows of a 5x7 pixel glyph for the slug line. The highest of the five bits is the
+/// leftmost pixel. Lowercase letters are drawn as uppercase letters.
+fn glyph(character: char) -> [u8; 7] {
+    match character.to_ascii_uppercase() {
+        ' ' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        'A' => [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'B' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+        'C' => [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'D' => [
+            0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+        ],
+        'E' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+        'F' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'G' => [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+        'H' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'I' => [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'J' => [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'K' => [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+        'L' => [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+        'M' => [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+        'N' => [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+        'O' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'P' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'Q' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+        'R' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+        'S' => [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+        'T' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'U' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'V' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'W' => [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+        'X' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+        'Y' => [
+            0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+        ],
+        'Z' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+        '0' => [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+        '1' => [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        '2' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '3' => [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+        '4' => [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+        '5' => [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+        '6' => [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+        '7' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+        '8' => [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+        '9' => [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+        '.' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+        ',' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+        ],
+        '-' => [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+        '_' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+        ':' => [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+        ],
+        '/' => [
+            0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+        ],
+        '(' => [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+        ')' => [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+        '|' => [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        '+' => [
+            0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+        ],
+        '=' => [
+            0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000,
+        ],
+        _ => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLYPH_TABLE: &str = " ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,-_:/()|+=?";
+
+    fn is_glyph_pixel(character: char, column: i32, row: i32) -> bool {
+        let row_bits = glyph(character)[row as usize];
+        (row_bits >> (GLYPH_WIDTH - 1 - column)) & 1 == 1
+    }
+
+    #[test]
+    fn glyph_rows_fit_into_glyph_width() {
+        for character in GLYPH_TABLE.chars() {
+            for row_bits in glyph(character).iter() {
+                assert!(*row_bits < 1 << GLYPH_WIDTH, "glyph '{}'", character);
+            }
+        }
+        assert_eq!(glyph('a'), glyph('A'));
+    }
+
+    #[test]
+    fn raster_text_renders_glyph_table() {
+        let text_scale = 2;
+        let text: Vec<char> = GLYPH_TABLE.chars().collect();
+        let text_width = text.len() as i32 * GLYPH_ADVANCE * text_scale;
+        let marks = RasterPrintMarks {
+            canvas_width: text_width + 10,
+            content: PixelRect {
+                x_min: 0,
+                y_min: 0,
+                x_max: 0,
+                y_max: 0,
+            },
+            rects: Vec::new(),
+            circles: Vec::new(),
+            half_line_width: 0.5,
+            text: text.clone(),
+            text_x: 3,
+            text_y: 4,
+            text_scale,
+        };
+
+        for y in 0..(GLYPH_HEIGHT + 2) * text_scale {
+            for x in 0..text_width + 10 {
+                let local_x = x - marks.text_x;
+                let local_y = y - marks.text_y;
+                let column = local_x.rem_euclid(GLYPH_ADVANCE * text_scale) / text_scale;
+                let row = local_y / text_scale;
+                let expected = local_x >= 0
+                    && local_y >= 0
+                    && row < GLYPH_HEIGHT
+                    && column < GLYPH_WIDTH
+                    && text
+                        .get((local_x / (GLYPH_ADVANCE * text_scale)) as usize)
+                        .map(|&character| is_glyph_pixel(character, column, row))
+                        .unwrap_or(false);
+                assert_eq!(marks.is_text(x, y), expected, "pixel {}x{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn pdf_glyph_runs_cover_glyph_table() {
+        let runs = glyph_pixel_runs(GLYPH_TABLE);
+        for (character_index, character) in GLYPH_TABLE.chars().enumerate() {
+            let glyph_x = character_index as i32 * GLYPH_ADVANCE;
+            for row in 0..GLYPH_HEIGHT {
+                for column in 0..GLYPH_ADVANCE {
+                    let covering_run_count = runs
+                        .iter()
+                        .filter(|run| {
+                            run.y == row
+                                && run.x <= glyph_x + column
+                                && glyph_x + column < run.x + run.length
+                        })
+                        .count();
+                    let expected = column < GLYPH_WIDTH && is_glyph_pixel(character, column, row);
+                    assert_eq!(
+                        covering_run_count, expected as usize,
+                        "glyph '{}' pixel {}x{}",
+                        character, column, row
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pdf_content_draws_slug_without_font() {
+        let marks = PrintMarks::new(100.0, 50.0, 3.0, 12.0, "Repeat 2x3".to_string());
+        let content = marks.pdf_content(72.0 / 25.4);
+        assert!(!content.contains(" Tf"));
+        assert!(!content.contains("BT"));
+        assert!(content.contains(" re\nf\nQ\n"));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::print_marks::PrintMarks;
+use crate::TilingGeometry;
+
+/// Where the svg gets the tile image from
+pub enum SvgTileSource<'a> {
+    /// The png encoded tile that is embedded as base64 data url
+    Embedded(&'a [u8]),
+    /// Path of a png file relative to the svg file
+    Linked(&'a str),
+}
+
+pub fn escape_xml_attribute(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Writes an svg file with the given physical size that is filled by a `<pattern>` element
+/// repeating the tile. The user units of the svg are millimeter so that the result can be resized
+/// and edited in vector graphics programs without losing the physical dimensions. With print marks
+/// the pattern leaves their margin free.
+pub fn write_pattern_svg(
+    svg_filepath: &str,
+    tile_source: SvgTileSource,
+    tiling: &TilingGeometry,
+    width_mm: f64,
+    height_mm: f64,
+    print_marks: Option<&PrintMarks>,
+) -> Result<(), String> {
+    let tile_href = match tile_source {
+        SvgTileSource::Embedded(png_data) => {
+            format!("data:image/png;base64,{}", base64::encode(png_data))
+        }
+        SvgTileSource::Linked(png_filepath) => escape_xml_attribute(png_filepath),
+    };
+
+    let (cell_width, cell_height, tile_positions) = tiling.cell_tile_positions();
+
+    // NOTE: Only the first image carries the image data, the others reference it
+    let mut pattern_content = format!(
+        "      <image id=\"tile\" x=\"0\" y=\"0\" width=\"{:.6}\" height=\"{:.6}\" \
+         preserveAspectRatio=\"none\" xlink:href=\"{}\"/>\n",
+        tiling.tile_width_mm, tiling.tile_height_mm, tile_href
+    );
+    for (x, y) in tile_positions.iter().skip(1) {
+        pattern_content += &format!(
+            "      <use xlink:href=\"#tile\" x=\"{:.6}\" y=\"{:.6}\"/>\n",
+            x, y
+        );
+    }
+
+    let margin = print_marks.map(PrintMarks::margin_mm).unwrap_or(0.0);
+    let print_marks_elements = print_marks
+        .map(PrintMarks::svg_elements)
+        .unwrap_or_default();
+
+    let svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+         version=\"1.1\" width=\"{width:.6}mm\" height=\"{height:.6}mm\" \
+         viewBox=\"0 0 {width:.6} {height:.6}\">\n\
+         \x20 <defs>\n\
+         \x20   <pattern id=\"repeat\" patternUnits=\"userSpaceOnUse\" x=\"{x:.6}\" y=\"{y:.6}\" \
+         width=\"{cell_width:.6}\" height=\"{cell_height:.6}\">\n\
+         {pattern_content}\
+         \x20   </pattern>\n\
+         \x20 </defs>\n\
+         \x20 <rect x=\"{margin:.6}\" y=\"{margin:.6}\" width=\"{fill_width:.6}\" \
+         height=\"{fill_height:.6}\" fill=\"url(#repeat)\"/>\n\
+         {print_marks_elements}\
+         </svg>\n",
+        width = width_mm,
+        height = height_mm,
+        margin = margin,
+        fill_width = width_mm - 2.0 * margin,
+        fill_height = height_mm - 2.0 * margin,
+        print_marks_elements = print_marks_elements,
+        x = -tiling.offset_x_mm,
+        y = -tiling.offset_y_mm,
+        cell_width = cell_width,
+        cell_height = cell_height,
+        pattern_content = pattern_content,
+    );
+
+    std::fs::write(svg_filepath, svg)
+        .map_err(|error| format!("Could not write svg file '{}' : {}", svg_filepath, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TileLayout;
+
+    fn write_test_svg(name: &str, tile_source: SvgTileSource, layout: TileLayout) -> String {
+        let filepath = std::env::temp_dir()
+            .join(format!("repeaty_test_{}.svg", name))
+            .to_string_lossy()
+            .to_string();
+        let tiling = TilingGeometry {
+            layout,
+            tile_width_mm: 40.0,
+            tile_height_mm: 30.0,
+            offset_x_mm: 5.0,
+            offset_y_mm: 2.5,
+        };
+        write_pattern_svg(&filepath, tile_source, &tiling, 210.0, 297.0, None).unwrap();
+        let result = std::fs::read_to_string(&filepath).unwrap();
+        std::fs::remove_file(&filepath).ok();
+        result
+    }
+
+    #[test]
+    fn svg_has_physical_size_and_millimeter_user_units() {
+        let svg = write_test_svg(
+            "size",
+            SvgTileSource::Embedded(&[1, 2, 3]),
+            TileLayout::Grid,
+        );
+        assert!(svg.contains("width=\"210.000000mm\" height=\"297.000000mm\""));
+        assert!(svg.contains("viewBox=\"0 0 210.000000 297.000000\""));
+        assert!(svg
+            .contains("x=\"-5.000000\" y=\"-2.500000\" width=\"40.000000\" height=\"30.000000\">"));
+        assert!(svg.contains("xlink:href=\"data:image/png;base64,AQID\""));
+        assert!(!svg.contains("<use "));
+    }
+
+    #[test]
+    fn offset_layouts_double_the_pattern_cell() {
+        let svg = write_test_svg(
+            "half_drop",
+            SvgTileSource::Embedded(&[]),
+            TileLayout::HalfDrop,
+        );
+        assert!(svg.contains("width=\"80.000000\" height=\"30.000000\">"));
+        assert!(svg.contains("<use xlink:href=\"#tile\" x=\"40.000000\" y=\"-15.000000\"/>"));
+        assert!(svg.contains("<use xlink:href=\"#tile\" x=\"40.000000\" y=\"15.000000\"/>"));
+
+        let svg = write_test_svg(
+            "half_brick",
+            SvgTileSource::Embedded(&[]),
+            TileLayout::HalfBrick,
+        );
+        assert!(svg.contains("width=\"40.000000\" height=\"60.000000\">"));
+        assert!(svg.contains("<use xlink:href=\"#tile\" x=\"-20.000000\" y=\"30.000000\"/>"));
+        assert!(svg.contains("<use xlink:href=\"#tile\" x=\"20.000000\" y=\"30.000000\"/>"));
+    }
+
+    #[test]
+    fn linked_tile_path_is_escaped() {
+        let svg = write_test_svg(
+            "linked",
+            SvgTileSource::Linked("a&b \"c\"__tile.png"),
+            TileLayout::Grid,
+        );
+        assert!(svg.contains("xlink:href=\"a&amp;b &quot;c&quot;__tile.png\""));
+        assert_eq!(escape_xml_attribute("<&\">"), "&lt;&amp;&quot;&gt;");
+    }
+}
@@ -0,0 +1,119 @@
+use crate::appdata;
+use crate::OutputFormat;
+
+use ct_lib::log;
+use ct_lib::serde_derive::{Deserialize, Serialize};
+
+use std::time::Instant;
+
+const TIMING_HISTORY_FILENAME: &str = "timing_history.json";
+const TIMING_HISTORY_MAX_ENTRY_COUNT_PER_FORMAT: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TimingEntry {
+    output_format: OutputFormat,
+    /// Amount of uncompressed image data that was encoded
+    processed_bytes: u64,
+    duration_seconds: f64,
+}
+
+/// How long previously created patterns took to write. It is used to estimate the duration of the
+/// next pattern.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimingHistory {
+    entries: Vec<TimingEntry>,
+}
+
+impl TimingHistory {
+    /// Returns an empty history if there is no previously saved history or if it is unreadable
+    pub fn load() -> TimingHistory {
+        appdata::load_json_or_default(TIMING_HISTORY_FILENAME, "timing history")
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = appdata::save_json(TIMING_HISTORY_FILENAME, "timing history", self) {
+            log::warn!("{}", error);
+        }
+    }
+
+    /// Adds a new entry and forgets the oldest entry of the same output format if there are too
+    /// many
+    pub fn add_entry(
+        &mut self,
+        output_format: OutputFormat,
+        processed_bytes: u64,
+        duration_seconds: f64,
+    ) {
+        self.entries.push(TimingEntry {
+            output_format,
+            processed_bytes,
+            duration_seconds,
+        });
+        let entry_count = self
+            .entries
+            .iter()
+            .filter(|entry| entry.output_format == output_format)
+            .count();
+        if entry_count > TIMING_HISTORY_MAX_ENTRY_COUNT_PER_FORMAT {
+            if let Some(oldest_index) = self
+                .entries
+                .iter()
+                .position(|entry| entry.output_format == output_format)
+            {
+                self.entries.remove(oldest_index);
+            }
+        }
+    }
+
+    /// Extrapolates the average throughput of the previous patterns with the same output format.
+    /// Returns `None` if no such pattern was created yet.
+    pub fn estimated_duration_seconds(
+        &self,
+        output_format: OutputFormat,
+        processed_bytes: u64,
+    ) -> Option<f64> {
+        let (total_bytes, total_seconds) = self
+            .entries
+            .iter()
+            .filter(|entry| entry.output_format == output_format)
+            .fold((0.0, 0.0), |(bytes, seconds), entry| {
+                (
+                    bytes + entry.processed_bytes as f64,
+                    seconds + entry.duration_seconds,
+                )
+            });
+        if total_bytes <= 0.0 {
+            return None;
+        }
+        Some(processed_bytes as f64 * total_seconds / total_bytes)
+    }
+}
+
+/// Measures how long a pattern takes to write. The duration is only added to the stored history
+/// if the pattern was written successfully.
+pub struct PatternTimer {
+    output_format: OutputFormat,
+    processed_bytes: u64,
+    start_time: Instant,
+}
+
+impl PatternTimer {
+    pub fn start(output_format: OutputFormat, processed_bytes: u64) -> PatternTimer {
+        PatternTimer {
+            output_format,
+            processed_bytes,
+            start_time: Instant::now(),
+        }
+    }
+
+    pub fn record(self) {
+        let mut history = TimingHistory::load();
+        history.add_entry(
+            self.output_format,
+            self.processed_bytes,
+            self.start_time.elapsed().as_secs_f64(),
+        );
+        history.save();
+    }
+}
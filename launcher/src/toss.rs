@@ -0,0 +1,351 @@
+use crate::pixel_buffer::{ColorType, OutputColorType, PixelBuffer};
+
+/// Number of candidates that are tried around a point before it is considered saturated
+const POISSON_CANDIDATE_COUNT: usize = 30;
+
+const SPLITMIX64_INCREMENT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Advances the given SplitMix64 state by one step and returns the mixed result
+pub fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(SPLITMIX64_INCREMENT);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Deterministic pseudo random numbers (SplitMix64) so that a seed always tosses the motifs the
+/// same way regardless of platform or library versions
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let result = splitmix64(self.state);
+        self.state = self.state.wrapping_add(SPLITMIX64_INCREMENT);
+        result
+    }
+
+    /// Returns a value in 0..1
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_index(&mut self, count: usize) -> usize {
+        (self.next_u64() % count as u64) as usize
+    }
+}
+
+/// A motif placed inside the cell. Positions are given in pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct TossedMotif {
+    pub motif_index: usize,
+    pub center_x: f64,
+    pub center_y: f64,
+    /// Clockwise rotation in radians
+    pub angle: f64,
+}
+
+/// Distance between two points on a cell whose opposite edges are connected
+fn wrapped_distance(a: (f64, f64), b: (f64, f64), width: f64, height: f64) -> f64 {
+    let dx = (a.0 - b.0).abs();
+    let dy = (a.1 - b.1).abs();
+    let dx = dx.min(width - dx);
+    let dy = dy.min(height - dy);
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Poisson-disk sampling after Bridson. Distances are measured across the cell edges so that no
+/// two points come closer than `min_distance` even when the cell is repeated.
+fn poisson_disk_points(
+    width: f64,
+    height: f64,
+    min_distance: f64,
+    random: &mut Random,
+) -> Vec<(f64, f64)> {
+    // NOTE: The grid cells divide the cell exactly and are small enough to hold one point at most.
+    //       Points closer than `min_distance` are then at most three grid cells apart.
+    let max_grid_cell_size = min_distance / std::f64::consts::SQRT_2;
+    let grid_width = (width / max_grid_cell_size).ceil().max(1.0) as usize;
+    let grid_height = (height / max_grid_cell_size).ceil().max(1.0) as usize;
+    let grid_cell_width = width / grid_width as f64;
+    let grid_cell_height = height / grid_height as f64;
+    let grid_position = |(x, y): (f64, f64)| {
+        (
+            ((x / grid_cell_width) as usize).min(grid_width - 1),
+            ((y / grid_cell_height) as usize).min(grid_height - 1),
+        )
+    };
+
+    let mut points: Vec<(f64, f64)> = Vec::new();
+    let mut grid: Vec<Option<usize>> = vec![None; grid_width * grid_height];
+    let is_free = |points: &[(f64, f64)], grid: &[Option<usize>], point: (f64, f64)| {
+        let (grid_x, grid_y) = grid_position(point);
+        for offset_y in -3..=3 {
+            for offset_x in -3..=3 {
+                let x = (grid_x as i64 + offset_x).rem_euclid(grid_width as i64) as usize;
+                let y = (grid_y as i64 + offset_y).rem_euclid(grid_height as i64) as usize;
+                if let Some(point_index) = grid[y * grid_width + x] {
+                    if wrapped_distance(points[point_index], point, width, height) < min_distance {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    };
+
+    let first_point = (random.next_f64() * width, random.next_f64() * height);
+    let (grid_x, grid_y) = grid_position(first_point);
+    grid[grid_y * grid_width + grid_x] = Some(0);
+    points.push(first_point);
+    let mut active_points = vec![0];
+
+    while !active_points.is_empty() {
+        let active_index = random.next_index(active_points.len());
+        let (center_x, center_y) = points[active_points[active_index]];
+        let mut found_candidate = false;
+        for _ in 0..POISSON_CANDIDATE_COUNT {
+            let radius = min_distance * (1.0 + random.next_f64());
+            let angle = 2.0 * std::f64::consts::PI * random.next_f64();
+            let candidate = (
+                (center_x + radius * angle.cos()).rem_euclid(width),
+                (center_y + radius * angle.sin()).rem_euclid(height),
+            );
+            if is_free(&points, &grid, candidate) {
+                let (grid_x, grid_y) = grid_position(candidate);
+                grid[grid_y * grid_width + grid_x] = Some(points.len());
+                active_points.push(points.len());
+                points.push(candidate);
+                found_candidate = true;
+                break;
+            }
+        }
+        if !found_candidate {
+            active_points.swap_remove(active_index);
+        }
+    }
+
+    points
+}
+
+/// Scatters the motifs with the given pixel sizes over the cell. Motifs are picked at random and
+/// kept `min_distance` apart from center to center. Motifs that are not rotated start at whole
+/// pixels so that they are drawn without resampling.
+pub fn toss_motifs(
+    cell_width: f64,
+    cell_height: f64,
+    min_distance: f64,
+    motif_sizes: &[(i32, i32)],
+    rotate: bool,
+    seed: u64,
+) -> Vec<TossedMotif> {
+    // NOTE: Snapping moves every center by up to half a pixel in both directions so two centers
+    //       can come closer by the diagonal of a pixel
+    let point_distance = if rotate {
+        min_distance
+    } else {
+        min_distance + std::f64::consts::SQRT_2
+    };
+    let snapped = |center: f64, size: i32| {
+        let half_size = 0.5 * size as f64;
+        (center - half_size).round() + half_size
+    };
+
+    let mut random = Random::new(seed);
+    poisson_disk_points(cell_width, cell_height, point_distance, &mut random)
+        .into_iter()
+        .map(|(center_x, center_y)| {
+            let motif_index = random.next_index(motif_sizes.len());
+            if rotate {
+                TossedMotif {
+                    motif_index,
+                    center_x,
+                    center_y,
+                    angle: 2.0 * std::f64::consts::PI * random.next_f64(),
+                }
+            } else {
+                let (motif_width, motif_height) = motif_sizes[motif_index];
+                TossedMotif {
+                    motif_index,
+                    center_x: snapped(center_x, motif_width),
+                    center_y: snapped(center_y, motif_height),
+                    angle: 0.0,
+                }
+            }
+        })
+        .collect()
+}
+
+/// The cell gets the highest bit depth of the motifs
+pub fn cell_bit_depth(motifs: &[PixelBuffer]) -> u8 {
+    motifs
+        .iter()
+        .map(|motif| motif.bit_depth)
+        .max()
+        .unwrap_or(8)
+}
+
+/// Returns the premultiplied color of the motif at the given position with bilinear filtering.
+/// Everything outside of the motif is transparent.
+fn premultiplied_sample(motif: &PixelBuffer, x: f64, y: f64) -> [f64; 4] {
+    let x = x - 0.5;
+    let y = y - 0.5;
+    let left = x.floor();
+    let top = y.floor();
+    let weight_x = x - left;
+    let weight_y = y - top;
+
+    let mut result = [0.0; 4];
+    for &(offset_x, offset_y, weight) in &[
+        (0, 0, (1.0 - weight_x) * (1.0 - weight_y)),
+        (1, 0, weight_x * (1.0 - weight_y)),
+        (0, 1, (1.0 - weight_x) * weight_y),
+        (1, 1, weight_x * weight_y),
+    ] {
+        let sample_x = left as i32 + offset_x;
+        let sample_y = top as i32 + offset_y;
+        if sample_x < 0 || sample_y < 0 || sample_x >= motif.width || sample_y >= motif.height {
+            continue;
+        }
+        let pixel = motif.pixel(sample_x, sample_y);
+        let alpha = motif.normalized_sample(pixel, 3);
+        for (channel, value) in result.iter_mut().take(3).enumerate() {
+            *value += weight * alpha * motif.normalized_sample(pixel, channel);
+        }
+        result[3] += weight * alpha;
+    }
+    result
+}
+
+/// Draws the tossed motifs onto a transparent RGBA cell with the bit depth of `cell_bit_depth`.
+/// Motifs that cross the cell edge continue on the opposite side so that the cell tiles
+/// seamlessly.
+pub fn render_toss_cell(
+    motifs: &[PixelBuffer],
+    cell_width: i32,
+    cell_height: i32,
+    tossed_motifs: &[TossedMotif],
+) -> Result<PixelBuffer, String> {
+    let motifs = motifs
+        .iter()
+        .map(|motif| motif.converted(OutputColorType::Rgba))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut result = PixelBuffer::new(
+        cell_width as u32,
+        cell_height as u32,
+        ColorType::Rgba,
+        cell_bit_depth(&motifs),
+    );
+    let bytes_per_pixel = result.bytes_per_pixel();
+    let mut data = std::mem::take(&mut result.data);
+    for tossed in tossed_motifs {
+        let motif = &motifs[tossed.motif_index];
+        let (sin, cos) = tossed.angle.sin_cos();
+        let motif_width = motif.width as f64;
+        let motif_height = motif.height as f64;
+        let half_extent_x = 0.5 * (motif_width * cos.abs() + motif_height * sin.abs());
+        let half_extent_y = 0.5 * (motif_width * sin.abs() + motif_height * cos.abs());
+
+        let begin_x = (tossed.center_x - half_extent_x).floor() as i32;
+        let end_x = (tossed.center_x + half_extent_x).ceil() as i32;
+        let begin_y = (tossed.center_y - half_extent_y).floor() as i32;
+        let end_y = (tossed.center_y + half_extent_y).ceil() as i32;
+        for y in begin_y..end_y {
+            for x in begin_x..end_x {
+                // Rotate the pixel center back into the coordinate system of the motif
+                let dx = x as f64 + 0.5 - tossed.center_x;
+                let dy = y as f64 + 0.5 - tossed.center_y;
+                let motif_x = cos * dx + sin * dy + 0.5 * motif_width;
+                let motif_y = -sin * dx + cos * dy + 0.5 * motif_height;
+                let source = premultiplied_sample(motif, motif_x, motif_y);
+                if source[3] <= 0.0 {
+                    continue;
+                }
+
+                // Blend the source over the cell
+                let wrapped_x = x.rem_euclid(cell_width) as usize;
+                let wrapped_y = y.rem_euclid(cell_height) as usize;
+                let index = (wrapped_y * cell_width as usize + wrapped_x) * bytes_per_pixel;
+                let target = &mut data[index..(index + bytes_per_pixel)];
+                let target_weight = result.normalized_sample(target, 3) * (1.0 - source[3]);
+                let alpha = source[3] + target_weight;
+                for (channel, &source_value) in source.iter().take(3).enumerate() {
+                    let premultiplied =
+                        source_value + target_weight * result.normalized_sample(target, channel);
+                    result.set_normalized_sample(target, channel, premultiplied / alpha);
+                }
+                result.set_normalized_sample(target, 3, alpha);
+            }
+        }
+    }
+    result.data = data;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_matches_splitmix64_reference() {
+        let mut random = Random::new(0);
+        assert_eq!(random.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(random.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+        assert_eq!(splitmix64(0), 0xE220_A839_7B1D_CDAF);
+    }
+
+    #[test]
+    fn poisson_disk_points_keep_distance_across_the_wrap() {
+        let (width, height, min_distance) = (100.0, 60.0, 13.0);
+        for seed in 0..10 {
+            let points = poisson_disk_points(width, height, min_distance, &mut Random::new(seed));
+            assert!(points.len() > 10);
+            for (index, &a) in points.iter().enumerate() {
+                assert!(0.0 <= a.0 && a.0 < width && 0.0 <= a.1 && a.1 < height);
+                for &b in &points[(index + 1)..] {
+                    // Compare against all neighboring copies of the cell
+                    for &(shift_x, shift_y) in
+                        &[(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+                    {
+                        let dx = (a.0 - b.0).abs();
+                        let dy = (a.1 - b.1).abs();
+                        let distance = (dx - shift_x).hypot(dy - shift_y);
+                        assert!(distance >= min_distance, "{:?} {:?}", a, b);
+                    }
+                    assert!(wrapped_distance(a, b, width, height) >= min_distance);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unrotated_motifs_are_copied_without_resampling() {
+        let mut motif = PixelBuffer::new(3, 2, ColorType::Rgba, 8);
+        for (index, sample) in motif.data.iter_mut().enumerate() {
+            *sample = if index % 4 == 3 { 255 } else { 7 * index as u8 };
+        }
+        let tossed_motifs = toss_motifs(40.0, 30.0, 8.0, &[(3, 2)], false, 3);
+        assert!(!tossed_motifs.is_empty());
+        let cell = render_toss_cell(&[motif.clone()], 40, 30, &tossed_motifs).unwrap();
+
+        for tossed in &tossed_motifs {
+            let left = tossed.center_x - 1.5;
+            let top = tossed.center_y - 1.0;
+            assert_eq!(left, left.round());
+            assert_eq!(top, top.round());
+            for y in 0..2 {
+                for x in 0..3 {
+                    let cell_x = (left as i32 + x).rem_euclid(40);
+                    let cell_y = (top as i32 + y).rem_euclid(30);
+                    assert_eq!(cell.pixel(cell_x, cell_y), motif.pixel(x, y));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::motifs::{self, MotifLayout};
+use crate::pixel_buffer::PixelBuffer;
+use crate::toss::splitmix64;
+
+/// Symmetry transforms that turn a tile into another tile of the same size. The transforms that
+/// swap the axes only keep the size of square tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileTransform {
+    Identity,
+    MirrorHorizontal,
+    MirrorVertical,
+    Rotate180,
+    /// Clockwise
+    Rotate90,
+    Rotate270,
+    /// Mirrors at the diagonal from the top left to the bottom right corner
+    Transpose,
+    /// Mirrors at the diagonal from the top right to the bottom left corner
+    AntiTranspose,
+}
+
+impl TileTransform {
+    /// Returns the position inside the original tile that is shown at the given position of the
+    /// transformed tile
+    fn source_position(self, x: i32, y: i32, width: i32, height: i32) -> (i32, i32) {
+        match self {
+            TileTransform::Identity => (x, y),
+            TileTransform::MirrorHorizontal => (width - 1 - x, y),
+            TileTransform::MirrorVertical => (x, height - 1 - y),
+            TileTransform::Rotate180 => (width - 1 - x, height - 1 - y),
+            TileTransform::Rotate90 => (y, width - 1 - x),
+            TileTransform::Rotate270 => (height - 1 - y, x),
+            TileTransform::Transpose => (y, x),
+            TileTransform::AntiTranspose => (height - 1 - y, width - 1 - x),
+        }
+    }
+}
+
+const TRANSFORMS_NONE: &[TileTransform] = &[TileTransform::Identity];
+// NOTE: Mirroring in both directions at once is the same as turning the tile upside down
+const TRANSFORMS_MIRROR: &[TileTransform] = &[
+    TileTransform::Identity,
+    TileTransform::MirrorHorizontal,
+    TileTransform::MirrorVertical,
+    TileTransform::Rotate180,
+];
+const TRANSFORMS_ROTATE: &[TileTransform] = &[TileTransform::Identity, TileTransform::Rotate180];
+const TRANSFORMS_ROTATE_SQUARE: &[TileTransform] = &[
+    TileTransform::Identity,
+    TileTransform::Rotate90,
+    TileTransform::Rotate180,
+    TileTransform::Rotate270,
+];
+const TRANSFORMS_ALL_SQUARE: &[TileTransform] = &[
+    TileTransform::Identity,
+    TileTransform::MirrorHorizontal,
+    TileTransform::MirrorVertical,
+    TileTransform::Rotate180,
+    TileTransform::Rotate90,
+    TileTransform::Rotate270,
+    TileTransform::Transpose,
+    TileTransform::AntiTranspose,
+];
+
+/// Picks a variant tile and a transform for every tile of the pattern. The choice only depends on
+/// the seed and the position of the tile in the repeat, so a pattern looks the same no matter in
+/// which order or by how many threads its pixels are composited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TileVariation {
+    seed: u64,
+    /// Number of tiles stacked on top of each other in the tile sheet. The first one is the input
+    /// image.
+    tile_count: i32,
+    transforms: &'static [TileTransform],
+}
+
+impl TileVariation {
+    /// Rotations by 90 degrees are only used for square tiles as they would not fit into the cell
+    /// otherwise
+    pub fn new(
+        seed: u64,
+        tile_count: usize,
+        mirror: bool,
+        rotate: bool,
+        is_square: bool,
+    ) -> TileVariation {
+        let transforms = match (mirror, rotate, is_square) {
+            (false, false, _) => TRANSFORMS_NONE,
+            (true, false, _) | (true, true, false) => TRANSFORMS_MIRROR,
+            (false, true, false) => TRANSFORMS_ROTATE,
+            (false, true, true) => TRANSFORMS_ROTATE_SQUARE,
+            (true, true, true) => TRANSFORMS_ALL_SQUARE,
+        };
+        TileVariation {
+            seed,
+            tile_count: tile_count.max(1) as i32,
+            transforms,
+        }
+    }
+
+    /// Number of tiles in the tile sheet
+    pub fn tile_count(&self) -> i32 {
+        self.tile_count.max(1)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.tile_count > 1 || self.transforms.len() > 1
+    }
+
+    /// Returns the position inside the tile sheet that is shown at the given position of the tile
+    /// in the given column and row of the repeat
+    pub fn sheet_position(
+        &self,
+        column: i32,
+        row: i32,
+        x: i32,
+        y: i32,
+        tile_width: i32,
+        tile_height: i32,
+    ) -> (i32, i32) {
+        if !self.is_active() {
+            return (x, y);
+        }
+        let hash = tile_hash(self.seed, column, row);
+        let tile_index = (hash % self.tile_count() as u64) as i32;
+        let transform = self.transforms[((hash >> 32) % self.transforms.len() as u64) as usize];
+        let (source_x, source_y) = transform.source_position(x, y, tile_width, tile_height);
+        (source_x, tile_index * tile_height + source_y)
+    }
+}
+
+/// Mixes the seed with the tile position
+fn tile_hash(seed: u64, column: i32, row: i32) -> u64 {
+    let position = ((row as u32 as u64) << 32) | column as u32 as u64;
+    splitmix64(splitmix64(seed) ^ position)
+}
+
+/// Stacks the input image and its variants on top of each other into one tile sheet. All tiles
+/// need the same size.
+pub fn tile_sheet(tiles: &[PixelBuffer]) -> Result<PixelBuffer, String> {
+    let layout = MotifLayout {
+        rows: (0..tiles.len()).map(|index| vec![index]).collect(),
+    };
+    motifs::combine_motifs(tiles, &layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_position_transforms_corners() {
+        let (width, height) = (3, 3);
+        let top_left = |transform: TileTransform| transform.source_position(0, 0, width, height);
+        assert_eq!(top_left(TileTransform::Identity), (0, 0));
+        assert_eq!(top_left(TileTransform::MirrorHorizontal), (2, 0));
+        assert_eq!(top_left(TileTransform::MirrorVertical), (0, 2));
+        assert_eq!(top_left(TileTransform::Rotate180), (2, 2));
+        // Turning clockwise brings the bottom left corner to the top left
+        assert_eq!(top_left(TileTransform::Rotate90), (0, 2));
+        assert_eq!(top_left(TileTransform::Rotate270), (2, 0));
+        assert_eq!(top_left(TileTransform::Transpose), (0, 0));
+        assert_eq!(top_left(TileTransform::AntiTranspose), (2, 2));
+        assert_eq!(
+            TileTransform::Transpose.source_position(2, 0, width, height),
+            (0, 2)
+        );
+    }
+
+    #[test]
+    fn source_position_permutes_the_tile() {
+        let check = |transforms: &[TileTransform], width: i32, height: i32| {
+            for &transform in transforms {
+                let mut positions = Vec::new();
+                for y in 0..height {
+                    for x in 0..width {
+                        let (source_x, source_y) = transform.source_position(x, y, width, height);
+                        assert!(0 <= source_x && source_x < width, "{:?}", transform);
+                        assert!(0 <= source_y && source_y < height, "{:?}", transform);
+                        positions.push((source_x, source_y));
+                    }
+                }
+                positions.sort();
+                positions.dedup();
+                assert_eq!(
+                    positions.len(),
+                    (width * height) as usize,
+                    "{:?}",
+                    transform
+                );
+            }
+        };
+        check(TRANSFORMS_ALL_SQUARE, 4, 4);
+        check(TRANSFORMS_MIRROR, 5, 3);
+        check(TRANSFORMS_ROTATE, 5, 3);
+
+        // Turning by 90 degrees in both directions cancels out
+        let (x, y) = TileTransform::Rotate90.source_position(1, 3, 4, 4);
+        assert_eq!(TileTransform::Rotate270.source_position(x, y, 4, 4), (1, 3));
+    }
+
+    #[test]
+    fn sheet_position_picks_one_tile_per_repeat() {
+        let (width, height) = (4, 3);
+        assert_eq!(
+            TileVariation::default().sheet_position(5, 7, 1, 2, width, height),
+            (1, 2)
+        );
+
+        let variation = TileVariation::new(42, 3, true, true, false);
+        let mut used_tiles = [false; 3];
+        for row in -3..3 {
+            for column in -3..3 {
+                let (_, first_y) = variation.sheet_position(column, row, 0, 0, width, height);
+                let tile_index = first_y / height;
+                used_tiles[tile_index as usize] = true;
+                for y in 0..height {
+                    for x in 0..width {
+                        let (sheet_x, sheet_y) =
+                            variation.sheet_position(column, row, x, y, width, height);
+                        assert!(0 <= sheet_x && sheet_x < width);
+                        assert_eq!(sheet_y / height, tile_index);
+                        assert_eq!(
+                            variation.sheet_position(column, row, x, y, width, height),
+                            (sheet_x, sheet_y)
+                        );
+                    }
+                }
+            }
+        }
+        assert!(used_tiles.iter().all(|&is_used| is_used));
+    }
+}